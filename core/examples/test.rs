@@ -1,25 +1,37 @@
-use comfy_table::Table;
-use planner_core::{FachGewichtung, generate};
-use std::{fs::File, io::Read};
-use tracing::{Level, level_filters::LevelFilter};
-use willi::WilliStundenplan;
+use planner_core::{
+  CallbackObserver, FachGewichtung, Objective, Praktikant, SolveOptions, SolverEvent, generate,
+};
+use std::{env, fs::File, io::Read, time::Duration};
+use tracing::level_filters::LevelFilter;
+use willi::{SyntheticParams, WilliStundenplan};
 
 fn main() {
-  let mut raw_source = vec![];
-  File::open("/Users/jake/Downloads/AKG2025.BAL")
-    .unwrap()
-    .read_to_end(&mut raw_source)
-    .unwrap();
-  let reencoded_source: String = raw_source.iter().map(|&c| c as char).collect();
-
-  let (plan, errors) = WilliStundenplan::parse(&reencoded_source[..]);
-
-  if !errors.is_empty() {
-    eprintln!("Encountered parse errors:");
-    for error in errors {
-      println!("{}", error.1);
+  // Ohne Pfad wird ein synthetischer Plan verwendet
+  let plan = match env::args().nth(1) {
+    Some(path) => {
+      let mut raw_source = vec![];
+      File::open(path)
+        .unwrap()
+        .read_to_end(&mut raw_source)
+        .unwrap();
+      let reencoded_source: String = raw_source.iter().map(|&c| c as char).collect();
+
+      let (plan, errors) = WilliStundenplan::parse(&reencoded_source[..]);
+
+      if !errors.is_empty() {
+        eprintln!("Encountered parse errors:");
+        for error in errors {
+          println!("{}", error.1);
+        }
+      }
+
+      plan
     }
-  }
+    None => WilliStundenplan::synthetic(&SyntheticParams {
+      klassen: 6,
+      ..Default::default()
+    }),
+  };
 
   tracing_subscriber::fmt()
     .with_max_level(LevelFilter::INFO)
@@ -27,19 +39,58 @@ fn main() {
 
   let solution = generate(
     &plan,
-    &vec![
-      FachGewichtung {
-        kuerzel: "M".to_string(),
-        gewicht: 1.0,
-      },
-      FachGewichtung {
-        kuerzel: "D".to_string(),
-        gewicht: 1.0,
-      },
-    ],
+    &Praktikant {
+      subjects: ["M", "D"]
+        .into_iter()
+        .map(|kuerzel| FachGewichtung {
+          kuerzel: kuerzel.to_string(),
+          gewicht: 1.0,
+        })
+        .collect(),
+      ..Default::default()
+    },
+    &Objective::default(),
+    &SolveOptions {
+      time_limit: Some(Duration::from_secs(30)),
+      ..Default::default()
+    },
+    &mut CallbackObserver(|event| match event {
+      SolverEvent::Solution { cost, .. } => println!("Neue Lösung mit Kosten {cost}"),
+      SolverEvent::Finished { visited, .. } => println!("{visited} Knoten besucht"),
+      SolverEvent::Progress { .. } => {}
+    }),
   );
 
-  println!("\nLösung: {solution:?}");
+  match solution {
+    Ok(result) => {
+      println!(
+        "\nLösung ({}): {:?}",
+        if result.optimal {
+          "optimal"
+        } else {
+          "nicht optimal"
+        },
+        result.solution
+      );
+
+      let breakdown = &result.breakdown;
+      println!(
+        "Kosten {:.2}: {} Klassen ({}) = {:.2}",
+        breakdown.total,
+        breakdown.classes.count,
+        breakdown.classes.kuerzel.join(", "),
+        breakdown.classes.cost
+      );
+      for subject in &breakdown.subjects {
+        println!(
+          "  {}: {} von {:.1} Stunden = {:.2}",
+          subject.kuerzel, subject.actual, subject.target, subject.cost
+        );
+      }
+      println!("  {} leere Stunden", breakdown.empty_slots.len());
+    }
+    Err(error) => eprintln!("\nFehler: {error}"),
+  }
 
   // let mut table = Table::new();
 
@@ -0,0 +1,174 @@
+//! Weitere Lösungen neben der besten, damit Praktikanten zwischen mehreren Plänen wählen können.
+//!
+//! Die Alternativen werden nacheinander bestimmt: Jede ist die beste Lösung, die sich von allen
+//! vorher gefundenen in mindestens [`SolveOptions::min_difference`](crate::SolveOptions) Stunden
+//! unterscheidet. Mit einem Mindestabstand von 1 sind das genau die k besten Lösungen, mit einem
+//! größeren eine Auswahl deutlich verschiedener Pläne.
+
+use serde::Serialize;
+
+use crate::{CostBreakdown, Progress, Solution, SolverObserver, TraineePlan};
+
+/// Eine weitere Lösung im Ergebnis von [`generate`](crate::generate)
+#[derive(Debug, Clone, Serialize)]
+pub struct Alternative {
+  /// Siehe [`SolveResult::solution`](crate::SolveResult)
+  pub solution: Vec<Vec<Option<usize>>>,
+  pub cost: f64,
+  pub breakdown: CostBreakdown,
+}
+
+/// Eine weitere Lösung im Ergebnis von [`generate_many`](crate::generate_many)
+#[derive(Debug, Clone, Serialize)]
+pub struct MultiAlternative {
+  /// Ein Plan je Praktikant, siehe [`MultiSolveResult::plans`](crate::MultiSolveResult)
+  pub plans: Vec<TraineePlan>,
+  /// Summe der Kosten aller Pläne
+  pub cost: f64,
+}
+
+/// Bereits gefundene Lösungen, von denen sich jede weitere unterscheiden muss
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Excluded<'a> {
+  // [n][trainee] = Belegung
+  solutions: &'a [Vec<Solution>],
+  min_difference: usize,
+}
+
+impl<'a> Excluded<'a> {
+  pub(crate) fn new(solutions: &'a [Vec<Solution>], min_difference: usize) -> Self {
+    Excluded {
+      solutions,
+      min_difference: min_difference.max(1),
+    }
+  }
+
+  /// Die ausgeschlossenen Lösungen, jeweils mit einer Belegung je Praktikant
+  pub(crate) fn solutions(&self) -> &'a [Vec<Solution>] {
+    self.solutions
+  }
+
+  pub(crate) fn min_difference(&self) -> usize {
+    self.min_difference
+  }
+
+  /// `assignments` (`[trainee][slot] = Option<pl_index>`) unterscheidet sich von jeder
+  /// ausgeschlossenen Lösung in mindestens `min_difference` Stunden
+  pub(crate) fn admits<'b>(&self, assignments: impl Fn(usize) -> &'b [Option<usize>]) -> bool {
+    self.solutions.iter().all(|excluded| {
+      let difference: usize = excluded
+        .iter()
+        .enumerate()
+        .map(|(trainee, solution)| {
+          solution
+            .assignments
+            .iter()
+            .zip(assignments(trainee))
+            .filter(|(a, b)| a != b)
+            .count()
+        })
+        .sum();
+      difference >= self.min_difference
+    })
+  }
+}
+
+/// Leitet während der Suche nach Alternativen nur den Fortschritt weiter, deren Zwischenlösungen
+/// sind keine Verbesserung der besten Lösung
+pub(crate) struct ProgressOnly<'a>(pub(crate) &'a mut dyn SolverObserver);
+
+impl SolverObserver for ProgressOnly<'_> {
+  fn progress(&mut self, progress: &Progress) {
+    self.0.progress(progress);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use willi::WilliStundenplan;
+
+  use crate::{
+    Backend, Capacity, MultiSolveResult, NoopObserver, Objective, SolveOptions, generate_many,
+    testing::{plan, trainee},
+  };
+
+  const BACKENDS: [Backend; 3] = [Backend::Search, Backend::Milp, Backend::LocalSearch];
+
+  fn solve(
+    plan: &WilliStundenplan,
+    backend: Backend,
+    alternatives: usize,
+    min_difference: usize,
+  ) -> MultiSolveResult {
+    generate_many(
+      plan,
+      &[trainee("A"), trainee("B")],
+      &Capacity::default(),
+      &Objective::default(),
+      &SolveOptions {
+        backend,
+        alternatives,
+        min_difference,
+        ..Default::default()
+      },
+      &mut NoopObserver,
+    )
+    .unwrap()
+  }
+
+  /// Anzahl der Stunden über alle Praktikanten, in denen sich zwei Lösungen unterscheiden
+  fn difference(a: &[Vec<Vec<Option<usize>>>], b: &[Vec<Vec<Option<usize>>>]) -> usize {
+    a.iter()
+      .flatten()
+      .flatten()
+      .zip(b.iter().flatten().flatten())
+      .filter(|(a, b)| a != b)
+      .count()
+  }
+
+  /// Alle Lösungen unterscheiden sich paarweise in mindestens `min_difference` Stunden, und keine
+  /// Alternative ist günstiger als die beste Lösung
+  #[test]
+  fn pairwise_different() {
+    let plan = plan(1);
+    for min_difference in [1, 4] {
+      for backend in BACKENDS {
+        let result = solve(&plan, backend, 3, min_difference);
+        let context = format!("{backend:?}, min_difference {min_difference}");
+        assert_eq!(result.alternatives.len(), 3, "{context}");
+
+        let solutions: Vec<Vec<_>> = std::iter::once(&result.plans)
+          .chain(result.alternatives.iter().map(|a| &a.plans))
+          .map(|plans| plans.iter().map(|p| p.solution.clone()).collect())
+          .collect();
+        for (i, a) in solutions.iter().enumerate() {
+          for b in &solutions[i + 1..] {
+            assert!(difference(a, b) >= min_difference, "{context}");
+          }
+        }
+
+        if backend != Backend::LocalSearch {
+          let costs: Vec<f64> = std::iter::once(result.cost)
+            .chain(result.alternatives.iter().map(|a| a.cost))
+            .collect();
+          assert!(
+            costs.windows(2).all(|w| w[0] <= w[1] + 1e-9),
+            "{context}: {costs:?}"
+          );
+        }
+      }
+    }
+  }
+
+  /// Gibt es keine weitere Lösung mit genug Abstand, endet die Suche ohne Fehler und mit weniger
+  /// Alternativen als verlangt
+  #[test]
+  fn stops_without_alternative() {
+    let plan = plan(2);
+    for backend in BACKENDS {
+      // Zwei Praktikanten mit je 10 Stunden können sich nicht in mehr als 20 Stunden unterscheiden
+      let result = solve(&plan, backend, 5, 21);
+      assert!(result.alternatives.is_empty(), "{backend:?}");
+    }
+  }
+}
@@ -0,0 +1,100 @@
+//! Zeiten, zu denen ein Praktikant an der Schule ist.
+//!
+//! Praktikanten haben Seminartage an der Universität und feste Fahrzeiten. Stunden außerhalb ihrer
+//! [`Availability`] werden entfernt, bevor der Plan des Praktikanten aufgestellt wird, als fände
+//! dort kein Unterricht statt. Die angestrebte Stundenzahl je Fach bezieht sich dann nur auf die
+//! verfügbaren Stunden.
+
+use serde::{Deserialize, Serialize};
+use willi::{WilliStundenplan, uhrzeit_minuten};
+
+use crate::{PlannerError, SlotRef, Timeslot};
+
+/// Verfügbarkeit eines Praktikanten. Ohne Angaben ist er in jeder Unterrichtsstunde verfügbar.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Availability {
+  /// Tage, an denen der Praktikant nicht an der Schule ist, als Zeile in der Ausgabe von
+  /// [`generate`](crate::generate)
+  pub unavailable_days: Vec<usize>,
+  /// Früheste Ankunft als Uhrzeit wie „08:30“. Stunden, die vorher beginnen, entfallen.
+  pub earliest_arrival: Option<String>,
+  /// Späteste Abfahrt als Uhrzeit. Stunden, die danach enden, entfallen.
+  pub latest_departure: Option<String>,
+  /// Einzelne gesperrte Stunden, z.B. für einen Termin an der Universität
+  pub blocked_slots: Vec<SlotRef>,
+}
+
+impl Availability {
+  /// Prüft die Uhrzeiten. Eine Ankunft nach der Abfahrt ließe keine Stunde übrig.
+  pub(crate) fn validate(&self) -> Result<(), PlannerError> {
+    let (arrival, departure) = self.times()?;
+    if let (Some(arrival), Some(departure)) = (arrival, departure)
+      && arrival > departure
+    {
+      return Err(PlannerError::InvalidOptions(format!(
+        "Ankunft {} liegt nach der Abfahrt {}",
+        self.earliest_arrival.as_deref().unwrap_or_default(),
+        self.latest_departure.as_deref().unwrap_or_default()
+      )));
+    }
+
+    Ok(())
+  }
+
+  /// Früheste Ankunft und späteste Abfahrt in Minuten nach Mitternacht
+  fn times(&self) -> Result<(Option<u32>, Option<u32>), PlannerError> {
+    let parse = |time: &Option<String>| {
+      time
+        .as_deref()
+        .map(|text| {
+          uhrzeit_minuten(text)
+            .ok_or_else(|| PlannerError::InvalidOptions(format!("Ungültige Uhrzeit „{text}“")))
+        })
+        .transpose()
+    };
+
+    Ok((
+      parse(&self.earliest_arrival)?,
+      parse(&self.latest_departure)?,
+    ))
+  }
+
+  /// `[slot]` = Praktikant ist in dieser Stunde verfügbar. Stunden ohne lesbare Uhrzeit im Plan
+  /// werden nur über Tage und gesperrte Stunden ausgeschlossen.
+  pub(crate) fn slots(
+    &self,
+    plan: &WilliStundenplan,
+    timeslots: &[Timeslot],
+  ) -> Result<Vec<bool>, PlannerError> {
+    let (arrival, departure) = self.times()?;
+
+    Ok(
+      timeslots
+        .iter()
+        .map(|timeslot| {
+          let Timeslot {
+            stunde,
+            day,
+            period,
+            ..
+          } = *timeslot;
+          let stunde = plan.stunde(stunde);
+
+          !self.unavailable_days.contains(&day)
+            && !self.blocked_slots.contains(&SlotRef { day, period })
+            && arrival.is_none_or(|arrival| {
+              stunde
+                .and_then(|s| s.beginn_minuten())
+                .is_none_or(|start| start >= arrival)
+            })
+            && departure.is_none_or(|departure| {
+              stunde
+                .and_then(|s| s.ende_minuten())
+                .is_none_or(|end| end <= departure)
+            })
+        })
+        .collect(),
+    )
+  }
+}
@@ -0,0 +1,433 @@
+//! Untere Schranke für die Kosten, die von einer Teilbelegung aus noch erreichbar sind.
+//!
+//! [`Problem::search`](crate::Problem) verwirft einen Zweig, sobald diese Schranke nicht mehr
+//! unter den Kosten der besten bekannten Lösung liegt. Die Schranke unterschätzt die
+//! tatsächlichen Kosten nie, die gefundene Lösung bleibt also optimal.
+
+use std::collections::HashMap;
+
+use ndarray::Array2;
+
+use crate::trainee::Trainee;
+
+/// Für die Schranke vorberechnete Informationen über die noch offenen Stunden
+#[derive(Debug)]
+pub(crate) struct LowerBound {
+  // [slot] = Klassen, die in dieser Stunde belegt werden können
+  slot_classes: Vec<Vec<usize>>,
+  // [slot] = Anzahl der Stunden ab `slot`, die belegt werden müssen
+  forced: Vec<usize>,
+  // [slot][subject] = Anzahl der Stunden ab `slot`, in denen nur dieses Fach möglich ist
+  min_counts: Array2<usize>,
+  // [slot][subject] = Anzahl der Stunden ab `slot`, in denen dieses Fach möglich ist
+  max_counts: Array2<usize>,
+  // [subject] = angestrebte Anzahl Stunden
+  targets: Vec<f64>,
+  // Fächer absteigend nach `targets`
+  order: Vec<usize>,
+}
+
+impl LowerBound {
+  pub(crate) fn new(
+    schedule: &Array2<Vec<(usize, usize)>>,
+    targets: &[f64],
+    time_slots: usize,
+  ) -> Self {
+    let subjects = targets.len();
+
+    let slot_classes: Vec<Vec<usize>> = (0..time_slots)
+      .map(|slot| {
+        (0..schedule.nrows())
+          .filter(|&class| !schedule[[class, slot]].is_empty())
+          .collect()
+      })
+      .collect();
+
+    let mut forced = vec![0; time_slots + 1];
+    let mut min_counts = Array2::zeros((time_slots + 1, subjects));
+    let mut max_counts = Array2::zeros((time_slots + 1, subjects));
+
+    // Von hinten aufsummieren
+    for slot in (0..time_slots).rev() {
+      let mut available = vec![false; subjects];
+      for &(subject, _) in schedule.column(slot).iter().flatten() {
+        available[subject] = true;
+      }
+      let num_available = available.iter().filter(|&&a| a).count();
+
+      forced[slot] = forced[slot + 1] + usize::from(num_available > 0);
+      for subject in 0..subjects {
+        min_counts[[slot, subject]] =
+          min_counts[[slot + 1, subject]] + usize::from(num_available == 1 && available[subject]);
+        max_counts[[slot, subject]] =
+          max_counts[[slot + 1, subject]] + usize::from(available[subject]);
+      }
+    }
+
+    let targets = targets.to_vec();
+    let mut order: Vec<usize> = (0..subjects).collect();
+    order.sort_by(|&a, &b| targets[b].total_cmp(&targets[a]));
+
+    LowerBound {
+      slot_classes,
+      forced,
+      min_counts,
+      max_counts,
+      targets,
+      order,
+    }
+  }
+
+  /// Anzahl der Stunden ab `slot`, in denen `subject` möglich ist
+  pub(crate) fn possible(&self, slot: usize, subject: usize) -> usize {
+    self.max_counts[[slot, subject]]
+  }
+
+  /// Mindestanzahl an Klassen, die in den Stunden ab `slot` zusätzlich zu `used_classes`
+  /// hinzukommen müssen.
+  ///
+  /// Jede Stunde mit möglichen Klassen wird belegt, bis auf höchstens `slack` Stunden. Hat eine
+  /// Stunde keine bereits verwendete Klasse zur Auswahl, kommt mindestens eine neue hinzu. Gezählt
+  /// werden nur Stunden, deren Klassen untereinander disjunkt sind, sonst könnte eine Klasse
+  /// mehrere davon abdecken.
+  pub(crate) fn new_classes(
+    &self,
+    slot: usize,
+    used_classes: &[usize],
+    slack: usize,
+    covered: &mut [bool],
+  ) -> usize {
+    covered.fill(false);
+    for &class in used_classes {
+      covered[class] = true;
+    }
+
+    let mut count: usize = 0;
+    for classes in &self.slot_classes[slot..] {
+      if classes.is_empty() || classes.iter().any(|&c| covered[c]) {
+        continue;
+      }
+      count += 1;
+      for &class in classes {
+        covered[class] = true;
+      }
+    }
+
+    count.saturating_sub(slack)
+  }
+
+  /// Kleinste erreichbare Abweichung von der Gewichtung (noch ohne das Gewicht aus dem
+  /// [`Objective`](crate::Objective)), wenn die Stunden ab `slot` noch offen sind.
+  ///
+  /// Jedes Fach kann nur zwischen den Stunden liegen, in denen es das einzige bzw. überhaupt ein
+  /// mögliches Fach ist, und insgesamt kommen `forced[slot]` Stunden hinzu, abzüglich höchstens
+  /// `slack` leer bleibender. Die Abweichung wird unter diesen Bedingungen ohne Ganzzahligkeit
+  /// minimiert, was höchstens kleiner ausfällt als mit.
+  pub(crate) fn imbalance(&self, slot: usize, subject_counts: &[usize], slack: usize) -> f64 {
+    let subjects = self.targets.len();
+
+    let bounds: Vec<(f64, f64)> = (0..subjects)
+      .map(|s| {
+        let count = subject_counts[s];
+        (
+          (count + self.min_counts[[slot, s]].saturating_sub(slack)) as f64,
+          (count + self.max_counts[[slot, s]]) as f64,
+        )
+      })
+      .collect();
+
+    // Jedes Fach so nah wie möglich am Ziel
+    let mut counts: Vec<f64> = (0..subjects)
+      .map(|s| self.targets[s].clamp(bounds[s].0, bounds[s].1))
+      .collect();
+
+    let max_total = (subject_counts.iter().sum::<usize>() + self.forced[slot]) as f64;
+    let min_total = max_total - slack.min(self.forced[slot]) as f64;
+    let sum = counts.iter().sum::<f64>();
+    let mut deficit = if sum > max_total {
+      max_total - sum
+    } else if sum < min_total {
+      min_total - sum
+    } else {
+      0.0
+    };
+
+    // Fehlende bzw. überzählige Stunden dort verteilen, wo sie am wenigsten kosten. Nach dem
+    // Clamping entfernt jede Verschiebung das Fach weiter vom Ziel, eine Stunde kostet dann
+    // `1 / target`, also zuerst die Fächer mit dem größten Ziel.
+    for &s in &self.order {
+      if deficit > 0.0 {
+        let shift = deficit.min(bounds[s].1 - counts[s]);
+        counts[s] += shift;
+        deficit -= shift;
+      } else if deficit < 0.0 {
+        let shift = (-deficit).min(counts[s] - bounds[s].0);
+        counts[s] -= shift;
+        deficit += shift;
+      }
+    }
+
+    counts
+      .iter()
+      .zip(self.targets.iter())
+      .map(|(count, target)| (count - target).abs() / target)
+      .sum()
+  }
+}
+
+/// Mindestanzahl der Stunden ab `slot`, die über alle Praktikanten hinweg leer bleiben müssen,
+/// weil es in einer Stunde weniger Plätze als Praktikanten gibt.
+///
+/// Je Stunde wird die größte Zuordnung von Praktikanten zu Stunden im Lehrerplan bestimmt, von
+/// denen jede höchstens `per_lesson` Praktikanten aufnimmt. Die Grenzen je Klasse und Lehrkraft
+/// bleiben unberücksichtigt, ebenso Praktikanten mit einem Ziel je Woche, deren leere Stunden nicht
+/// zählen. Die Schranke fällt dadurch höchstens kleiner aus.
+pub(crate) fn blocked_slots(
+  trainees: &[Trainee],
+  per_lesson: usize,
+  time_slots: usize,
+) -> Vec<usize> {
+  let mut blocked = vec![0; time_slots + 1];
+
+  for slot in (0..time_slots).rev() {
+    // [trainee] = mögliche Stunden im Lehrerplan
+    let lessons: Vec<Vec<usize>> = trainees
+      .iter()
+      .map(|t| {
+        if !t.workload.fills_all() {
+          return vec![];
+        }
+        t.schedule
+          .column(slot)
+          .iter()
+          .flatten()
+          .map(|&(_, pl)| pl)
+          .collect()
+      })
+      .collect();
+
+    // [pl_index] = zugeordnete Praktikanten
+    let mut assigned: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut unmatched = 0;
+    for (trainee, candidates) in lessons.iter().enumerate() {
+      if candidates.is_empty() {
+        continue;
+      }
+      let mut visited = vec![];
+      if !augment(trainee, &lessons, per_lesson, &mut assigned, &mut visited) {
+        unmatched += 1;
+      }
+    }
+
+    blocked[slot] = blocked[slot + 1] + unmatched;
+  }
+
+  blocked
+}
+
+/// Sucht einen Platz für `trainee`, wobei bereits zugeordnete Praktikanten auf andere Stunden
+/// ausweichen dürfen (augmentierender Pfad)
+fn augment(
+  trainee: usize,
+  lessons: &[Vec<usize>],
+  per_lesson: usize,
+  assigned: &mut HashMap<usize, Vec<usize>>,
+  visited: &mut Vec<usize>,
+) -> bool {
+  for &pl_index in &lessons[trainee] {
+    if visited.contains(&pl_index) {
+      continue;
+    }
+    visited.push(pl_index);
+
+    let occupants = assigned.entry(pl_index).or_default();
+    if occupants.len() < per_lesson {
+      occupants.push(trainee);
+      return true;
+    }
+
+    for i in 0..occupants.len() {
+      let other = assigned[&pl_index][i];
+      if augment(other, lessons, per_lesson, assigned, visited) {
+        assigned.get_mut(&pl_index).unwrap()[i] = trainee;
+        return true;
+      }
+    }
+  }
+
+  false
+}
+
+#[cfg(test)]
+mod tests {
+  use ndarray::Array2;
+
+  use super::{LowerBound, blocked_slots};
+  use crate::{Mentors, Workload, trainee::Trainee, workload::ForcedEmpty};
+
+  /// Stundenplan mit `classes` Klassen und `slots` Stunden, in dem je nach `seed` einzelne Stunden
+  /// frei bleiben, die Fächer wechseln und manche Klassen zwei Einträge in einer Stunde haben
+  fn schedule(
+    seed: usize,
+    classes: usize,
+    slots: usize,
+    subjects: usize,
+  ) -> Array2<Vec<(usize, usize)>> {
+    Array2::from_shape_fn((classes, slots), |(class, slot)| {
+      let entries = if (class + slot * seed).is_multiple_of(3) {
+        0
+      } else if (class * seed + slot).is_multiple_of(4) {
+        2
+      } else {
+        1
+      };
+      (0..entries)
+        .map(|entry| {
+          (
+            (class * 7 + slot * 3 + seed + entry) % subjects,
+            2 * (class * slots + slot) + entry,
+          )
+        })
+        .collect()
+    })
+  }
+
+  /// Alle vollständigen Belegungen als `[slot] = Option<(subject, class)>`. Auch Stunden mit
+  /// möglichen Klassen dürfen leer bleiben, etwa wegen der Kapazität.
+  fn completions(schedule: &Array2<Vec<(usize, usize)>>) -> Vec<Vec<Option<(usize, usize)>>> {
+    let mut all = vec![vec![]];
+    for slot in 0..schedule.ncols() {
+      let choices: Vec<Option<(usize, usize)>> = [None]
+        .into_iter()
+        .chain((0..schedule.nrows()).flat_map(|class| {
+          schedule[[class, slot]]
+            .iter()
+            .map(move |&(subject, _)| Some((subject, class)))
+        }))
+        .collect();
+
+      all = all
+        .into_iter()
+        .flat_map(|prefix: Vec<_>| {
+          choices.iter().map(move |&choice| {
+            let mut next = prefix.clone();
+            next.push(choice);
+            next
+          })
+        })
+        .collect();
+    }
+    all
+  }
+
+  /// Für jede Belegung überschätzt die Schranke an keiner Stelle die Klassen und die Abweichung
+  /// von der Gewichtung, die die restliche Belegung tatsächlich hinzufügt, wenn `slack` die Anzahl
+  /// der danach noch leer bleibenden Stunden ist. Nach der letzten Stunde stimmt sie genau.
+  #[test]
+  fn bound_never_exceeds_completion() {
+    let weights = [0.5, 0.3, 0.2];
+    for seed in 1..5 {
+      let schedule = schedule(seed, 3, 6, weights.len());
+      let slots = schedule.ncols();
+      let targets: Vec<f64> = weights.iter().map(|w| slots as f64 * w).collect();
+      let bound = LowerBound::new(&schedule, &targets, slots);
+      let has_candidates =
+        |slot: usize| (0..schedule.nrows()).any(|c| !schedule[[c, slot]].is_empty());
+      let mut covered = vec![false; schedule.nrows()];
+
+      for assignment in completions(&schedule) {
+        let mut final_counts = vec![0; weights.len()];
+        let mut final_classes = vec![];
+        for &(subject, class) in assignment.iter().flatten() {
+          final_counts[subject] += 1;
+          if !final_classes.contains(&class) {
+            final_classes.push(class);
+          }
+        }
+        let final_imbalance: f64 = final_counts
+          .iter()
+          .zip(&targets)
+          .map(|(&count, &target)| (count as f64 - target).abs() / target)
+          .sum();
+
+        let mut counts = vec![0; weights.len()];
+        let mut classes = vec![];
+        for slot in 0..=slots {
+          let slack = (slot..slots)
+            .filter(|&s| assignment[s].is_none() && has_candidates(s))
+            .count();
+
+          let imbalance = bound.imbalance(slot, &counts, slack);
+          assert!(
+            imbalance <= final_imbalance + 1e-9,
+            "Seed {seed}, Stunde {slot}: Schranke {imbalance} > {final_imbalance}"
+          );
+          let new_classes = bound.new_classes(slot, &classes, slack, &mut covered);
+          assert!(
+            classes.len() + new_classes <= final_classes.len(),
+            "Seed {seed}, Stunde {slot}: {} + {new_classes} Klassen > {}",
+            classes.len(),
+            final_classes.len()
+          );
+
+          if let Some(&Some((subject, class))) = assignment.get(slot) {
+            counts[subject] += 1;
+            if !classes.contains(&class) {
+              classes.push(class);
+            }
+          }
+        }
+
+        assert!((bound.imbalance(slots, &counts, 0) - final_imbalance).abs() < 1e-9);
+        assert_eq!(bound.new_classes(slots, &classes, 0, &mut covered), 0);
+      }
+    }
+  }
+
+  /// Praktikant, der in Stunde 0 die Einträge `pl_indices` im Lehrerplan besuchen kann, je einen
+  /// in einer eigenen Klasse
+  fn trainee(pl_indices: &[usize]) -> Trainee {
+    let schedule = Array2::from_shape_fn((4, 1), |(class, _)| {
+      pl_indices
+        .get(class)
+        .map(|&pl_index| (0, pl_index))
+        .into_iter()
+        .collect()
+    });
+    Trainee {
+      name: String::new(),
+      subject_targets: vec![1.0],
+      subject_kuerzel: vec!["M".to_string()],
+      bound: LowerBound::new(&schedule, &[1.0], 1),
+      forced_empty: ForcedEmpty::new(&schedule, &[], &Workload::default()),
+      schedule,
+      pinned: vec![false],
+      double_lessons: false,
+      required: vec![],
+      coverage: vec![],
+      workload: Workload::default(),
+      mentors: Mentors::default(),
+      preferred_mentor: None,
+      empty_reasons: vec![None],
+    }
+  }
+
+  /// Wer keinen Platz findet, bleibt leer, andere Praktikanten weichen dafür aber auf ihre übrigen
+  /// Stunden aus
+  #[test]
+  fn blocked_slots_match_capacity() {
+    // Alle wollen in dieselbe Stunde
+    let crowded = [trainee(&[0]), trainee(&[0]), trainee(&[0])];
+    assert_eq!(blocked_slots(&crowded, 1, 1), [2, 0]);
+    assert_eq!(blocked_slots(&crowded, 2, 1), [1, 0]);
+    assert_eq!(blocked_slots(&crowded, 3, 1), [0, 0]);
+
+    // A belegt zuerst Stunde 0, muss für B aber auf Stunde 1 ausweichen
+    let shifting = [trainee(&[0, 1]), trainee(&[0])];
+    assert_eq!(blocked_slots(&shifting, 1, 1), [0, 0]);
+
+    // Ohne mögliche Stunde bleibt ein Praktikant ohnehin leer, das zählt nicht
+    let idle = [trainee(&[0]), trainee(&[])];
+    assert_eq!(blocked_slots(&idle, 1, 1), [0, 0]);
+  }
+}
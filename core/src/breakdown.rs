@@ -0,0 +1,519 @@
+//! Aufschlüsselung der Kosten einer Lösung, damit sich der Plan gegenüber Praktikant und
+//! Koordination begründen lässt.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  EmptyReason, EmptySlot, Objective, Problem, Solution, Timeslot,
+  continuity::{ClassLoad, lesson_pairs},
+};
+
+/// Zusammensetzung der Kosten einer Lösung
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostBreakdown {
+  /// Gesamtkosten, Summe aus `classes.cost`, allen `subjects[..].cost`, `gaps.cost`,
+  /// `mentors.cost`, `mentors.elsewhere_cost`, allen `coverage[..].cost`, `continuity.cost` und
+  /// `workload.cost`
+  pub total: f64,
+  pub classes: ClassesCost,
+  /// Ein Eintrag je Fach in der Reihenfolge der übergebenen Fächer
+  pub subjects: Vec<SubjectCost>,
+  pub gaps: GapsCost,
+  pub mentors: MentorsCost,
+  /// Ein Eintrag je Gruppe von Jahrgangsstufen, siehe [`GradeCoverage`](crate::GradeCoverage)
+  pub coverage: Vec<CoverageCost>,
+  pub continuity: ContinuityCost,
+  pub workload: WorkloadCost,
+  /// Unterrichtsstunden des Rasters, in denen der Praktikant keine Stunde hat
+  pub empty_slots: Vec<SlotRef>,
+  /// Die Stunden aus `empty_slots`, in denen zwar Unterricht in einem seiner Fächer stattfindet,
+  /// aber die [`Capacity`](crate::Capacity) durch andere Praktikanten ausgeschöpft ist oder der
+  /// [`Workload`](crate::Workload) keine weitere Stunde zulässt
+  pub blocked_slots: Vec<SlotRef>,
+  /// Einträge im Lehrerplan, die zur selben Zeit in derselben Klasse wie eine besuchte Stunde
+  /// stattfinden und deshalb nicht besucht werden, z.B. die andere Gruppe einer geteilten Klasse
+  pub dropped: Vec<DroppedLesson>,
+  /// Begründung für jede leere Stunde des Rasters nach Tag und Stunde sortiert, auch für Stunden,
+  /// die keine Unterrichtsstunden sind. Die Stunden aus `blocked_slots` haben den Grund
+  /// [`EmptyReason::LeftEmpty`].
+  pub empty_reasons: Vec<EmptySlot>,
+}
+
+/// Anteil der besuchten Klassen an den Kosten
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClassesCost {
+  /// Kürzel der besuchten Klassen in der Reihenfolge ihres ersten Besuchs
+  pub kuerzel: Vec<String>,
+  pub count: usize,
+  /// `count` multipliziert mit [`Objective::classes`]
+  pub cost: f64,
+}
+
+/// Anteil der Freistunden an den Kosten
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GapsCost {
+  /// Anzahl der Freistunden über alle Tage
+  pub count: usize,
+  /// `count` multipliziert mit [`Objective::gaps`]
+  pub cost: f64,
+}
+
+/// Anteil der Lehrkräfte an den Kosten
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MentorsCost {
+  /// Kürzel der Lehrkräfte, bei denen der Praktikant hospitiert, in der Reihenfolge ihres ersten
+  /// Besuchs
+  pub kuerzel: Vec<String>,
+  /// Anzahl der Stunden je Lehrkraft aus `kuerzel`
+  pub lessons: Vec<usize>,
+  pub count: usize,
+  /// `count` multipliziert mit [`Objective::mentors`]
+  pub cost: f64,
+  /// Kürzel des Betreuungslehrers, siehe [`Mentors::preferred`](crate::Mentors)
+  pub preferred: Option<String>,
+  /// Anzahl der Stunden, die nicht beim Betreuungslehrer stattfinden, 0 ohne Betreuungslehrer
+  pub elsewhere: usize,
+  /// `elsewhere` multipliziert mit [`Objective::preferred_mentor`]
+  pub elsewhere_cost: f64,
+}
+
+/// Stunden in einer Gruppe von Jahrgangsstufen und ihr Anteil an den Kosten. Bei
+/// [`GradeCoverage::per_subject`](crate::GradeCoverage) ein Eintrag je Fach.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CoverageCost {
+  pub name: String,
+  /// Fach der Gruppe, `None` = alle Fächer
+  pub subject: Option<String>,
+  /// Anzahl der Stunden in der Gruppe
+  pub lessons: usize,
+  pub min_lessons: usize,
+  pub target: usize,
+  /// Stunden, die bis `target` fehlen
+  pub shortfall: usize,
+  /// `shortfall` multipliziert mit [`Objective::coverage`]
+  pub cost: f64,
+}
+
+/// Anteil der Kombinationen aus Klasse und Fach an den Kosten
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContinuityCost {
+  /// Besuchte Kombinationen in der Reihenfolge ihres ersten Besuchs
+  pub pairs: Vec<FollowedPair>,
+  pub count: usize,
+  /// `count` multipliziert mit [`Objective::continuity`]
+  pub cost: f64,
+}
+
+/// Eine Klasse, die der Praktikant in einem Fach besucht. Mit mehr als einer Stunde begleitet er
+/// sie über die Woche.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FollowedPair {
+  /// Kürzel der Klasse
+  pub class: String,
+  /// Kürzel des Fachs
+  pub subject: String,
+  /// Stunden der Kombination in der Reihenfolge der Woche
+  pub slots: Vec<SlotRef>,
+}
+
+/// Anteil der Abweichung vom Ziel je Woche an den Kosten
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkloadCost {
+  /// Anzahl der belegten Stunden in der Woche
+  pub lessons: usize,
+  /// Siehe [`Workload::target_per_week`](crate::Workload)
+  pub target: Option<usize>,
+  /// `|lessons - target|`, 0 ohne Ziel
+  pub deviation: usize,
+  /// `deviation` multipliziert mit [`Objective::workload`]
+  pub cost: f64,
+}
+
+/// Anteil eines Fachs an den Kosten
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SubjectCost {
+  pub kuerzel: String,
+  /// Anzahl der Stunden in diesem Fach
+  pub actual: usize,
+  /// Angestrebte Anzahl gemäß der Gewichtung des Fachs
+  pub target: f64,
+  /// Relative Abweichung `|actual - target| / target`
+  pub imbalance: f64,
+  /// `imbalance` multipliziert mit [`Objective::balance`]
+  pub cost: f64,
+}
+
+/// Ein nicht besuchter Eintrag im Lehrerplan, siehe [`CostBreakdown::dropped`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DroppedLesson {
+  pub slot: SlotRef,
+  /// Index des nicht besuchten Eintrags im Lehrerplan
+  pub pl_index: usize,
+  /// Index des stattdessen besuchten Eintrags
+  pub chosen: usize,
+}
+
+/// Eine Stunde im Raster, in derselben Form wie die Indizes der Rückgabe von
+/// [`generate`](crate::generate)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SlotRef {
+  pub day: usize,
+  pub period: usize,
+}
+
+impl Problem {
+  /// Schlüsselt die Kosten von `solution` auf. `total` entspricht dabei genau den Kosten, die das
+  /// Verfahren für die Lösung berechnet hat.
+  pub(crate) fn breakdown(
+    &self,
+    trainee: usize,
+    solution: &Solution,
+    timeslots: &[Timeslot],
+  ) -> CostBreakdown {
+    let Objective {
+      classes,
+      balance,
+      gaps: gap_weight,
+      mentors: mentor_weight,
+      preferred_mentor,
+      coverage: coverage_weight,
+      continuity,
+      workload,
+    } = self.objective;
+    let problem = &self.trainees[trainee];
+
+    let mut used_classes = vec![];
+    let mut subject_counts = vec![0; problem.subject_targets.len()];
+    let mut empty_slots = vec![];
+    let mut blocked_slots = vec![];
+    let mut dropped = vec![];
+    let mut empty_reasons: Vec<EmptySlot> = self
+      .non_lesson_periods
+      .iter()
+      .map(|&slot| EmptySlot {
+        slot,
+        reason: EmptyReason::NoLessonPeriod,
+      })
+      .collect();
+    // (class, subject) der besuchten Stunden
+    let mut visited = vec![];
+    // [i] = Stunde von `visited[i]`
+    let mut visited_slots = vec![];
+
+    for (slot, assignment) in solution.assignments.iter().enumerate() {
+      let Timeslot { day, period, .. } = timeslots[slot];
+      let Some(pl_index) = *assignment else {
+        empty_slots.push(SlotRef { day, period });
+        if problem.has_candidates(slot) {
+          blocked_slots.push(SlotRef { day, period });
+        }
+        empty_reasons.push(EmptySlot {
+          slot: SlotRef { day, period },
+          reason: problem.empty_reasons[slot].unwrap_or(EmptyReason::LeftEmpty),
+        });
+        continue;
+      };
+
+      let Some((class, subject, _)) = problem.lessons(slot).find(|&(.., pl)| pl == pl_index) else {
+        continue;
+      };
+
+      dropped.extend(
+        problem.schedule[[class, slot]]
+          .iter()
+          .filter(|&&(_, pl)| pl != pl_index)
+          .map(|&(_, pl)| DroppedLesson {
+            slot: SlotRef { day, period },
+            pl_index: pl,
+            chosen: pl_index,
+          }),
+      );
+
+      if !used_classes.contains(&class) {
+        used_classes.push(class);
+      }
+      subject_counts[subject] += 1;
+      visited.push((class, subject));
+      visited_slots.push(SlotRef { day, period });
+    }
+
+    empty_reasons.sort_by_key(|empty| empty.slot);
+
+    let (gaps, _) = self.gaps_and_violations(trainee, |slot| solution.assignments[slot].is_some());
+    let week_lessons = subject_counts.iter().sum();
+    let deviation = problem.workload.deviation(week_lessons);
+
+    let lessons = || solution.assignments.iter().flatten().copied();
+    let mentor_lessons = self.mentor_lessons(lessons());
+    let mentors = self.mentor_load(trainee, lessons());
+    let coverage = self.coverage_counts(trainee, visited.iter().copied());
+    let pairs = lesson_pairs(visited);
+
+    let subjects = subject_counts
+      .iter()
+      .zip(problem.subject_targets.iter())
+      .zip(problem.subject_kuerzel.iter())
+      .map(|((&actual, &target), kuerzel)| {
+        let imbalance = (actual as f64 - target).abs() / target;
+        SubjectCost {
+          kuerzel: kuerzel.clone(),
+          actual,
+          target,
+          imbalance,
+          cost: balance * imbalance,
+        }
+      })
+      .collect();
+
+    CostBreakdown {
+      total: self.cost(
+        trainee,
+        ClassLoad {
+          classes: used_classes.len(),
+          pairs: pairs.len(),
+        },
+        &subject_counts,
+        gaps,
+        mentors,
+        &coverage,
+      ),
+      classes: ClassesCost {
+        kuerzel: used_classes
+          .iter()
+          .map(|&c| self.class_kuerzel[c].clone())
+          .collect(),
+        count: used_classes.len(),
+        cost: classes * used_classes.len() as f64,
+      },
+      subjects,
+      gaps: GapsCost {
+        count: gaps,
+        cost: gap_weight * gaps as f64,
+      },
+      mentors: MentorsCost {
+        kuerzel: mentor_lessons
+          .iter()
+          .map(|&(t, _)| self.teacher_kuerzel[t].clone())
+          .collect(),
+        lessons: mentor_lessons.iter().map(|&(_, count)| count).collect(),
+        count: mentors.mentors,
+        cost: mentor_weight * mentors.mentors as f64,
+        preferred: problem.mentors.preferred.clone(),
+        elsewhere: mentors.elsewhere,
+        elsewhere_cost: preferred_mentor * mentors.elsewhere as f64,
+      },
+      coverage: problem
+        .coverage
+        .iter()
+        .zip(&coverage)
+        .map(|(group, &lessons)| CoverageCost {
+          name: group.name.clone(),
+          subject: group.subject.map(|s| problem.subject_kuerzel[s].clone()),
+          lessons,
+          min_lessons: group.min_lessons,
+          target: group.target,
+          shortfall: group.shortfall(lessons),
+          cost: coverage_weight * group.shortfall(lessons) as f64,
+        })
+        .collect(),
+      continuity: ContinuityCost {
+        pairs: pairs
+          .iter()
+          .map(|((class, subject), positions)| FollowedPair {
+            class: self.class_kuerzel[*class].clone(),
+            subject: problem.subject_kuerzel[*subject].clone(),
+            slots: positions.iter().map(|&i| visited_slots[i]).collect(),
+          })
+          .collect(),
+        count: pairs.len(),
+        cost: continuity * pairs.len() as f64,
+      },
+      workload: WorkloadCost {
+        lessons: week_lessons,
+        target: problem.workload.target_per_week,
+        deviation,
+        cost: workload * deviation as f64,
+      },
+      empty_slots,
+      blocked_slots,
+      dropped,
+      empty_reasons,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use willi::{SyntheticParams, WilliStundenplan};
+
+  use super::{DroppedLesson, SlotRef};
+  use crate::{
+    Backend, CallbackObserver, Mentors, NoopObserver, Objective, Praktikant, SolveOptions,
+    SolverEvent, generate,
+    testing::{plan, trainee},
+  };
+
+  /// Bei jedem Verfahren stimmt `total` mit den Kosten des Verfahrens und der Summe der
+  /// Bestandteile überein, auch in der zuletzt gemeldeten Lösung
+  #[test]
+  fn total_matches_cost() {
+    let objectives = [
+      Objective::default(),
+      Objective {
+        classes: 2.5,
+        balance: 0.5,
+        gaps: 1.5,
+        mentors: 0.5,
+        preferred_mentor: 1.0,
+        coverage: 3.0,
+        continuity: 0.5,
+        workload: 2.0,
+      },
+    ];
+
+    for seed in 1..4 {
+      let plan = plan(seed);
+      for objective in &objectives {
+        for backend in [Backend::Search, Backend::Milp, Backend::LocalSearch] {
+          let mut reported = vec![];
+          let result = generate(
+            &plan,
+            &trainee("A"),
+            objective,
+            &SolveOptions {
+              backend,
+              seed,
+              ..Default::default()
+            },
+            &mut CallbackObserver(|event| {
+              if let SolverEvent::Solution { mut plans, cost } = event {
+                reported.push((cost, plans.remove(0).breakdown));
+              }
+            }),
+          )
+          .unwrap();
+
+          let breakdown = &result.breakdown;
+          let context = format!("Seed {seed}, {backend:?}, {objective:?}");
+          assert!(
+            (breakdown.total - result.cost).abs() < 1e-9,
+            "{context}: {} ≠ {}",
+            breakdown.total,
+            result.cost
+          );
+          let parts = breakdown.classes.cost
+            + breakdown.gaps.cost
+            + breakdown.mentors.cost
+            + breakdown.mentors.elsewhere_cost
+            + breakdown.coverage.iter().map(|c| c.cost).sum::<f64>()
+            + breakdown.continuity.cost
+            + breakdown.workload.cost
+            + breakdown.subjects.iter().map(|s| s.cost).sum::<f64>();
+          assert!((breakdown.total - parts).abs() < 1e-9, "{context}");
+          assert_eq!(breakdown.classes.count, breakdown.classes.kuerzel.len());
+          assert_eq!(
+            breakdown.subjects.iter().map(|s| s.actual).sum::<usize>(),
+            result.solution.iter().flatten().flatten().count(),
+            "{context}"
+          );
+
+          // Die zuletzt gemeldete Lösung ist die zurückgegebene
+          let (cost, last) = reported.pop().unwrap();
+          assert_eq!(&last, breakdown, "{context}");
+          assert!((cost - result.cost).abs() < 1e-9, "{context}");
+        }
+      }
+    }
+  }
+
+  /// Beide Gruppen einer geteilten Klasse bleiben möglich: Jedes Verfahren besucht die Gruppe des
+  /// Betreuungslehrers, die andere steht in `dropped`
+  #[test]
+  fn dropped() {
+    // Erste Stunde in Mathematik, in der die Klasse geteilt ist
+    let (plan, a, b) = (1..10)
+      .find_map(|seed| {
+        let mut params = SyntheticParams {
+          tage: 2,
+          stunden: 5,
+          klassen: 4,
+          lehrkraefte: 12,
+          seed,
+          ..Default::default()
+        };
+        params.faecher[0].geteilt = true;
+        let plan = WilliStundenplan::synthetic(&params);
+
+        let lines = plan.lehrerstunden();
+        let (a, b) = (0..lines.len()).find_map(|a| {
+          let b = (a + 1..lines.len()).find(|&b| {
+            let (first, second) = (&lines[a], &lines[b]);
+            first.tag_stunde.tag == second.tag_stunde.tag
+              && first.tag_stunde.stunde == second.tag_stunde.stunde
+              && first.klasse == second.klasse
+          })?;
+          (plan.fach(lines[a].fach).unwrap().kuerzel == "M").then_some((a, b))
+        })?;
+        Some((plan, a, b))
+      })
+      .expect("Kein Plan mit geteilter Stunde");
+    let lines = plan.lehrerstunden();
+    let days: Vec<_> = plan.tage().iter().map(|(_, tag)| tag.id).collect();
+    let periods: Vec<_> = plan.stundenraster().collect();
+    let slot = SlotRef {
+      day: days
+        .iter()
+        .position(|&d| d == lines[a].tag_stunde.tag)
+        .unwrap(),
+      period: periods
+        .iter()
+        .position(|&p| p == lines[a].tag_stunde.stunde)
+        .unwrap(),
+    };
+
+    for backend in [Backend::Search, Backend::Milp, Backend::LocalSearch] {
+      for (chosen, other) in [(a, b), (b, a)] {
+        let teacher = plan.lehrkraft(lines[chosen].lehrkraft).unwrap();
+        let trainee = Praktikant {
+          mentors: Mentors {
+            preferred: Some(teacher.kuerzel.clone()),
+            ..Default::default()
+          },
+          ..trainee("A")
+        };
+        let result = generate(
+          &plan,
+          &trainee,
+          &Objective {
+            preferred_mentor: 10.0,
+            ..Default::default()
+          },
+          &SolveOptions {
+            backend,
+            ..Default::default()
+          },
+          &mut NoopObserver,
+        )
+        .unwrap();
+
+        assert_eq!(result.solution[slot.day][slot.period], Some(chosen));
+        assert!(
+          result.breakdown.dropped.contains(&DroppedLesson {
+            slot,
+            pl_index: other,
+            chosen,
+          }),
+          "{backend:?}: {:?}",
+          result.breakdown.dropped
+        );
+        // Alle anderen Einträge in `dropped` gehören ebenfalls zu besuchten Stunden
+        for dropped in &result.breakdown.dropped {
+          assert_eq!(
+            result.solution[dropped.slot.day][dropped.slot.period],
+            Some(dropped.chosen)
+          );
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,175 @@
+//! Kontinuität: dieselbe Klasse im selben Fach mehrmals besuchen.
+//!
+//! Ein Praktikant soll eine Klasse in einem Fach über die Woche begleiten, statt viele einzelne
+//! Stunden in verschiedenen Klassen zu sehen. [`Objective::classes`](crate::Objective) zählt nur
+//! die Klassen, [`Objective::continuity`](crate::Objective) dagegen jede besuchte Kombination aus
+//! Klasse und Fach. Bei gleicher Anzahl Stunden sind weniger Kombinationen gleichbedeutend mit mehr
+//! Stunden je Kombination, das Gewicht wirkt also wie ein Bonus für wiederholte Besuche. Wie die
+//! Klassen kommen Kombinationen während der Suche nur hinzu, die Schranke der Suche bleibt gültig.
+//!
+//! Welche Kombinationen der Praktikant wie oft besucht, steht in
+//! [`CostBreakdown::continuity`](crate::CostBreakdown).
+
+use ndarray::Array2;
+
+/// Klassen einer Belegung, soweit sie in die Kosten eingehen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct ClassLoad {
+  /// Anzahl verschiedener Klassen
+  pub(crate) classes: usize,
+  /// Anzahl verschiedener Kombinationen aus Klasse und Fach
+  pub(crate) pairs: usize,
+}
+
+/// Kombinationen `(class, subject)` mit den Positionen ihrer Stunden in `lessons`, in der
+/// Reihenfolge des ersten Besuchs
+pub(crate) fn lesson_pairs(
+  lessons: impl IntoIterator<Item = (usize, usize)>,
+) -> Vec<((usize, usize), Vec<usize>)> {
+  let mut pairs: Vec<((usize, usize), Vec<usize>)> = vec![];
+  for (position, pair) in lessons.into_iter().enumerate() {
+    match pairs.iter_mut().find(|(p, _)| *p == pair) {
+      Some((_, positions)) => positions.push(position),
+      None => pairs.push((pair, vec![position])),
+    }
+  }
+
+  pairs
+}
+
+/// Stunden je Kombination während der Suche
+#[derive(Debug)]
+pub(crate) struct PairLessons {
+  // [class][subject] = Anzahl Stunden
+  lessons: Array2<usize>,
+  /// Anzahl der Kombinationen mit mindestens einer Stunde
+  pub(crate) pairs: usize,
+}
+
+impl PairLessons {
+  pub(crate) fn new(classes: usize, subjects: usize) -> Self {
+    PairLessons {
+      lessons: Array2::zeros((classes, subjects)),
+      pairs: 0,
+    }
+  }
+
+  pub(crate) fn add(&mut self, class: usize, subject: usize) {
+    let lessons = &mut self.lessons[[class, subject]];
+    if *lessons == 0 {
+      self.pairs += 1;
+    }
+    *lessons += 1;
+  }
+
+  pub(crate) fn remove(&mut self, class: usize, subject: usize) {
+    let lessons = &mut self.lessons[[class, subject]];
+    *lessons -= 1;
+    if *lessons == 0 {
+      self.pairs -= 1;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    Backend, NoopObserver, Objective, Praktikant, SolveOptions, Workload, generate,
+    testing::{plan, trainee},
+  };
+
+  use super::{PairLessons, lesson_pairs};
+
+  #[test]
+  fn pairs_in_order_of_first_visit() {
+    let pairs = lesson_pairs([(0, 1), (2, 0), (0, 1), (1, 1), (2, 0), (0, 1)]);
+    assert_eq!(
+      pairs,
+      vec![
+        ((0, 1), vec![0, 2, 5]),
+        ((2, 0), vec![1, 4]),
+        ((1, 1), vec![3]),
+      ]
+    );
+    assert!(lesson_pairs([]).is_empty());
+  }
+
+  /// Eine Kombination zählt, solange sie mindestens eine Stunde hat
+  #[test]
+  fn pair_lessons() {
+    let mut lessons = PairLessons::new(2, 2);
+    lessons.add(0, 1);
+    lessons.add(0, 1);
+    lessons.add(1, 1);
+    assert_eq!(lessons.pairs, 2);
+
+    lessons.remove(0, 1);
+    assert_eq!(lessons.pairs, 2);
+    lessons.remove(0, 1);
+    assert_eq!(lessons.pairs, 1);
+    lessons.add(0, 0);
+    assert_eq!(lessons.pairs, 2);
+  }
+
+  /// Mit Gewicht besucht der Plan nie mehr Kombinationen als ohne, auf mindestens einem Plan
+  /// weniger. Der Bericht nennt jede besuchte Stunde genau einmal.
+  #[test]
+  fn favours_repeated_pairs() {
+    let free = Objective {
+      classes: 0.0,
+      ..Default::default()
+    };
+    let weighted = Objective {
+      continuity: 2.0,
+      ..free
+    };
+
+    // Mit einem Ziel je Woche bleibt Spielraum bei der Wahl der Stunden
+    let trainee = Praktikant {
+      workload: Workload {
+        target_per_week: Some(5),
+        ..Default::default()
+      },
+      ..trainee("A")
+    };
+
+    let mut fewer = false;
+    for seed in 1..4 {
+      let plan = plan(seed);
+      for backend in [Backend::Search, Backend::Milp] {
+        let solve = |objective: &Objective| {
+          generate(
+            &plan,
+            &trainee,
+            objective,
+            &SolveOptions {
+              backend,
+              ..Default::default()
+            },
+            &mut NoopObserver,
+          )
+          .unwrap()
+        };
+        let without = solve(&free).breakdown.continuity;
+        let result = solve(&weighted);
+        let with = &result.breakdown.continuity;
+
+        let context = format!("{backend:?}, Seed {seed}");
+        assert!(with.count <= without.count, "{context}");
+        fewer |= with.count < without.count;
+
+        assert_eq!(with.count, with.pairs.len(), "{context}");
+        assert!(
+          (with.cost - 2.0 * with.count as f64).abs() < 1e-9,
+          "{context}"
+        );
+        assert_eq!(
+          with.pairs.iter().map(|p| p.slots.len()).sum::<usize>(),
+          result.solution.iter().flatten().flatten().count(),
+          "{context}"
+        );
+      }
+    }
+    assert!(fewer);
+  }
+}
@@ -0,0 +1,391 @@
+//! Abdeckung von Jahrgangsstufen und Schultypen.
+//!
+//! Praktikumsordnungen verlangen oft, sowohl in der Unter- als auch in der Oberstufe zu
+//! hospitieren. Eine [`GradeCoverage`] beschreibt eine Gruppe von Klassen über
+//! `KlassenZeile::jahrgangsstufe` und `KlassenZeile::schultyp` und verlangt dort eine Mindestanzahl
+//! Stunden (harte Bedingung) bzw. bevorzugt eine angestrebte Anzahl über
+//! [`Objective::coverage`](crate::Objective). Klassen ohne Jahrgangsstufe gehören nur zu Gruppen
+//! ohne Grenzen für die Jahrgangsstufe.
+
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+use willi::KlassenZeile;
+
+use crate::{PlannerError, Problem};
+
+/// Anforderung an die Stunden in einer Gruppe von Klassen, z.B.
+/// `{ name: "Unterstufe", min_grade: 5, max_grade: 7, per_subject: true, min_lessons: 4 }`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GradeCoverage {
+  /// Bezeichnung für das Ergebnis
+  pub name: String,
+  /// Niedrigste Jahrgangsstufe der Gruppe
+  pub min_grade: Option<usize>,
+  /// Höchste Jahrgangsstufe der Gruppe
+  pub max_grade: Option<usize>,
+  /// Schultyp der Klassen wie im Plan. `None` = jeder
+  pub school_type: Option<String>,
+  /// Kürzel eines Fachs des Praktikanten. `None` = alle seine Fächer
+  pub subject: Option<String>,
+  /// Die Anforderung gilt für jedes Fach einzeln statt für alle zusammen
+  pub per_subject: bool,
+  /// Mindestanzahl Stunden in der Gruppe
+  pub min_lessons: usize,
+  /// Angestrebte Anzahl Stunden in der Gruppe. Jede fehlende Stunde kostet
+  /// [`Objective::coverage`](crate::Objective).
+  pub target: usize,
+}
+
+impl GradeCoverage {
+  pub(crate) fn validate(&self, subjects: &[String]) -> Result<(), PlannerError> {
+    let invalid = |message: String| Err(PlannerError::InvalidOptions(message));
+
+    if let (Some(min), Some(max)) = (self.min_grade, self.max_grade)
+      && min > max
+    {
+      return invalid(format!(
+        "„{}“: Jahrgangsstufe {min} liegt über {max}",
+        self.name
+      ));
+    }
+    if let Some(kuerzel) = &self.subject
+      && !subjects.contains(kuerzel)
+    {
+      return invalid(format!(
+        "„{}“: „{kuerzel}“ ist kein Fach des Praktikanten",
+        self.name
+      ));
+    }
+
+    Ok(())
+  }
+
+  /// Die Klasse gehört zur Gruppe
+  fn includes(&self, class: Option<&KlassenZeile>) -> bool {
+    let grade = class.and_then(|k| k.jahrgangsstufe);
+    let school_type = class.and_then(|k| k.schultyp.as_deref());
+
+    (self.min_grade.is_none() && self.max_grade.is_none()
+      || grade.is_some_and(|grade| {
+        self.min_grade.is_none_or(|min| grade >= min)
+          && self.max_grade.is_none_or(|max| grade <= max)
+      }))
+      && self
+        .school_type
+        .as_ref()
+        .is_none_or(|t| school_type == Some(t.as_str()))
+  }
+}
+
+/// Eine [`GradeCoverage`] für einen Praktikanten, bei `per_subject` eine je Fach
+#[derive(Debug)]
+pub(crate) struct CoverageGroup {
+  // Siehe `GradeCoverage::name`
+  pub(crate) name: String,
+  // Fach, `None` = alle Fächer
+  pub(crate) subject: Option<usize>,
+  // [class] = Klasse gehört zur Gruppe
+  classes: Vec<bool>,
+  pub(crate) min_lessons: usize,
+  pub(crate) target: usize,
+  // [slot] = Anzahl der Stunden ab `slot`, in denen eine Stunde der Gruppe möglich ist
+  possible: Vec<usize>,
+}
+
+impl CoverageGroup {
+  /// Bildet die Gruppen eines Praktikanten mit `schedule` (`[class][slot] = [(subject, _)]`).
+  /// `classes[class]` ist die Zeile der Klasse im Plan.
+  pub(crate) fn build(
+    rules: &[GradeCoverage],
+    subjects: &[String],
+    classes: &[Option<&KlassenZeile>],
+    schedule: &Array2<Vec<(usize, usize)>>,
+  ) -> Vec<CoverageGroup> {
+    let mut groups = vec![];
+
+    for coverage in rules {
+      let subject = coverage
+        .subject
+        .as_ref()
+        .and_then(|kuerzel| subjects.iter().position(|s| s == kuerzel));
+      let group_subjects = match (subject, coverage.per_subject) {
+        (Some(subject), _) => vec![Some(subject)],
+        (None, true) => (0..subjects.len()).map(Some).collect(),
+        (None, false) => vec![None],
+      };
+
+      for subject in group_subjects {
+        let mut group = CoverageGroup {
+          name: coverage.name.clone(),
+          subject,
+          classes: classes.iter().map(|&k| coverage.includes(k)).collect(),
+          min_lessons: coverage.min_lessons,
+          target: coverage.target,
+          possible: vec![0; schedule.ncols() + 1],
+        };
+        for slot in (0..schedule.ncols()).rev() {
+          let possible = (0..schedule.nrows()).any(|class| {
+            schedule[[class, slot]]
+              .iter()
+              .any(|&(subject, _)| group.matches(class, subject))
+          });
+          group.possible[slot] = group.possible[slot + 1] + usize::from(possible);
+        }
+        groups.push(group);
+      }
+    }
+
+    groups
+  }
+
+  /// Eine Stunde von `class` in `subject` zählt für die Gruppe
+  pub(crate) fn matches(&self, class: usize, subject: usize) -> bool {
+    self.classes[class] && self.subject.is_none_or(|s| s == subject)
+  }
+
+  /// Anzahl der Stunden ab `slot`, in denen eine Stunde der Gruppe möglich ist
+  pub(crate) fn possible(&self, slot: usize) -> usize {
+    self.possible[slot]
+  }
+
+  /// Fehlende Stunden bis `target` bei `lessons` Stunden in der Gruppe
+  pub(crate) fn shortfall(&self, lessons: usize) -> usize {
+    self.target.saturating_sub(lessons)
+  }
+}
+
+impl Problem {
+  /// `[group]` = Anzahl der Stunden von `trainee` in jeder [`CoverageGroup`], wenn er die
+  /// `(class, subject)` aus `lessons` besucht
+  pub(crate) fn coverage_counts(
+    &self,
+    trainee: usize,
+    lessons: impl IntoIterator<Item = (usize, usize)>,
+  ) -> Vec<usize> {
+    let groups = &self.trainees[trainee].coverage;
+    let mut counts = vec![0; groups.len()];
+    for (class, subject) in lessons {
+      for (count, group) in counts.iter_mut().zip(groups) {
+        if group.matches(class, subject) {
+          *count += 1;
+        }
+      }
+    }
+
+    counts
+  }
+
+  /// Stunden, die `trainee` bei `counts` bis zur Mindestanzahl jeder Gruppe fehlen
+  pub(crate) fn coverage_violations(&self, trainee: usize, counts: &[usize]) -> usize {
+    self.trainees[trainee]
+      .coverage
+      .iter()
+      .zip(counts)
+      .map(|(group, &count)| group.min_lessons.saturating_sub(count))
+      .sum()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use willi::{KlassenZeile, SyntheticParams, WilliStundenplan};
+
+  use crate::{
+    Backend, NoopObserver, Objective, Praktikant, SolveOptions, SolveResult, generate,
+    testing::trainee,
+  };
+
+  use super::GradeCoverage;
+
+  const BACKENDS: [Backend; 3] = [Backend::Search, Backend::Milp, Backend::LocalSearch];
+
+  /// Wie [`crate::testing::plan`], die Klassen der Jahrgangsstufen 5, 7, 9 und 11 sind
+  /// abwechselnd Realschul- und Gymnasialklassen
+  fn plan(seed: u64) -> WilliStundenplan {
+    WilliStundenplan::synthetic(&SyntheticParams {
+      tage: 2,
+      stunden: 5,
+      klassen: 4,
+      lehrkraefte: 12,
+      schultypen: vec!["RS".to_string(), "GY".to_string()],
+      seed,
+      ..Default::default()
+    })
+  }
+
+  fn solve(
+    plan: &WilliStundenplan,
+    trainee: &Praktikant,
+    objective: &Objective,
+    backend: Backend,
+  ) -> SolveResult {
+    generate(
+      plan,
+      trainee,
+      objective,
+      &SolveOptions {
+        backend,
+        ..Default::default()
+      },
+      &mut NoopObserver,
+    )
+    .unwrap()
+  }
+
+  /// Anzahl der Stunden der Lösung in Klassen und im Fach von `coverage`
+  fn lessons(plan: &WilliStundenplan, result: &SolveResult, coverage: &GradeCoverage) -> usize {
+    result
+      .solution
+      .iter()
+      .flatten()
+      .flatten()
+      .filter(|&&pl_index| {
+        let line = &plan.lehrerstunden()[pl_index];
+        let subject = &plan.fach(line.fach).unwrap().kuerzel;
+        coverage.includes(plan.klassen().get(line.klasse.0))
+          && coverage.subject.as_ref().is_none_or(|s| s == subject)
+      })
+      .count()
+  }
+
+  #[test]
+  fn includes() {
+    let class = |grade: Option<usize>, school_type: Option<&str>| KlassenZeile {
+      jahrgangsstufe: grade,
+      schultyp: school_type.map(str::to_string),
+      ..Default::default()
+    };
+    let lower = GradeCoverage {
+      min_grade: Some(5),
+      max_grade: Some(7),
+      ..Default::default()
+    };
+    let upper = GradeCoverage {
+      min_grade: Some(11),
+      school_type: Some("GY".to_string()),
+      ..Default::default()
+    };
+
+    assert!(lower.includes(Some(&class(Some(5), None))));
+    assert!(lower.includes(Some(&class(Some(7), Some("RS")))));
+    assert!(!lower.includes(Some(&class(Some(8), None))));
+    assert!(!lower.includes(Some(&class(None, None))));
+    assert!(!lower.includes(None));
+
+    assert!(upper.includes(Some(&class(Some(12), Some("GY")))));
+    assert!(!upper.includes(Some(&class(Some(12), Some("RS")))));
+    assert!(!upper.includes(Some(&class(Some(12), None))));
+    assert!(!upper.includes(Some(&class(Some(9), Some("GY")))));
+
+    // Ohne Grenzen gehört auch eine Klasse ohne Jahrgangsstufe zur Gruppe
+    let any = GradeCoverage::default();
+    assert!(any.includes(Some(&class(None, None))));
+    assert!(any.includes(None));
+  }
+
+  /// Jedes Verfahren erreicht die Mindestanzahl in einer Gruppe von Jahrgangsstufen und in einer
+  /// Gruppe eines Schultyps, die ohne Anforderung jeweils mindestens einmal verfehlt wird
+  #[test]
+  fn min_lessons() {
+    let rules = [
+      GradeCoverage {
+        name: "Unterstufe".to_string(),
+        max_grade: Some(7),
+        min_lessons: 4,
+        ..Default::default()
+      },
+      GradeCoverage {
+        name: "Realschule".to_string(),
+        school_type: Some("RS".to_string()),
+        min_lessons: 3,
+        ..Default::default()
+      },
+    ];
+
+    for rule in rules {
+      let constrained = Praktikant {
+        coverage: vec![rule.clone()],
+        ..trainee("A")
+      };
+
+      let mut missed = false;
+      for seed in 1..4 {
+        let plan = plan(seed);
+        let free = solve(&plan, &trainee("A"), &Objective::default(), Backend::Search);
+        missed |= lessons(&plan, &free, &rule) < rule.min_lessons;
+
+        for backend in BACKENDS {
+          let result = solve(&plan, &constrained, &Objective::default(), backend);
+          let count = lessons(&plan, &result, &rule);
+          assert!(
+            count >= rule.min_lessons,
+            "{}, {backend:?}, Seed {seed}: {count}",
+            rule.name
+          );
+          assert_eq!(result.breakdown.coverage[0].lessons, count);
+        }
+      }
+      assert!(missed, "{}", rule.name);
+    }
+  }
+
+  /// Der Bericht nennt je Fach die Stunden in der Gruppe, die fehlenden bis zum Ziel und deren
+  /// Kosten. Mit Gewicht fehlen nie mehr Stunden als ohne.
+  #[test]
+  fn breakdown() {
+    let rule = GradeCoverage {
+      name: "Oberstufe".to_string(),
+      min_grade: Some(9),
+      per_subject: true,
+      target: 4,
+      ..Default::default()
+    };
+    let trainee = Praktikant {
+      coverage: vec![rule.clone()],
+      ..trainee("A")
+    };
+    let weighted = Objective {
+      coverage: 2.0,
+      ..Default::default()
+    };
+
+    for seed in 1..4 {
+      let plan = plan(seed);
+      let free = solve(&plan, &trainee, &Objective::default(), Backend::Search);
+      let free_shortfall: usize = free.breakdown.coverage.iter().map(|c| c.shortfall).sum();
+
+      for backend in BACKENDS {
+        let result = solve(&plan, &trainee, &weighted, backend);
+        let coverage = &result.breakdown.coverage;
+        assert_eq!(coverage.len(), 2);
+
+        for (cost, kuerzel) in coverage.iter().zip(["M", "D"]) {
+          let context = format!("{backend:?}, Seed {seed}, {kuerzel}");
+          let check = GradeCoverage {
+            subject: Some(kuerzel.to_string()),
+            ..rule.clone()
+          };
+          assert_eq!(cost.name, "Oberstufe");
+          assert_eq!(cost.subject.as_deref(), Some(kuerzel));
+          assert_eq!(cost.lessons, lessons(&plan, &result, &check), "{context}");
+          assert_eq!(cost.target, 4);
+          assert_eq!(
+            cost.shortfall,
+            4usize.saturating_sub(cost.lessons),
+            "{context}"
+          );
+          assert!(
+            (cost.cost - 2.0 * cost.shortfall as f64).abs() < 1e-9,
+            "{context}"
+          );
+        }
+
+        if backend != Backend::LocalSearch {
+          let shortfall: usize = coverage.iter().map(|c| c.shortfall).sum();
+          assert!(shortfall <= free_shortfall, "{backend:?}, Seed {seed}");
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,602 @@
+//! Begründungen für leere Stunden und unlösbare Vorgaben.
+//!
+//! Koordinatoren fragen regelmäßig, warum eine Stunde im Plan leer bleibt. Dafür steht in
+//! [`CostBreakdown::empty_reasons`](crate::CostBreakdown) zu jeder leeren Stunde des Rasters ein
+//! [`EmptyReason`]. Findet kein Verfahren eine gültige Lösung, sucht [`conflicts`] eine minimale
+//! Menge von Vorgaben, die sich widersprechen: Jede Vorgabe wird probeweise aufgehoben und bleibt
+//! nur dann aufgehoben, wenn das Problem trotzdem unlösbar ist. Was übrig bleibt, ist für sich
+//! unlösbar, ohne eine beliebige dieser Vorgaben aber lösbar. Ob ein Problem lösbar ist,
+//! entscheidet der erste Durchlauf des MILP, siehe [`Problem::feasible`].
+//!
+//! Die Grenzen aus den [`SolveOptions`] gelten für die Analyse erneut, jede Prüfung zählt als ein
+//! Knoten. Ist das Budget erschöpft, bevor alle Vorgaben geprüft sind, ist das Ergebnis zwar
+//! weiterhin unlösbar, aber nicht unbedingt minimal.
+
+use serde::Serialize;
+use willi::WilliStundenplan;
+
+use crate::{
+  Availability, Capacity, Objective, Praktikant, Problem, SlotRef, SolveOptions, options::Limits,
+};
+
+/// Grund, aus dem eine Stunde im Plan eines Praktikanten leer bleibt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptyReason {
+  /// Die Stunde ist laut `stundenmerkmale` des Tages keine Unterrichtsstunde
+  NoLessonPeriod,
+  /// Der Praktikant ist laut [`Availability`] nicht an der Schule
+  Unavailable,
+  /// Keine Klasse hat Unterricht in einem der gewählten Fächer
+  NoLesson,
+  /// Unterricht in den gewählten Fächern findet nur bei ausgeschlossenen Lehrkräften statt
+  TeachersExcluded,
+  /// Der mögliche Unterricht ist über [`Pins::forbidden`](crate::Pins) verboten
+  Forbidden,
+  /// Der mögliche Unterricht gehört zu Doppelstunden, deren andere Hälfte nicht besucht werden
+  /// kann, siehe [`Pins::double_lessons`](crate::Pins)
+  BrokenDouble,
+  /// Unterricht wäre möglich, das Verfahren lässt die Stunde aber leer, weil die [`Capacity`] durch
+  /// andere Praktikanten ausgeschöpft ist oder sonst eine Vorgabe verletzt würde, z.B. der
+  /// [`Workload`](crate::Workload)
+  LeftEmpty,
+}
+
+/// Eine leere Stunde mit ihrer Begründung
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct EmptySlot {
+  pub slot: SlotRef,
+  pub reason: EmptyReason,
+}
+
+/// Eine Vorgabe, die sich für die Suche nach einem Widerspruch einzeln aufheben lässt. Das erste
+/// Feld ist jeweils der Praktikant, das zweite ggf. die Position in der Liste.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Constraint {
+  Pinned(usize, usize),
+  Forbidden(usize, usize),
+  ExcludedTeacher(usize, usize),
+  DoubleLessons(usize),
+  Availability(usize),
+  MinPerWeek(usize),
+  MaxPerWeek(usize),
+  MinPerDay(usize),
+  MaxPerDay(usize),
+  MaxGaps(usize),
+  MaxMentorLessons(usize),
+  EverySubject(usize),
+  Coverage(usize, usize),
+  PerLesson,
+  PerClass,
+  PerMentor,
+}
+
+impl Constraint {
+  /// Alle Vorgaben, die tatsächlich etwas einschränken
+  fn all(trainees: &[Praktikant], capacity: &Capacity) -> Vec<Constraint> {
+    let mut constraints = vec![];
+
+    for (t, trainee) in trainees.iter().enumerate() {
+      constraints.extend((0..trainee.pins.pinned.len()).map(|i| Constraint::Pinned(t, i)));
+      constraints.extend((0..trainee.pins.forbidden.len()).map(|i| Constraint::Forbidden(t, i)));
+      constraints
+        .extend((0..trainee.excluded_teachers.len()).map(|i| Constraint::ExcludedTeacher(t, i)));
+
+      let workload = &trainee.workload;
+      for (set, constraint) in [
+        (trainee.pins.double_lessons, Constraint::DoubleLessons(t)),
+        (
+          trainee.availability != Availability::default(),
+          Constraint::Availability(t),
+        ),
+        (workload.min_per_week.is_some(), Constraint::MinPerWeek(t)),
+        (workload.max_per_week.is_some(), Constraint::MaxPerWeek(t)),
+        (workload.min_per_day.is_some(), Constraint::MinPerDay(t)),
+        (workload.max_per_day.is_some(), Constraint::MaxPerDay(t)),
+        (workload.max_gaps.is_some(), Constraint::MaxGaps(t)),
+        (
+          trainee.mentors.max_lessons.is_some(),
+          Constraint::MaxMentorLessons(t),
+        ),
+        (trainee.mentors.every_subject, Constraint::EverySubject(t)),
+      ] {
+        if set {
+          constraints.push(constraint);
+        }
+      }
+
+      constraints.extend(
+        (0..trainee.coverage.len())
+          .filter(|&i| trainee.coverage[i].min_lessons > 0)
+          .map(|i| Constraint::Coverage(t, i)),
+      );
+    }
+
+    if trainees.len() > 1 {
+      constraints.push(Constraint::PerLesson);
+      if capacity.per_class.is_some() {
+        constraints.push(Constraint::PerClass);
+      }
+      if capacity.per_mentor.is_some() {
+        constraints.push(Constraint::PerMentor);
+      }
+    }
+
+    constraints
+  }
+
+  /// Praktikant, zu dem die Vorgabe gehört, `None` für die [`Capacity`]
+  fn trainee(self) -> Option<usize> {
+    match self {
+      Constraint::Pinned(t, _)
+      | Constraint::Forbidden(t, _)
+      | Constraint::ExcludedTeacher(t, _)
+      | Constraint::DoubleLessons(t)
+      | Constraint::Availability(t)
+      | Constraint::MinPerWeek(t)
+      | Constraint::MaxPerWeek(t)
+      | Constraint::MinPerDay(t)
+      | Constraint::MaxPerDay(t)
+      | Constraint::MaxGaps(t)
+      | Constraint::MaxMentorLessons(t)
+      | Constraint::EverySubject(t)
+      | Constraint::Coverage(t, _) => Some(t),
+      Constraint::PerLesson | Constraint::PerClass | Constraint::PerMentor => None,
+    }
+  }
+
+  /// Beschreibung für die Fehlermeldung, mit dem Namen des Praktikanten, falls er einen hat
+  fn describe(self, trainees: &[Praktikant], capacity: &Capacity) -> String {
+    let unlimited = || "unbegrenzt".to_string();
+    let description = match self {
+      Constraint::Pinned(t, i) => format!("Festlegung „{}“", trainees[t].pins.pinned[i]),
+      Constraint::Forbidden(t, i) => format!("Verbot „{}“", trainees[t].pins.forbidden[i]),
+      Constraint::ExcludedTeacher(t, i) => format!(
+        "ausgeschlossene Lehrkraft „{}“",
+        trainees[t].excluded_teachers[i]
+      ),
+      Constraint::DoubleLessons(_) => "Doppelstunden nur ganz besuchen".to_string(),
+      Constraint::Availability(_) => "Verfügbarkeit".to_string(),
+      Constraint::MinPerWeek(t) => format!(
+        "mindestens {} Stunden je Woche",
+        trainees[t].workload.min_per_week.unwrap_or_default()
+      ),
+      Constraint::MaxPerWeek(t) => format!(
+        "höchstens {} Stunden je Woche",
+        trainees[t]
+          .workload
+          .max_per_week
+          .map_or_else(unlimited, |n| n.to_string())
+      ),
+      Constraint::MinPerDay(t) => format!(
+        "mindestens {} Stunden an Tagen mit Unterricht",
+        trainees[t].workload.min_per_day.unwrap_or_default()
+      ),
+      Constraint::MaxPerDay(t) => format!(
+        "höchstens {} Stunden je Tag",
+        trainees[t]
+          .workload
+          .max_per_day
+          .map_or_else(unlimited, |n| n.to_string())
+      ),
+      Constraint::MaxGaps(t) => format!(
+        "höchstens {} Freistunden je Tag",
+        trainees[t].workload.max_gaps.unwrap_or_default()
+      ),
+      Constraint::MaxMentorLessons(t) => format!(
+        "höchstens {} Stunden je Lehrkraft",
+        trainees[t]
+          .mentors
+          .max_lessons
+          .map_or_else(unlimited, |n| n.to_string())
+      ),
+      Constraint::EverySubject(_) => "jedes Fach mindestens einmal".to_string(),
+      Constraint::Coverage(t, i) => {
+        let coverage = &trainees[t].coverage[i];
+        format!(
+          "mindestens {} Stunden in „{}“",
+          coverage.min_lessons, coverage.name
+        )
+      }
+      Constraint::PerLesson => format!("höchstens {} Praktikanten je Stunde", capacity.per_lesson),
+      Constraint::PerClass => format!(
+        "höchstens {} Praktikanten je Klasse",
+        capacity.per_class.map_or_else(unlimited, |n| n.to_string())
+      ),
+      Constraint::PerMentor => format!(
+        "höchstens {} Praktikanten je Lehrkraft",
+        capacity
+          .per_mentor
+          .map_or_else(unlimited, |n| n.to_string())
+      ),
+    };
+
+    match self.trainee().map(|t| &trainees[t].name) {
+      Some(name) if !name.is_empty() => format!("{name}: {description}"),
+      _ => description,
+    }
+  }
+}
+
+/// `trainees` und `capacity`, in denen die Vorgaben aus `relaxed` aufgehoben sind
+fn relax(
+  trainees: &[Praktikant],
+  capacity: &Capacity,
+  relaxed: &[Constraint],
+) -> (Vec<Praktikant>, Capacity) {
+  let keep = |constraint| !relaxed.contains(&constraint);
+  // Die Einträge aus `list`, deren Vorgabe nicht aufgehoben ist
+  fn kept<T: Clone>(
+    list: &[T],
+    relaxed: &[Constraint],
+    constraint: impl Fn(usize) -> Constraint,
+  ) -> Vec<T> {
+    list
+      .iter()
+      .enumerate()
+      .filter(|&(i, _)| !relaxed.contains(&constraint(i)))
+      .map(|(_, item)| item.clone())
+      .collect()
+  }
+
+  let trainees: Vec<Praktikant> = trainees
+    .iter()
+    .enumerate()
+    .map(|(t, trainee)| {
+      let mut trainee = trainee.clone();
+      let pins = &mut trainee.pins;
+      pins.pinned = kept(&pins.pinned, relaxed, |i| Constraint::Pinned(t, i));
+      pins.forbidden = kept(&pins.forbidden, relaxed, |i| Constraint::Forbidden(t, i));
+      trainee.excluded_teachers = kept(&trainee.excluded_teachers, relaxed, |i| {
+        Constraint::ExcludedTeacher(t, i)
+      });
+      pins.double_lessons &= keep(Constraint::DoubleLessons(t));
+      if !keep(Constraint::Availability(t)) {
+        trainee.availability = Availability::default();
+      }
+
+      let workload = &mut trainee.workload;
+      for (constraint, limit) in [
+        (Constraint::MinPerWeek(t), &mut workload.min_per_week),
+        (Constraint::MaxPerWeek(t), &mut workload.max_per_week),
+        (Constraint::MinPerDay(t), &mut workload.min_per_day),
+        (Constraint::MaxPerDay(t), &mut workload.max_per_day),
+        (Constraint::MaxGaps(t), &mut workload.max_gaps),
+        (
+          Constraint::MaxMentorLessons(t),
+          &mut trainee.mentors.max_lessons,
+        ),
+      ] {
+        if !keep(constraint) {
+          *limit = None;
+        }
+      }
+      trainee.mentors.every_subject &= keep(Constraint::EverySubject(t));
+      for (i, coverage) in trainee.coverage.iter_mut().enumerate() {
+        if !keep(Constraint::Coverage(t, i)) {
+          coverage.min_lessons = 0;
+        }
+      }
+
+      trainee
+    })
+    .collect();
+
+  let capacity = Capacity {
+    per_lesson: if keep(Constraint::PerLesson) {
+      capacity.per_lesson
+    } else {
+      trainees.len()
+    },
+    per_class: capacity.per_class.filter(|_| keep(Constraint::PerClass)),
+    per_mentor: capacity.per_mentor.filter(|_| keep(Constraint::PerMentor)),
+  };
+
+  (trainees, capacity)
+}
+
+/// Die Vorgaben lassen gemeinsam eine gültige Lösung zu. Fehler beim Aufbau des Problems, z.B.
+/// widersprüchliche Festlegungen, gelten als unlösbar.
+fn feasible(
+  plan: &WilliStundenplan,
+  trainees: &[Praktikant],
+  capacity: &Capacity,
+  objective: &Objective,
+) -> bool {
+  Problem::build(plan, trainees, capacity, objective)
+    .is_ok_and(|(problem, _)| problem.feasible().unwrap_or(false))
+}
+
+/// Beschreibungen einer Menge von Vorgaben, die sich widersprechen, und ob sie minimal ist, siehe
+/// [`conflicting`]
+pub(crate) fn conflicts(
+  plan: &WilliStundenplan,
+  trainees: &[Praktikant],
+  capacity: &Capacity,
+  objective: &Objective,
+  options: &SolveOptions,
+) -> (Vec<String>, bool) {
+  let (constraints, minimal) = conflicting(plan, trainees, capacity, objective, options);
+  let descriptions = constraints
+    .into_iter()
+    .map(|c| c.describe(trainees, capacity))
+    .collect();
+  (descriptions, minimal)
+}
+
+/// Eine Menge von Vorgaben, die sich widersprechen, und ob sie minimal ist. Leer, wenn die Vorgaben
+/// lösbar sind, der Widerspruch auch ohne alle aufhebbaren Vorgaben besteht oder die Grenzen aus
+/// `options` schon vor der ersten Prüfung erreicht sind.
+fn conflicting(
+  plan: &WilliStundenplan,
+  trainees: &[Praktikant],
+  capacity: &Capacity,
+  objective: &Objective,
+  options: &SolveOptions,
+) -> (Vec<Constraint>, bool) {
+  let mut limits = Limits::new(options);
+  let mut checks = 0;
+  // `None`, sobald eine der Grenzen erreicht ist
+  let mut check = |trainees: &[Praktikant], capacity: &Capacity| {
+    if limits.check_now(checks) {
+      return None;
+    }
+    checks += 1;
+    Some(feasible(plan, trainees, capacity, objective))
+  };
+
+  match check(trainees, capacity) {
+    Some(false) => {}
+    Some(true) => return (vec![], true),
+    None => return (vec![], false),
+  }
+
+  let constraints = Constraint::all(trainees, capacity);
+  let mut relaxed = vec![];
+  let mut minimal = true;
+  for &constraint in &constraints {
+    relaxed.push(constraint);
+    let (t, c) = relax(trainees, capacity, &relaxed);
+    match check(&t, &c) {
+      Some(false) => {}
+      // Ohne diese Vorgabe wäre das Problem lösbar, sie gehört also zum Widerspruch
+      Some(true) => {
+        relaxed.pop();
+      }
+      // Die übrigen Vorgaben bleiben ungeprüft im Ergebnis
+      None => {
+        relaxed.pop();
+        minimal = false;
+        break;
+      }
+    }
+  }
+
+  let conflicting = constraints
+    .into_iter()
+    .filter(|c| !relaxed.contains(c))
+    .collect();
+  (conflicting, minimal)
+}
+
+#[cfg(test)]
+mod tests {
+  use willi::WilliStundenplan;
+
+  use crate::{
+    Availability, Backend, Capacity, LessonFilter, Objective, Pins, Praktikant, Problem, SlotRef,
+    SolveOptions, Workload,
+    testing::{plan, solve, trainee},
+  };
+
+  use super::{Constraint, EmptyReason, conflicting, feasible, relax};
+
+  /// Ohne alle Vorgaben außer `conflict` bleibt das Problem unlösbar, ohne eine beliebige davon
+  /// wird es lösbar
+  fn assert_minimal(
+    plan: &WilliStundenplan,
+    trainees: &[Praktikant],
+    capacity: &Capacity,
+    conflict: &[Constraint],
+  ) {
+    let objective = Objective::default();
+    let relaxed: Vec<Constraint> = Constraint::all(trainees, capacity)
+      .into_iter()
+      .filter(|c| !conflict.contains(c))
+      .collect();
+    let (t, c) = relax(trainees, capacity, &relaxed);
+    assert!(!feasible(plan, &t, &c, &objective));
+
+    for &constraint in conflict {
+      let mut without = relaxed.clone();
+      without.push(constraint);
+      let (t, c) = relax(trainees, capacity, &without);
+      assert!(feasible(plan, &t, &c, &objective), "{constraint:?}");
+    }
+  }
+
+  /// Mehr Stunden je Woche, als an den Tagen erlaubt sind, neben Vorgaben, die nichts zum
+  /// Widerspruch beitragen
+  fn overloaded() -> Praktikant {
+    Praktikant {
+      excluded_teachers: vec!["L001".to_string()],
+      pins: Pins {
+        forbidden: vec![LessonFilter {
+          day: Some(0),
+          period: Some(0),
+          ..Default::default()
+        }],
+        ..Default::default()
+      },
+      workload: Workload {
+        min_per_week: Some(5),
+        max_per_day: Some(2),
+        max_gaps: Some(1),
+        ..Default::default()
+      },
+      ..trainee("A")
+    }
+  }
+
+  #[test]
+  fn minimal_for_one_trainee() {
+    for seed in 1..4 {
+      let plan = plan(seed);
+      let trainees = [overloaded()];
+      let capacity = Capacity::default();
+
+      let (conflict, minimal) = conflicting(
+        &plan,
+        &trainees,
+        &capacity,
+        &Objective::default(),
+        &SolveOptions::default(),
+      );
+      assert!(minimal);
+      assert_eq!(
+        conflict,
+        [Constraint::MinPerWeek(0), Constraint::MaxPerDay(0)],
+        "Seed {seed}"
+      );
+      assert_minimal(&plan, &trainees, &capacity, &conflict);
+    }
+  }
+
+  /// Zwei Praktikanten sind auf dieselbe Stunde festgelegt, die nur einen aufnimmt
+  #[test]
+  fn minimal_for_shared_lesson() {
+    let plan = plan(1);
+    let (problem, _) = Problem::build(
+      &plan,
+      &[trainee("A")],
+      &Capacity::default(),
+      &Objective::default(),
+    )
+    .unwrap();
+    let &(_, pl_index) = problem.trainees[0]
+      .schedule
+      .iter()
+      .flatten()
+      .next()
+      .unwrap();
+    let pinned = Praktikant {
+      pins: Pins {
+        pinned: vec![LessonFilter {
+          pl_index: Some(pl_index),
+          ..Default::default()
+        }],
+        ..Default::default()
+      },
+      ..trainee("A")
+    };
+    let trainees = [
+      pinned.clone(),
+      Praktikant {
+        name: "B".to_string(),
+        ..pinned
+      },
+    ];
+    let capacity = Capacity::default();
+
+    let (conflict, minimal) = conflicting(
+      &plan,
+      &trainees,
+      &capacity,
+      &Objective::default(),
+      &SolveOptions::default(),
+    );
+    assert!(minimal);
+    assert_eq!(
+      conflict,
+      [
+        Constraint::Pinned(0, 0),
+        Constraint::Pinned(1, 0),
+        Constraint::PerLesson
+      ]
+    );
+    assert_minimal(&plan, &trainees, &capacity, &conflict);
+  }
+
+  /// Vorzeitig beendet, ist das Ergebnis weiterhin unlösbar, aber als nicht minimal markiert
+  #[test]
+  fn stopped_early() {
+    let plan = plan(1);
+    let trainees = [overloaded()];
+    let capacity = Capacity::default();
+    let objective = Objective::default();
+
+    let (conflict, minimal) = conflicting(
+      &plan,
+      &trainees,
+      &capacity,
+      &objective,
+      &SolveOptions {
+        node_limit: Some(2),
+        ..Default::default()
+      },
+    );
+    assert!(!minimal);
+    assert!(conflict.len() > 1);
+
+    let relaxed: Vec<Constraint> = Constraint::all(&trainees, &capacity)
+      .into_iter()
+      .filter(|c| !conflict.contains(c))
+      .collect();
+    let (t, c) = relax(&trainees, &capacity, &relaxed);
+    assert!(!feasible(&plan, &t, &c, &objective));
+  }
+
+  /// Jede leere Stunde hat genau eine Begründung, die zu den Vorgaben passt
+  #[test]
+  fn empty_reasons() {
+    let plan = plan(1);
+    let (problem, timeslots) = Problem::build(
+      &plan,
+      &[trainee("A")],
+      &Capacity::default(),
+      &Objective::default(),
+    )
+    .unwrap();
+    let forbidden = (0..timeslots.len())
+      .find(|&slot| timeslots[slot].day == 0 && problem.trainees[0].has_candidates(slot))
+      .unwrap();
+    let forbidden = SlotRef {
+      day: timeslots[forbidden].day,
+      period: timeslots[forbidden].period,
+    };
+
+    let trainees = [Praktikant {
+      availability: Availability {
+        unavailable_days: vec![1],
+        ..Default::default()
+      },
+      pins: Pins {
+        forbidden: vec![LessonFilter {
+          day: Some(forbidden.day),
+          period: Some(forbidden.period),
+          ..Default::default()
+        }],
+        ..Default::default()
+      },
+      ..trainee("A")
+    }];
+    let result = solve(&plan, &trainees, &Capacity::default(), Backend::Search).unwrap();
+    let breakdown = &result.plans[0].breakdown;
+
+    let slots: Vec<SlotRef> = breakdown.empty_reasons.iter().map(|e| e.slot).collect();
+    assert_eq!(slots, breakdown.empty_slots);
+    for empty in &breakdown.empty_reasons {
+      let expected = if empty.slot.day == 1 {
+        EmptyReason::Unavailable
+      } else if empty.slot == forbidden {
+        EmptyReason::Forbidden
+      } else if breakdown.blocked_slots.contains(&empty.slot) {
+        EmptyReason::LeftEmpty
+      } else {
+        EmptyReason::NoLesson
+      };
+      assert_eq!(empty.reason, expected, "{:?}", empty.slot);
+    }
+    assert!(breakdown.empty_slots.contains(&forbidden));
+  }
+}
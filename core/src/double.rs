@@ -0,0 +1,242 @@
+//! Doppelstunden.
+//!
+//! Fächer mit `FachEigenschaft::D` und Unterricht mit `doppmin` bzw. `doppmax` in der U-Zeile
+//! werden in Doppelstunden erteilt. Als Doppelstunde gelten zwei Einträge im Lehrerplan mit
+//! derselben Lehrkraft, Klasse und demselben Fach in aufeinanderfolgenden Unterrichtsstunden
+//! eines Tages. Längere Folgen werden von vorne in Paare geteilt, mit `doppmax` höchstens so viele
+//! Paare je U-Zeile. `doppmax = 0` schließt Doppelstunden aus.
+//!
+//! Mit [`Pins::double_lessons`](crate::Pins) besucht ein Praktikant beide Hälften oder keine.
+//! Hälften, deren Gegenstück er nicht besuchen kann, z.B. wegen der
+//! [`Availability`](crate::Availability), entfernt [`DoubleLessons::prune`] vor der Suche.
+
+use std::collections::{BTreeMap, HashSet};
+
+use ndarray::Array2;
+use willi::{FachEigenschaft, WilliStundenplan};
+
+use crate::Problem;
+
+/// Paare von Einträgen im Lehrerplan, die eine Doppelstunde bilden
+#[derive(Debug, Default)]
+pub(crate) struct DoubleLessons {
+  // [pl_index] = (slot, pl_index) der anderen Hälfte
+  partner: Vec<Option<(usize, usize)>>,
+}
+
+impl DoubleLessons {
+  /// Sucht die Doppelstunden unter den Einträgen, die laut `lessons` (`[pl_index] =
+  /// Option<(class, slot)>`) in eine Unterrichtsstunde fallen
+  pub(crate) fn detect(
+    plan: &WilliStundenplan,
+    lessons: &[Option<(usize, usize)>],
+    slot_day: &[usize],
+  ) -> Self {
+    let lines = plan.lehrerstunden();
+    let mut doubles = DoubleLessons {
+      partner: vec![None; lines.len()],
+    };
+
+    // [(lehrkraft, klasse, fach)] = [(slot, pl_index)]
+    let mut runs: BTreeMap<_, Vec<(usize, usize)>> = BTreeMap::new();
+    for (pl_index, line) in lines.iter().enumerate() {
+      if let Some((_, slot)) = lessons[pl_index] {
+        runs
+          .entry((line.lehrkraft, line.klasse, line.fach))
+          .or_default()
+          .push((slot, pl_index));
+      }
+    }
+
+    for ((lehrkraft, klasse, fach), mut run) in runs {
+      let unterricht = plan
+        .unterrichtseinheiten()
+        .values()
+        .find(|u| u.lehrkraft == lehrkraft && u.klasse == klasse && u.fach == fach);
+      let flagged = plan
+        .fach(fach)
+        .is_some_and(|f| matches!(f.eigenschaft, Some(FachEigenschaft::D)));
+      let max_pairs = match unterricht.and_then(|u| u.doppmax) {
+        Some(max) => max,
+        None if flagged || unterricht.is_some_and(|u| u.doppmin.is_some_and(|min| min > 0)) => {
+          usize::MAX
+        }
+        None => continue,
+      };
+
+      run.sort_unstable();
+      let mut pairs = 0;
+      let mut i = 0;
+      while i + 1 < run.len() && pairs < max_pairs {
+        let (first_slot, first) = run[i];
+        let (second_slot, second) = run[i + 1];
+        if second_slot == first_slot + 1 && slot_day[first_slot] == slot_day[second_slot] {
+          doubles.partner[first] = Some((second_slot, second));
+          doubles.partner[second] = Some((first_slot, first));
+          pairs += 1;
+          i += 2;
+        } else {
+          i += 1;
+        }
+      }
+    }
+
+    doubles
+  }
+
+  /// `(slot, pl_index)` der anderen Hälfte, falls `pl_index` zu einer Doppelstunde gehört
+  fn partner(&self, pl_index: usize) -> Option<(usize, usize)> {
+    self.partner[pl_index]
+  }
+
+  /// Entfernt aus `schedule` (`[class][slot] = [(subject, pl_index)]`) jede Hälfte, deren andere
+  /// Hälfte fehlt
+  pub(crate) fn prune(&self, schedule: &mut Array2<Vec<(usize, usize)>>) {
+    let mut broken = HashSet::new();
+    for ((class, _), lessons) in schedule.indexed_iter() {
+      for &(_, pl_index) in lessons {
+        if let Some((slot, partner)) = self.partner(pl_index)
+          && !schedule[[class, slot]].iter().any(|&(_, pl)| pl == partner)
+        {
+          broken.insert(pl_index);
+          broken.insert(partner);
+        }
+      }
+    }
+
+    for lessons in schedule.iter_mut() {
+      lessons.retain(|(_, pl_index)| !broken.contains(pl_index));
+    }
+  }
+}
+
+impl Problem {
+  /// `(slot, pl_index)` der anderen Hälfte, falls `pl_index` zu einer Doppelstunde gehört und
+  /// `trainee` Doppelstunden nur ganz besucht
+  pub(crate) fn double_partner(&self, trainee: usize, pl_index: usize) -> Option<(usize, usize)> {
+    if !self.trainees[trainee].double_lessons {
+      return None;
+    }
+    self.doubles.partner(pl_index)
+  }
+
+  /// Zweite Hälfte einer Doppelstunde, die `trainee` in `slot` besuchen muss, weil er mit
+  /// `previous` in der Stunde davor die erste Hälfte besucht
+  pub(crate) fn second_half(
+    &self,
+    trainee: usize,
+    slot: usize,
+    previous: Option<usize>,
+  ) -> Option<usize> {
+    previous
+      .and_then(|pl_index| self.double_partner(trainee, pl_index))
+      .filter(|&(other, _)| other == slot)
+      .map(|(_, second)| second)
+  }
+
+  /// Anzahl der Hälften von Doppelstunden, deren andere Hälfte `trainee` nicht besucht.
+  /// `assignment(slot)` gibt den besuchten Eintrag im Lehrerplan an.
+  pub(crate) fn broken_doubles(
+    &self,
+    trainee: usize,
+    assignment: impl Fn(usize) -> Option<usize>,
+  ) -> usize {
+    (0..self.time_slots)
+      .filter(|&slot| {
+        assignment(slot)
+          .and_then(|pl_index| self.double_partner(trainee, pl_index))
+          .is_some_and(|(other, partner)| assignment(other) != Some(partner))
+      })
+      .count()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use willi::{SyntheticFach, SyntheticParams, WilliStundenplan};
+
+  use super::DoubleLessons;
+  use crate::{
+    Backend, Capacity, Pins, Praktikant,
+    testing::{solve, trainee, unfilled},
+  };
+
+  /// Kleiner Plan mit 2 Tagen à 5 Stunden, in dem Mathematik in Doppelstunden unterrichtet wird
+  fn plan(seed: u64) -> WilliStundenplan {
+    WilliStundenplan::synthetic(&SyntheticParams {
+      tage: 2,
+      stunden: 5,
+      klassen: 4,
+      lehrkraefte: 12,
+      // Wenige Fächer, damit Mathematik mehr als eine Stunde je Klasse erhält
+      faecher: vec![
+        SyntheticFach {
+          doppelstunden: true,
+          ..SyntheticFach::new("M", "Mathematik", 4.0)
+        },
+        SyntheticFach::new("D", "Deutsch", 4.0),
+        SyntheticFach::new("E", "Englisch", 4.0),
+        SyntheticFach::new("B", "Biologie", 2.0),
+      ],
+      seed,
+      ..Default::default()
+    })
+  }
+
+  /// Mit [`Pins::double_lessons`] besucht kein Verfahren nur eine Hälfte einer Doppelstunde, und
+  /// Suche und MILP finden dieselbe Lösung
+  #[test]
+  fn never_split() {
+    let trainees = [Praktikant {
+      pins: Pins {
+        double_lessons: true,
+        ..Default::default()
+      },
+      ..trainee("A")
+    }];
+    let mut visited_doubles = 0;
+
+    for seed in 1..4 {
+      let plan = plan(seed);
+      // Alle Stunden des Rasters haben Unterricht, die Stunde ergibt sich also direkt aus der Zeile
+      let lessons: Vec<_> = plan
+        .lehrerstunden()
+        .iter()
+        .map(|line| {
+          let slot = (line.tag_stunde.tag.0 - 1) * 5 + line.tag_stunde.stunde.0 - 1;
+          Some((line.klasse.0, slot))
+        })
+        .collect();
+      let slot_day: Vec<usize> = (0..10).map(|slot| slot / 5).collect();
+      let doubles = DoubleLessons::detect(&plan, &lessons, &slot_day);
+
+      let mut costs = vec![];
+      for backend in [Backend::Search, Backend::Milp, Backend::LocalSearch] {
+        let result = solve(&plan, &trainees, &Capacity::default(), backend).unwrap();
+        let visited: Vec<usize> = result.plans[0]
+          .solution
+          .iter()
+          .flatten()
+          .flatten()
+          .copied()
+          .collect();
+
+        for &pl_index in &visited {
+          if let Some((_, partner)) = doubles.partner(pl_index) {
+            assert!(
+              visited.contains(&partner),
+              "Seed {seed}, {backend:?}: {pl_index} ohne {partner}"
+            );
+            visited_doubles += 1;
+          }
+        }
+        costs.push((unfilled(&result), result.cost));
+      }
+
+      assert_eq!(costs[0].0, costs[1].0, "Seed {seed}");
+      assert!((costs[0].1 - costs[1].1).abs() < 1e-6, "Seed {seed}");
+    }
+
+    assert!(visited_doubles > 0);
+  }
+}
@@ -0,0 +1,215 @@
+use thiserror::Error;
+use wasm_bindgen::prelude::*;
+
+use crate::StopReason;
+
+#[derive(Debug, Error)]
+pub enum PlannerError {
+  /// Mit einer Menge von Vorgaben, die sich widersprechen, soweit sie sich bestimmen lässt.
+  /// `minimal` ist nicht gesetzt, wenn die Grenzen der Suche die Analyse vorzeitig beendet haben.
+  #[error("Keine gültige Lösung gefunden{}", describe_conflicts(conflicts, *minimal))]
+  NoFeasiblePlan {
+    conflicts: Vec<String>,
+    minimal: bool,
+  },
+  #[error("Das Fach „{0}“ ist im Plan nicht vorhanden")]
+  UnknownSubject(String),
+  #[error("Das Fach „{0}“ wurde mehrfach angegeben")]
+  DuplicateSubject(String),
+  #[error("Eintrag {pl_index} im Lehrerplan verweist auf nicht vorhandene {table} „{kuerzel}“")]
+  DanglingReference {
+    pl_index: usize,
+    table: &'static str,
+    kuerzel: String,
+  },
+  #[error("Der Plan enthält keine Unterrichtsstunden")]
+  EmptyTimeslotGrid,
+  #[error("Ungültige Gewichtung — {0}")]
+  InvalidWeights(String),
+  #[error("Suche beendet, bevor eine Lösung gefunden wurde — {0}")]
+  Stopped(StopReason),
+  #[error("Ungültige Optionen — {0}")]
+  InvalidOptions(String),
+  #[error("Festlegungen widersprechen sich — {0}")]
+  ConflictingPins(String),
+  #[error("Fehler im MILP-Solver — {0}")]
+  Solver(String),
+  #[error("Ergebnis konnte nicht serialisiert werden — {0}")]
+  Serialization(String),
+}
+
+impl PlannerError {
+  /// Maschinenlesbare Art des Fehlers, z.B. für die Fehlerbehandlung im Frontend
+  pub fn kind(&self) -> &'static str {
+    match self {
+      PlannerError::NoFeasiblePlan { .. } => "no_feasible_plan",
+      PlannerError::UnknownSubject(_) => "unknown_subject",
+      PlannerError::DuplicateSubject(_) => "duplicate_subject",
+      PlannerError::DanglingReference { .. } => "dangling_reference",
+      PlannerError::EmptyTimeslotGrid => "empty_timeslot_grid",
+      PlannerError::InvalidWeights(_) => "invalid_weights",
+      PlannerError::Stopped(_) => "stopped",
+      PlannerError::InvalidOptions(_) => "invalid_options",
+      PlannerError::ConflictingPins(_) => "conflicting_pins",
+      PlannerError::Solver(_) => "solver",
+      PlannerError::Serialization(_) => "serialization",
+    }
+  }
+}
+
+fn describe_conflicts(conflicts: &[String], minimal: bool) -> String {
+  if conflicts.is_empty() {
+    return String::new();
+  }
+  let incomplete = if minimal {
+    ""
+  } else {
+    " (Analyse vorzeitig beendet, die Auswahl ist nicht minimal)"
+  };
+  format!(
+    " — diese Vorgaben widersprechen sich{incomplete}: {}",
+    conflicts.join("; ")
+  )
+}
+
+#[derive(Clone, Debug)]
+#[wasm_bindgen(js_name = PlannerError)]
+pub struct WasmPlannerError {
+  /// Siehe [`PlannerError::kind`]
+  #[wasm_bindgen(getter_with_clone)]
+  pub kind: String,
+  #[wasm_bindgen(getter_with_clone)]
+  pub message: String,
+}
+
+impl From<PlannerError> for WasmPlannerError {
+  fn from(error: PlannerError) -> Self {
+    WasmPlannerError {
+      kind: error.kind().to_string(),
+      message: error.to_string(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use willi::{SyntheticParams, WilliStundenplan};
+
+  use super::*;
+  use crate::{
+    FachGewichtung, Objective, Praktikant, SolveOptions, generate, observer::NoopObserver,
+  };
+
+  fn plan() -> WilliStundenplan {
+    WilliStundenplan::synthetic(&SyntheticParams {
+      tage: 2,
+      stunden: 5,
+      klassen: 4,
+      lehrkraefte: 12,
+      ..Default::default()
+    })
+  }
+
+  fn subjects(kuerzel: &[&str]) -> Vec<FachGewichtung> {
+    kuerzel
+      .iter()
+      .map(|k| FachGewichtung {
+        kuerzel: k.to_string(),
+        gewicht: 1.0,
+      })
+      .collect()
+  }
+
+  fn solve(plan: &WilliStundenplan, kuerzel: &[&str]) -> Result<(), PlannerError> {
+    generate(
+      plan,
+      &Praktikant {
+        subjects: subjects(kuerzel),
+        ..Default::default()
+      },
+      &Objective::default(),
+      &SolveOptions::default(),
+      &mut NoopObserver,
+    )
+    .map(|_| ())
+  }
+
+  #[test]
+  fn kinds() {
+    let errors = [
+      (
+        PlannerError::NoFeasiblePlan {
+          conflicts: vec![],
+          minimal: true,
+        },
+        "no_feasible_plan",
+      ),
+      (PlannerError::UnknownSubject("Xy".into()), "unknown_subject"),
+      (
+        PlannerError::DuplicateSubject("M".into()),
+        "duplicate_subject",
+      ),
+      (
+        PlannerError::DanglingReference {
+          pl_index: 3,
+          table: "Klasse",
+          kuerzel: "K9".into(),
+        },
+        "dangling_reference",
+      ),
+      (PlannerError::EmptyTimeslotGrid, "empty_timeslot_grid"),
+      (PlannerError::InvalidWeights("x".into()), "invalid_weights"),
+      (PlannerError::Stopped(StopReason::Cancelled), "stopped"),
+      (PlannerError::InvalidOptions("x".into()), "invalid_options"),
+      (PlannerError::Solver("x".into()), "solver"),
+      (PlannerError::Serialization("x".into()), "serialization"),
+    ];
+
+    for (error, kind) in errors {
+      assert_eq!(error.kind(), kind);
+
+      let message = error.to_string();
+      let wasm = WasmPlannerError::from(error);
+      assert_eq!(wasm.kind, kind);
+      assert_eq!(wasm.message, message);
+    }
+  }
+
+  #[test]
+  fn unknown_subject() {
+    let error = solve(&plan(), &["M", "Xy"]).unwrap_err();
+
+    assert!(matches!(&error, PlannerError::UnknownSubject(k) if k == "Xy"));
+    let wasm = WasmPlannerError::from(error);
+    assert_eq!(wasm.kind, "unknown_subject");
+    assert_eq!(wasm.message, "Das Fach „Xy“ ist im Plan nicht vorhanden");
+  }
+
+  #[test]
+  fn dangling_reference() {
+    // Beim Einlesen werden alle Verweise aufgelöst; nicht vorhandene Klassen kann nur ein
+    // anderweitig erzeugter Plan enthalten.
+    let plan = plan();
+    let fach = plan
+      .fach(plan.lehrerstunden()[0].fach)
+      .unwrap()
+      .kuerzel
+      .clone();
+    let mut json = serde_json::to_value(&plan).unwrap();
+    json["stunden_lehrerplan"][0]["klasse"] = 99.into();
+    let plan: WilliStundenplan = serde_json::from_str(&json.to_string()).unwrap();
+
+    let error = solve(&plan, &[&fach]).unwrap_err();
+
+    assert!(matches!(
+      &error,
+      PlannerError::DanglingReference { pl_index: 0, table: "Klasse", kuerzel } if kuerzel == "K99"
+    ));
+    let wasm = WasmPlannerError::from(error);
+    assert_eq!(wasm.kind, "dangling_reference");
+    assert_eq!(
+      wasm.message,
+      "Eintrag 0 im Lehrerplan verweist auf nicht vorhandene Klasse „K99“"
+    );
+  }
+}
@@ -1,230 +1,748 @@
-use std::cell::LazyCell;
+#![allow(clippy::four_forward_slashes)]
 
+use std::{collections::HashMap, ops::Range};
+
+use alternatives::{Excluded, ProgressOnly};
+use bound::LowerBound;
+use continuity::{ClassLoad, PairLessons};
+use coverage::CoverageGroup;
+use double::DoubleLessons;
+use mentors::MentorLoad;
 use ndarray::Array2;
-use serde::Serialize;
-use tracing::{debug, info, trace, warn};
+use options::{JsSolveOptions, Limits};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+use trainee::{Occupancy, Trainee};
 use wasm_bindgen::prelude::*;
 use wasm_tracing::WasmLayerConfig;
-use willi::WilliStundenplan;
+use willi::{
+  KlasseId, KlassenZeile, LehrerStundenZeile, LehrkraftId, StundeId, TagId, TagStunde,
+  WilliStundenplan,
+};
+use workload::ForcedEmpty;
+
+mod alternatives;
+mod availability;
+mod bound;
+mod breakdown;
+mod continuity;
+mod coverage;
+mod diagnostics;
+mod double;
+mod error;
+mod local_search;
+mod mentors;
+mod milp;
+mod objective;
+mod observer;
+mod options;
+mod pins;
+#[cfg(test)]
+mod testing;
+mod trainee;
+mod workload;
 
-const GLOBAL: LazyCell<web_sys::DedicatedWorkerGlobalScope> =
-  LazyCell::new(|| js_sys::global().unchecked_into());
+pub use alternatives::{Alternative, MultiAlternative};
+pub use availability::Availability;
+pub use breakdown::{
+  ClassesCost, ContinuityCost, CostBreakdown, CoverageCost, DroppedLesson, FollowedPair, GapsCost,
+  MentorsCost, SlotRef, SubjectCost, WorkloadCost,
+};
+pub use coverage::GradeCoverage;
+pub use diagnostics::{EmptyReason, EmptySlot};
+pub use error::{PlannerError, WasmPlannerError};
+pub use mentors::Mentors;
+pub use objective::Objective;
+pub use observer::{
+  CallbackObserver, ChannelObserver, NoopObserver, Progress, SolverEvent, SolverObserver,
+  WorkerObserver,
+};
+pub use options::{Backend, CancellationToken, SolveOptions, StopReason};
+pub use pins::{LessonFilter, Pins};
+pub use trainee::{Capacity, Praktikant};
+pub use workload::Workload;
 
 #[wasm_bindgen]
 #[derive(Debug)]
 pub struct Problem {
   time_slots: usize,
+  // [day] = Stunden dieses Tages, nur Tage mit Unterrichtsstunden
+  days: Vec<Range<usize>>,
+  // [slot] = Index in `days`
+  slot_day: Vec<usize>,
   classes: usize,
-  // [subject] = weight
-  /// Weights must add up to 1
-  subject_weights: Vec<f64>,
-  // [class][slot] = Option<(subject, pl_index)>
-  schedule: Array2<Option<(usize, usize)>>,
+  // [class] = Kürzel
+  class_kuerzel: Vec<String>,
+  // [teacher] = Kürzel
+  teacher_kuerzel: Vec<String>,
+  // [pl_index] = teacher
+  lesson_teacher: Vec<usize>,
+  doubles: DoubleLessons,
+  // Stunden des Rasters, die laut `stundenmerkmale` keine Unterrichtsstunden sind
+  non_lesson_periods: Vec<SlotRef>,
+  trainees: Vec<Trainee>,
+  capacity: Capacity,
+  // [slot] = Mindestanzahl leerer Stunden ab `slot`, siehe `bound::blocked_slots`
+  blocked: Vec<usize>,
+  objective: Objective,
 }
 
-#[derive(Debug)]
+/// Eine Unterrichtsstunde im Wochenraster, in der der Praktikant eingeplant werden kann
+#[derive(Debug, Clone, Copy)]
+struct Timeslot {
+  tag: TagId,
+  stunde: StundeId,
+  /// Position des Tages in der Woche, d.h. die Zeile in der Ausgabe von [`generate`]
+  day: usize,
+  /// Position der Stunde im Tagesraster, d.h. die Spalte in der Ausgabe von [`generate`]
+  period: usize,
+}
+
+#[derive(Debug, Clone)]
 pub struct Solution {
   // [slot] = Option<pl_index>
   assignments: Vec<Option<usize>>,
 }
 
-#[derive(Serialize)]
-struct Progress {
-  best: f64,
-  current_cost: f64,
-  current_classes: usize,
+/// Ergebnis von [`generate`]
+#[derive(Debug, Clone, Serialize)]
+pub struct SolveResult {
+  /// Eine zwei-dimensionale Liste der Form `[tag][stunde] = [index]`, siehe [`generate`]
+  pub solution: Vec<Vec<Option<usize>>>,
+  pub cost: f64,
+  /// Die Lösung ist bewiesen optimal. Bei [`Backend::LocalSearch`] oder nach einem vorzeitigen
+  /// Abbruch nie gesetzt.
+  pub optimal: bool,
+  /// Grund für einen vorzeitigen Abbruch der Suche
+  pub stopped: Option<StopReason>,
+  /// Anzahl der besuchten Knoten, bei [`Backend::Milp`] der gelösten Durchläufe
+  pub visited: usize,
+  pub breakdown: CostBreakdown,
+  /// Weitere Lösungen nach steigenden Kosten, siehe [`SolveOptions::alternatives`]
+  pub alternatives: Vec<Alternative>,
+}
+
+/// Plan eines einzelnen Praktikanten
+#[derive(Debug, Clone, Serialize)]
+pub struct TraineePlan {
+  /// Siehe [`Praktikant::name`]
+  pub name: String,
+  /// Siehe [`SolveResult::solution`]
+  pub solution: Vec<Vec<Option<usize>>>,
+  pub breakdown: CostBreakdown,
+}
+
+/// Ergebnis von [`generate_many`]
+#[derive(Debug, Clone, Serialize)]
+pub struct MultiSolveResult {
+  /// Ein Plan je Praktikant in der übergebenen Reihenfolge
+  pub plans: Vec<TraineePlan>,
+  /// Summe der Kosten aller Pläne
+  pub cost: f64,
+  /// Siehe [`SolveResult::optimal`]
+  pub optimal: bool,
+  pub stopped: Option<StopReason>,
+  pub visited: usize,
+  /// Siehe [`SolveResult::alternatives`]
+  pub alternatives: Vec<MultiAlternative>,
+}
+
+/// Ergebnis eines Lösungsverfahrens, bevor es von [`generate_many`] aufbereitet wird
+struct Outcome {
+  // [trainee] = Belegung
+  best: Option<Vec<Solution>>,
+  cost: f64,
+  /// Anzahl der besuchten Knoten, sofern das Verfahren sie zählt
   visited: usize,
+  stopped: Option<StopReason>,
+  /// Das Verfahren hat bewiesen, dass es keine bessere Lösung gibt
+  optimal: bool,
+}
+
+/// Teilbelegung eines Praktikanten während der Suche
+struct TraineeState {
+  // [slot] = Option<pl_index>
+  current: Vec<Option<usize>>,
+  used_classes: Vec<usize>,
+  // [idx] = count; Where idx matches the index of that subject in `subject_targets`
+  subject_counts: Vec<usize>,
+  // [pin] = Anzahl Stunden, die diese `RequiredLesson` erfüllen
+  required: Vec<usize>,
+  // [day] = Anzahl belegter Stunden
+  day_lessons: Vec<usize>,
+  // [day] = zuletzt belegte Stunde
+  day_last: Vec<Option<usize>>,
+  // [day] = Anzahl Freistunden
+  day_gaps: Vec<usize>,
+  /// Summe von `day_gaps`
+  gaps: usize,
+  mentors: MentorLoad,
+  // [group] = Anzahl Stunden in dieser `CoverageGroup`
+  coverage: Vec<usize>,
+  pairs: PairLessons,
+}
+
+impl TraineeState {
+  /// Anzahl belegter Stunden in der Woche
+  fn lessons(&self) -> usize {
+    self.subject_counts.iter().sum()
+  }
 }
 
-#[derive(Serialize)]
-struct ProgressMessage {
-  r#type: String,
-  progress: Progress,
+/// Zustand der Suche, der über alle Rekursionsebenen hinweg geteilt wird
+struct Search<'a> {
+  // [trainee] = Teilbelegung
+  trainees: Vec<TraineeState>,
+  occupancy: Occupancy,
+  /// Anzahl der Stunden, die trotz möglichem Unterricht leer bleiben
+  unfilled: usize,
+  best: Option<Vec<Solution>>,
+  best_cost: f64,
+  best_unfilled: usize,
+  nodes_visited: usize,
+  // Map used to finalize the intermediate plans
+  timeslots: &'a [Timeslot],
+  observer: &'a mut dyn SolverObserver,
+  limits: Limits<'a>,
+  excluded: Excluded<'a>,
+  // [class] = Hilfsspeicher für `LowerBound::new_classes`
+  covered: Vec<bool>,
 }
 
-#[derive(Serialize)]
-struct SolutionMessage {
-  r#type: String,
-  solution: Vec<Vec<Option<usize>>>
+impl<'a> Search<'a> {
+  fn new(
+    problem: &Problem,
+    timeslots: &'a [Timeslot],
+    observer: &'a mut dyn SolverObserver,
+    options: &'a SolveOptions,
+    excluded: Excluded<'a>,
+  ) -> Self {
+    Search {
+      trainees: problem
+        .trainees
+        .iter()
+        .map(|trainee| TraineeState {
+          current: vec![None; problem.time_slots],
+          used_classes: vec![],
+          subject_counts: vec![0; trainee.subject_targets.len()],
+          required: vec![0; trainee.required.len()],
+          day_lessons: vec![0; problem.days.len()],
+          day_last: vec![None; problem.days.len()],
+          day_gaps: vec![0; problem.days.len()],
+          gaps: 0,
+          mentors: MentorLoad::default(),
+          coverage: vec![0; trainee.coverage.len()],
+          pairs: PairLessons::new(problem.classes, trainee.subject_targets.len()),
+        })
+        .collect(),
+      occupancy: Occupancy::new(problem),
+      unfilled: 0,
+      best: None,
+      best_cost: f64::INFINITY,
+      best_unfilled: usize::MAX,
+      nodes_visited: 0,
+      timeslots,
+      observer,
+      limits: Limits::new(options),
+      excluded,
+      covered: vec![false; problem.classes],
+    }
+  }
+
+  /// Eine Lösung mit `unfilled` leeren Stunden und Kosten `cost` wäre besser als die beste
+  /// bekannte: zuerst zählen die leeren Stunden, dann die Kosten
+  fn improves(&self, unfilled: usize, cost: f64) -> bool {
+    unfilled < self.best_unfilled
+      || (unfilled == self.best_unfilled && cost < self.best_cost - Problem::COST_EPSILON)
+  }
+
+  /// Anzahl der Stunden, die bei einer Lösung höchstens noch leer bleiben dürfen, damit sie
+  /// besser als die beste bekannte sein kann
+  fn slack(&self) -> usize {
+    self.best_unfilled.saturating_sub(self.unfilled)
+  }
 }
 
 impl Problem {
-  // Rekursiver Optimierungsalgorithmus
-  fn search(
+  /// Kosten, die sich kleiner als die beste bekannte Lösung unterscheiden, gelten als gleich.
+  /// Verhindert, dass Rundungsfehler in der Schranke einen gleichwertigen Zweig offen halten.
+  const COST_EPSILON: f64 = 1e-9;
+
+  // Kostenfunktion für einen Praktikanten
+  fn cost(
     &self,
-    slot: usize,
-    // Must be pre-filled with [time_slots] Nones
-    current: &mut Vec<Option<usize>>,
-    used_classes: &mut Vec<usize>,
-    // [idx] = count; Where idx matches the index of that subject in `subject_weights`
-    // must be pre-filled with zeroes
-    subject_counts: &mut Vec<usize>,
-    best: &mut Option<Solution>,
-    best_cost: &mut f64,
-    best_used_classes: &mut usize,
-    nodes_visited: &mut usize,
-    // Map used to finalize the intermediate plans
-    timeslots: &Vec<(&str, usize, usize)>,
-  ) {
-    // Gewichtung der gleichmäßigen verteilung der fächer. 0 = Verteilung wird ignoriert
-    const BALANCE_WT: f64 = 3.0;
-
-    // Kostenfunktion
-    fn cost(
-      problem: &Problem,
-      slot: usize,
-      used_classes: &mut Vec<usize>,
-      subject_counts: &mut Vec<usize>,
-    ) -> f64 {
-      // Strafe für Anzahl der Klassen
-      let num_classes = used_classes.len() as f64;
-
-      // Strafe für Abweichung von Gewichtung
-      let imbalance = subject_counts
+    trainee: usize,
+    classes: ClassLoad,
+    subject_counts: &[usize],
+    gaps: usize,
+    mentors: MentorLoad,
+    coverage: &[usize],
+  ) -> f64 {
+    let ClassLoad {
+      classes: num_classes,
+      pairs,
+    } = classes;
+    // Strafe für Anzahl der Klassen
+    let num_classes = num_classes as f64;
+
+    // Strafe für Abweichung von Gewichtung
+    let imbalance = subject_counts
+      .iter()
+      .zip(self.trainees[trainee].subject_targets.iter())
+      .map(|(&count, &target)| (count as f64 - target).abs() / target)
+      .sum::<f64>();
+
+    let Objective {
+      classes,
+      balance,
+      gaps: gap_weight,
+      mentors: mentor_weight,
+      preferred_mentor,
+      coverage: coverage_weight,
+      continuity,
+      workload: workload_weight,
+    } = self.objective;
+    let MentorLoad { mentors, elsewhere } = mentors;
+
+    // Strafe für fehlende Stunden in Jahrgangsstufen
+    let shortfall: usize = self.trainees[trainee]
+      .coverage
+      .iter()
+      .zip(coverage)
+      .map(|(group, &count)| group.shortfall(count))
+      .sum();
+
+    // Strafe für Abweichung von der angestrebten Anzahl Stunden
+    let deviation = self.trainees[trainee]
+      .workload
+      .deviation(subject_counts.iter().sum());
+
+    let cost = classes * num_classes
+      + balance * imbalance
+      + gap_weight * gaps as f64
+      + mentor_weight * mentors as f64
+      + preferred_mentor * elsewhere as f64
+      + coverage_weight * shortfall as f64
+      + continuity * pairs as f64
+      + workload_weight * deviation as f64;
+    debug!(
+      "Current branch cost: {cost} = ({classes} * {num_classes}) + ({balance} * {imbalance}) + ({gap_weight} * {gaps}) + ({mentor_weight} * {mentors}) + ({preferred_mentor} * {elsewhere}) + ({coverage_weight} * {shortfall}) + ({continuity} * {pairs}) + ({workload_weight} * {deviation})"
+    );
+
+    cost
+  }
+
+  /// Summe der Kosten aller Praktikanten
+  fn total_cost(&self, state: &Search) -> f64 {
+    state
+      .trainees
+      .iter()
+      .enumerate()
+      .map(|(trainee, s)| {
+        self.cost(
+          trainee,
+          ClassLoad {
+            classes: s.used_classes.len(),
+            pairs: s.pairs.pairs,
+          },
+          &s.subject_counts,
+          s.gaps,
+          s.mentors,
+          &s.coverage,
+        )
+      })
+      .sum()
+  }
+
+  /// Untere Schranke für die Kosten jeder Lösung, die die aktuelle Belegung fortsetzt und dabei
+  /// höchstens `slack` weitere Stunden leer lässt. Alle Praktikanten bis einschließlich `trainee`
+  /// haben `slot` bereits belegt, siehe [`LowerBound`].
+  fn lower_bound(&self, slot: usize, trainee: usize, slack: usize, state: &mut Search) -> f64 {
+    let mut bound = 0.0;
+
+    for (t, (problem, s)) in self.trainees.iter().zip(state.trainees.iter()).enumerate() {
+      let next = if t <= trainee { slot + 1 } else { slot };
+      // Mit einem Ziel je Woche darf jede weitere Stunde leer bleiben
+      let slack = if problem.workload.fills_all() {
+        slack
+      } else {
+        usize::MAX
+      };
+      let num_classes = s.used_classes.len()
+        + problem
+          .bound
+          .new_classes(next, &s.used_classes, slack, &mut state.covered);
+      let imbalance = problem.bound.imbalance(next, &s.subject_counts, slack);
+
+      // Höchstens alle noch möglichen Stunden einer Gruppe verringern die fehlenden
+      let shortfall: usize = problem
+        .coverage
         .iter()
-        .zip(problem.subject_weights.iter())
-        .map(|(&count, weight)| {
-          let target = problem.time_slots as f64 * weight;
-          (count as f64 - target).abs() / target
-        })
-        .sum::<f64>();
+        .zip(&s.coverage)
+        .map(|(group, &count)| group.shortfall(count + group.possible(next)))
+        .sum();
+      // Über dem Ziel kommen nur Stunden hinzu, darunter höchstens die noch erreichbaren
+      let lessons = s.lessons();
+      let deviation = problem.workload.target_per_week.map_or(0, |target| {
+        lessons.saturating_sub(target) + target.saturating_sub(self.reachable(next, t, s))
+      });
 
-      let cost = num_classes + (BALANCE_WT * imbalance);
-      debug!(
-        "Current branch cost: {cost} = {num_classes} + {} = {num_classes} + ({BALANCE_WT} * {imbalance})",
-        BALANCE_WT * imbalance
-      );
+      // Freistunden, Lehrkräfte und Kombinationen aus Klasse und Fach kommen später nur hinzu
+      bound += self.objective.classes * num_classes as f64
+        + self.objective.balance * imbalance
+        + self.objective.gaps * s.gaps as f64
+        + self.objective.mentors * s.mentors.mentors as f64
+        + self.objective.preferred_mentor * s.mentors.elsewhere as f64
+        + self.objective.coverage * shortfall as f64
+        + self.objective.continuity * s.pairs.pairs as f64
+        + self.objective.workload * deviation as f64;
+    }
+
+    bound
+  }
+
+  /// Höchstens erreichbare Anzahl Stunden von `trainee` in der Woche, wenn die Stunden ab `next`
+  /// noch offen sind
+  fn reachable(&self, next: usize, trainee: usize, s: &TraineeState) -> usize {
+    let lessons = s.lessons();
+    if next >= self.time_slots {
+      return lessons;
+    }
+    let day = self.slot_day[next];
+    self.trainees[trainee].forced_empty.max_lessons(
+      next,
+      day,
+      &self.days,
+      s.day_lessons[day],
+      lessons,
+    )
+  }
+
+  /// Jeder Praktikant kann nach der aktuellen Belegung, in der alle Praktikanten bis
+  /// einschließlich `trainee` `slot` bereits belegt haben, noch jede Festlegung ohne feste Stunde
+  /// erfüllen, falls verlangt jedes Fach besuchen und die [`GradeCoverage::min_lessons`] erreichen
+  fn completable(&self, slot: usize, trainee: usize, state: &Search) -> bool {
+    self
+      .trainees
+      .iter()
+      .zip(&state.trainees)
+      .enumerate()
+      .all(|(t, (problem, s))| {
+        let next = if t <= trainee { slot + 1 } else { slot };
+        let required = problem
+          .required
+          .iter()
+          .zip(&s.required)
+          .all(|(required, &count)| count > 0 || required.possible(next) > 0);
+        let subjects = !problem.mentors.every_subject
+          || (0..s.subject_counts.len()).all(|subject| {
+            s.subject_counts[subject] > 0 || problem.bound.possible(next, subject) > 0
+          });
+        let coverage = problem
+          .coverage
+          .iter()
+          .zip(&s.coverage)
+          .all(|(group, &count)| count + group.possible(next) >= group.min_lessons);
+
+        required && subjects && coverage
+      })
+  }
+
+  /// Nachdem `trainee` `slot` belegt hat, kann er an diesem Tag noch auf
+  /// [`Workload::min_per_day`] Stunden kommen oder hat dort keinen Unterricht, und in der Woche
+  /// noch auf [`Workload::min_per_week`] Stunden kommen
+  fn workload_completable(&self, slot: usize, trainee: usize, state: &Search) -> bool {
+    let problem = &self.trainees[trainee];
+    let s = &state.trainees[trainee];
+    let day = self.slot_day[slot];
+
+    if let Some(min) = problem.workload.min_per_day {
+      let lessons = s.day_lessons[day];
+      let remaining = self.days[day].end - slot - 1;
+      if lessons > 0 && lessons + remaining < min {
+        return false;
+      }
+    }
+
+    let Some(min) = problem.workload.min_per_week else {
+      return true;
+    };
+    self.reachable(slot + 1, trainee, s) >= min
+  }
+
+  /// Stunden nach der aktuellen Belegung, die wegen der Grenzen im [`Workload`] mindestens leer
+  /// bleiben, siehe [`ForcedEmpty`]. Praktikanten mit einem Ziel je Woche zählen nicht mit.
+  fn forced_empty(&self, slot: usize, trainee: usize, state: &Search) -> usize {
+    self
+      .trainees
+      .iter()
+      .zip(&state.trainees)
+      .enumerate()
+      .map(|(t, (problem, s))| {
+        let next = if t <= trainee { slot + 1 } else { slot };
+        if next >= self.slot_day.len() || !problem.workload.fills_all() {
+          return 0;
+        }
+        let day = self.slot_day[next];
+        problem
+          .forced_empty
+          .from(next, day, &self.days, s.day_lessons[day], s.lessons())
+      })
+      .sum()
+  }
+
+  /// Von der aktuellen Belegung aus, in der alle Praktikanten bis einschließlich `trainee` `slot`
+  /// bereits belegt haben, ist noch eine bessere Lösung als die beste bekannte erreichbar
+  fn promising(&self, slot: usize, trainee: usize, state: &mut Search) -> bool {
+    if !self.completable(slot, trainee, state) {
+      return false;
+    }
+
+    let unfilled =
+      state.unfilled + self.blocked[slot + 1].max(self.forced_empty(slot, trainee, state));
+    if unfilled != state.best_unfilled {
+      return unfilled < state.best_unfilled;
+    }
+
+    // Jede bessere Lösung hat genauso viele leere Stunden wie die beste bekannte, nur dann
+    // lassen sich die Kosten vergleichen
+    let bound = self.lower_bound(slot, trainee, state.slack(), state);
+    bound < state.best_cost - Self::COST_EPSILON
+  }
+
+  /// Löst das Problem per Tiefensuche über alle Stunden, siehe [`Problem::search`]
+  fn solve_search(
+    &self,
+    timeslots: &[Timeslot],
+    options: &SolveOptions,
+    excluded: Excluded,
+    observer: &mut dyn SolverObserver,
+  ) -> Outcome {
+    let mut search = Search::new(self, timeslots, observer, options, excluded);
+    self.search(0, &mut search);
+
+    info!(
+      "Search finished with {} unfilled slots",
+      search.best_unfilled
+    );
+
+    Outcome {
+      best: search.best,
+      cost: search.best_cost,
+      visited: search.nodes_visited,
+      stopped: search.limits.stopped,
+      optimal: search.limits.stopped.is_none(),
+    }
+  }
 
-      cost
+  // Rekursiver Optimierungsalgorithmus. Die Stunden werden nacheinander belegt, innerhalb einer
+  // Stunde für jeden Praktikanten der Reihe nach.
+  fn search(&self, position: usize, state: &mut Search) {
+    // Abbruch, sobald eine der Grenzen aus den `SolveOptions` erreicht ist
+    if state.limits.check(state.nodes_visited) {
+      return;
     }
 
+    let trainees = self.trainees.len();
+
     // Abbruchbedingung: letzte Stunde erreicht.
-    if slot == self.time_slots {
+    if position == self.time_slots * trainees {
       // Falls die Lösung eine Verbesserung darstellt: Speichern der neuen Lösung
-      let current_cost = cost(self, slot, used_classes, subject_counts);
+      let current_cost = self.total_cost(state);
+      let accepted = state.improves(state.unfilled, current_cost)
+        && state
+          .excluded
+          .admits(|trainee| &state.trainees[trainee].current);
       info!(
-        "Comparing current cost {current_cost} to best {best_cost}: {} incoming\t(bal {:?})",
-        if current_cost < *best_cost {
-          "accepted"
-        } else {
-          "rejected"
-        },
-        subject_counts
+        "Comparing current cost {current_cost} to best {}: {} incoming\t(unfilled {})",
+        state.best_cost,
+        if accepted { "accepted" } else { "rejected" },
+        state.unfilled
       );
-      // TODO: Currently, this check will **always** pass, since any other branch would already be
-      // pruned. It is purely a safety against future changes of the pruning heuristic.
-      if current_cost < *best_cost {
-        let solution = Solution {
-          assignments: current.clone(),
-        };
+      // An dieser Stelle entspricht die Schranke den Kosten, die Prüfung der Kosten ist also nur
+      // eine Absicherung gegen spätere Änderungen an der Schranke. Bereits gefundene Lösungen
+      // werden dagegen erst hier ausgeschlossen.
+      if accepted {
+        let solutions: Vec<Solution> = state
+          .trainees
+          .iter()
+          .map(|s| Solution {
+            assignments: s.current.clone(),
+          })
+          .collect();
 
-        let _ = GLOBAL.post_message(
-          &serde_wasm_bindgen::to_value(&SolutionMessage {
-            r#type: "solution".to_string(),
-            solution: finalize(&solution, timeslots),
-          }).unwrap()
-        );
-
-        *best = Some(solution);
-        *best_cost = current_cost;
-        *best_used_classes = used_classes.len();
+        state
+          .observer
+          .solution(&self.plans(&solutions, state.timeslots));
 
+        state.best = Some(solutions);
+        state.best_cost = current_cost;
+        state.best_unfilled = state.unfilled;
       }
       // Rekursionsabbruch
       return;
     }
 
-    let mut assigned = false;
-
-    let mut branches = 0.0;
-    for class in 0..self.classes {
-      // Falls diese Klasse zu dieser Zeit in einem Fach des Praktikanten unterrichtet wird
-      if let Some((subject, pl_index)) = &self.schedule[[class, slot]] {
-        // Klasse für diese Stunde eintragen
-        current[slot] = Some(*pl_index);
-        let was_new = if used_classes.contains(&class) {
-          false
-        } else {
-          used_classes.push(class);
-          true
-        };
+    let (slot, trainee) = (position / trainees, position % trainees);
+    let mut candidates = false;
+    let previous = slot
+      .checked_sub(1)
+      .and_then(|previous| state.trainees[trainee].current[previous]);
+    let second_half = self.second_half(trainee, slot, previous);
 
-        // Verteilungen aktualisieren
-        subject_counts[*subject] += 1;
-
-        // Fortschritt Zurückmelden
-        let current_cost = cost(self, slot, used_classes, subject_counts);
-        let current_classes = used_classes.len();
-        // Rate-limit progresse messages
-        if *nodes_visited % 3197 == 0 {
-          GLOBAL.post_message(
-            &serde_wasm_bindgen::to_value(&ProgressMessage {
-              r#type: "progress".to_string(),
-              progress: Progress {
-                best: *best_cost,
-                current_cost,
-                current_classes,
-                visited: *nodes_visited,
-              },
-            })
-            .unwrap(),
-          );
-        }
+    // Jede Stunde, in der eine Klasse zu dieser Zeit in einem Fach des Praktikanten unterrichtet
+    // wird, bei mehreren Einträgen je Klasse jeder davon
+    for lesson in self.trainees[trainee].lessons(slot) {
+      let (class, subject, pl_index) = lesson;
+      candidates = true;
 
-        // Nur weiter suchen, wenn diese Lösung nicht schon schlechter ist als die Letzte
-        // if used_classes.len()  < *best_used_classes {
-        if current_classes < *best_used_classes || current_cost < *best_cost {
-          // Pruning heuristic
-          // if cost(self, slot, used_classes, subject_counts) < *best_cost {
-          // Weiter bei der nächsten Stunde
-          branches += 1.0;
-          self.search(
-            slot + 1,
-            current,
-            used_classes,
-            subject_counts,
-            best,
-            best_cost,
-            best_used_classes,
-            nodes_visited,
-            timeslots
-          );
-        } else {
-          debug!("Branch pruned (at slot {slot})");
+      // Die zweite Hälfte einer Doppelstunde genau dann, wenn die erste belegt ist
+      let is_second = self
+        .double_partner(trainee, pl_index)
+        .is_some_and(|(other, _)| other < slot);
+      if second_half.map_or(is_second, |second| second != pl_index) {
+        continue;
+      }
+
+      // Stunde, Klasse oder Lehrkraft sind durch andere Praktikanten bereits ausgelastet
+      if !state.occupancy.admits(self, trainee, lesson) {
+        continue;
+      }
+
+      // Grenzen aus dem `Workload`. Spätere Stunden können die Freistunden nur erhöhen.
+      let day = self.slot_day[slot];
+      let s = &state.trainees[trainee];
+      let new_gaps = s.day_last[day].map_or(0, |last| slot - last - 1);
+      let workload = &self.trainees[trainee].workload;
+      if workload
+        .max_per_day
+        .is_some_and(|max| s.day_lessons[day] >= max)
+        || workload.max_per_week.is_some_and(|max| s.lessons() >= max)
+        || workload
+          .max_gaps
+          .is_some_and(|max| s.day_gaps[day] + new_gaps > max)
+      {
+        continue;
+      }
+
+      // Lehrkraft für die Kosten, vor dem Eintragen
+      let teacher = self.lesson_teacher[pl_index];
+      let previous_mentors = s.mentors;
+      let mentors = MentorLoad {
+        mentors: s.mentors.mentors
+          + usize::from(state.occupancy.mentor_load(trainee, teacher) == 0),
+        elsewhere: s.mentors.elsewhere
+          + usize::from(
+            self.trainees[trainee]
+              .preferred_mentor
+              .is_some_and(|p| p != teacher),
+          ),
+      };
+
+      // Klasse für diese Stunde eintragen
+      let was_new = state.occupancy.add(self, trainee, lesson);
+      let s = &mut state.trainees[trainee];
+      s.current[slot] = Some(pl_index);
+      if was_new {
+        s.used_classes.push(class);
+      }
+      s.mentors = mentors;
+      let previous_last = s.day_last[day].replace(slot);
+      s.day_lessons[day] += 1;
+      s.day_gaps[day] += new_gaps;
+      s.gaps += new_gaps;
+
+      // Verteilungen aktualisieren
+      s.subject_counts[subject] += 1;
+      for (count, required) in s.required.iter_mut().zip(&self.trainees[trainee].required) {
+        if required.matches(pl_index) {
+          *count += 1;
+        }
+      }
+      s.pairs.add(class, subject);
+      for (count, group) in s.coverage.iter_mut().zip(&self.trainees[trainee].coverage) {
+        if group.matches(class, subject) {
+          *count += 1;
         }
+      }
+
+      // Fortschritt Zurückmelden
+      // Rate-limit progresse messages
+      if state.nodes_visited.is_multiple_of(3197) {
+        state.observer.progress(&Progress {
+          best: state.best_cost,
+          current_cost: self.total_cost(state),
+          current_classes: state.trainees.iter().map(|s| s.used_classes.len()).sum(),
+          visited: state.nodes_visited,
+        });
+      }
 
-        // Backtracking: eintrag rückgänging machen
-        current[slot] = None;
-        subject_counts[*subject] -= 1;
-        if was_new {
-          used_classes.retain(|c| *c != class);
+      // Nur weiter suchen, wenn von hier aus noch eine bessere Lösung erreichbar ist
+      if self.workload_completable(slot, trainee, state) && self.promising(slot, trainee, state) {
+        // Weiter bei der nächsten Stunde bzw. dem nächsten Praktikanten
+        self.search(position + 1, state);
+      } else {
+        debug!("Branch pruned (at slot {slot})");
+      }
+
+      // Backtracking: eintrag rückgänging machen
+      state.occupancy.remove(self, trainee, lesson);
+      let s = &mut state.trainees[trainee];
+      s.current[slot] = None;
+      s.subject_counts[subject] -= 1;
+      for (count, required) in s.required.iter_mut().zip(&self.trainees[trainee].required) {
+        if required.matches(pl_index) {
+          *count -= 1;
+        }
+      }
+      s.pairs.remove(class, subject);
+      for (count, group) in s.coverage.iter_mut().zip(&self.trainees[trainee].coverage) {
+        if group.matches(class, subject) {
+          *count -= 1;
         }
+      }
+      if was_new {
+        s.used_classes.retain(|c| *c != class);
+      }
+      s.day_last[day] = previous_last;
+      s.day_lessons[day] -= 1;
+      s.day_gaps[day] -= new_gaps;
+      s.gaps -= new_gaps;
+      s.mentors = previous_mentors;
 
-        // Es gibt mindestens eine gültige Zuweisung dieser Stunde
-        assigned = true;
+      if state.limits.stopped.is_some() {
+        break;
       }
     }
 
-    // Mit leerem Eintrag, falls es keine gültige Stunde gibt
-    if !assigned {
-      self.search(
-        slot + 1,
-        current,
-        used_classes,
-        subject_counts,
-        best,
-        best_cost,
-        best_used_classes,
-        nodes_visited,
-        timeslots
-      );
+    if !candidates {
+      // Mit leerem Eintrag, falls es keine gültige Stunde gibt
+      if self.workload_completable(slot, trainee, state) && self.completable(slot, trainee, state) {
+        self.search(position + 1, state);
+      }
+    } else if state.limits.stopped.is_none()
+      && (!self.trainees[trainee].workload.fills_all() || state.unfilled < state.best_unfilled)
+      && !self.trainees[trainee].pinned[slot]
+      && second_half.is_none()
+    {
+      // Stunde trotz möglichem Unterricht leer lassen, z.B. damit ein anderer Praktikant sie
+      // belegen kann. Lohnt sich nur, solange die beste Lösung mehr leere Stunden hat, und nie
+      // bei festgelegten Stunden oder der zweiten Hälfte einer Doppelstunde. Mit einem Ziel je
+      // Woche zählt die Stunde nicht als leer, dann entscheiden allein die Kosten.
+      let counted = usize::from(self.trainees[trainee].workload.fills_all());
+      state.unfilled += counted;
+      if self.workload_completable(slot, trainee, state) && self.promising(slot, trainee, state) {
+        self.search(position + 1, state);
+      }
+      state.unfilled -= counted;
     }
 
-    *nodes_visited += 1;
+    state.nodes_visited += 1;
   }
 }
 
 #[wasm_bindgen]
+#[derive(Debug, Clone, Deserialize)]
 pub struct FachGewichtung {
   #[wasm_bindgen(getter_with_clone)]
   pub kuerzel: String,
@@ -232,26 +750,222 @@ pub struct FachGewichtung {
   pub gewicht: f64,
 }
 
-#[wasm_bindgen(unchecked_return_type = "(string | null)[][]")]
-/// Siehe [`generate`].
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &str = r#"
+export interface CostBreakdown {
+  total: number;
+  classes: { kuerzel: string[]; count: number; cost: number };
+  subjects: { kuerzel: string; actual: number; target: number; imbalance: number; cost: number }[];
+  gaps: { count: number; cost: number };
+  mentors: {
+    kuerzel: string[];
+    lessons: number[];
+    count: number;
+    cost: number;
+    preferred: string | null;
+    elsewhere: number;
+    elsewhere_cost: number;
+  };
+  coverage: {
+    name: string;
+    subject: string | null;
+    lessons: number;
+    min_lessons: number;
+    target: number;
+    shortfall: number;
+    cost: number;
+  }[];
+  continuity: {
+    pairs: { class: string; subject: string; slots: { day: number; period: number }[] }[];
+    count: number;
+    cost: number;
+  };
+  workload: { lessons: number; target: number | null; deviation: number; cost: number };
+  empty_slots: { day: number; period: number }[];
+  blocked_slots: { day: number; period: number }[];
+  dropped: { slot: { day: number; period: number }; pl_index: number; chosen: number }[];
+  empty_reasons: { slot: { day: number; period: number }; reason: EmptyReason }[];
+}
+export type EmptyReason =
+  | "no_lesson_period"
+  | "unavailable"
+  | "no_lesson"
+  | "teachers_excluded"
+  | "forbidden"
+  | "broken_double"
+  | "left_empty";
+export interface LessonFilter {
+  pl_index?: number;
+  class?: string;
+  teacher?: string;
+  day?: number;
+  period?: number;
+}
+export interface Pins {
+  pinned?: LessonFilter[];
+  forbidden?: LessonFilter[];
+  double_lessons?: boolean;
+}
+export interface Availability {
+  unavailable_days?: number[];
+  earliest_arrival?: string | null;
+  latest_departure?: string | null;
+  blocked_slots?: { day: number; period: number }[];
+}
+export type StopReason = "time_limit" | "node_limit" | "cancelled";
+export interface Workload {
+  min_per_week?: number | null;
+  max_per_week?: number | null;
+  target_per_week?: number | null;
+  min_per_day?: number | null;
+  max_per_day?: number | null;
+  max_gaps?: number | null;
+}
+export interface Mentors {
+  preferred?: string | null;
+  max_lessons?: number | null;
+  every_subject?: boolean;
+}
+export interface GradeCoverage {
+  name?: string;
+  min_grade?: number | null;
+  max_grade?: number | null;
+  school_type?: string | null;
+  subject?: string | null;
+  per_subject?: boolean;
+  min_lessons?: number;
+  target?: number;
+}
+export interface Objective {
+  classes?: number;
+  balance?: number;
+  gaps?: number;
+  mentors?: number;
+  preferred_mentor?: number;
+  coverage?: number;
+  continuity?: number;
+  workload?: number;
+}
+export interface JsSolveOptions {
+  backend?: "search" | "milp" | "local_search";
+  time_limit_ms?: number;
+  node_limit?: number;
+  cancel?: Int32Array;
+  seed?: number;
+  alternatives?: number;
+  min_difference?: number;
+}
+export interface Praktikant {
+  name?: string;
+  subjects: { kuerzel: string; gewicht: number }[];
+  excluded_teachers?: string[];
+  pins?: Pins;
+  availability?: Availability;
+  workload?: Workload;
+  mentors?: Mentors;
+  coverage?: GradeCoverage[];
+}
+export interface GenerateInput {
+  plan: string;
+  trainee: Praktikant;
+  objective?: Objective;
+  options?: JsSolveOptions;
+}
+export interface GenerateManyInput {
+  plan: string;
+  trainees: Praktikant[];
+  capacity?: { per_lesson?: number; per_class?: number | null; per_mentor?: number | null };
+  objective?: Objective;
+  options?: JsSolveOptions;
+}
+"#;
+
+#[wasm_bindgen(
+  unchecked_return_type = "{ solution: (number | null)[][], cost: number, optimal: boolean, stopped: StopReason | null, visited: number, breakdown: CostBreakdown, alternatives: { solution: (number | null)[][], cost: number, breakdown: CostBreakdown }[] }"
+)]
+/// Siehe [`generate`]. Fehler werden als `PlannerError` geworfen.
+///
+/// `input` enthält den WILLI2-Plan als Text und den [`Praktikant`]en, optional das [`Objective`]
+/// und die [`SolveOptions`]. Fehlende Angaben haben ihren Standardwert. `cancel` in den Optionen
+/// ist ein `Int32Array` über einem `SharedArrayBuffer`; sobald der Hauptthread das erste Element
+/// auf einen Wert ungleich 0 setzt, wird die Suche abgebrochen. Ohne Angaben läuft
+/// [`Backend::Search`] ohne Grenzen.
 pub fn wasm_generate(
-  raw_plan: String,
-  subjects: Vec<String>,
-  weights: Vec<f64>,
-  excluded_teachers: Vec<String>,
-) -> JsValue {
+  #[wasm_bindgen(unchecked_param_type = "GenerateInput")] input: JsValue,
+) -> Result<JsValue, WasmPlannerError> {
+  let JsGenerateInput {
+    plan: raw_plan,
+    trainee,
+    objective,
+    options,
+  } = serde_wasm_bindgen::from_value(input)
+    .map_err(|e| PlannerError::InvalidOptions(e.to_string()))?;
+
+  info!("Parsing!");
+  let (plan, _errors) = WilliStundenplan::parse(&raw_plan);
+
+  let result = generate(
+    &plan,
+    &trainee,
+    &objective.unwrap_or_default(),
+    &options.unwrap_or_default().into(),
+    &mut WorkerObserver,
+  )?;
+  serde_wasm_bindgen::to_value(&result)
+    .map_err(|e| PlannerError::Serialization(e.to_string()).into())
+}
+
+#[wasm_bindgen(
+  unchecked_return_type = "{ plans: { name: string, solution: (number | null)[][], breakdown: CostBreakdown }[], cost: number, optimal: boolean, stopped: StopReason | null, visited: number, alternatives: { plans: { name: string, solution: (number | null)[][], breakdown: CostBreakdown }[], cost: number }[] }"
+)]
+/// Siehe [`generate_many`]. Fehler werden als `PlannerError` geworfen.
+///
+/// `input` wie bei [`wasm_generate`], aber mit einer Liste von [`Praktikant`]en und optional der
+/// [`Capacity`].
+pub fn wasm_generate_many(
+  #[wasm_bindgen(unchecked_param_type = "GenerateManyInput")] input: JsValue,
+) -> Result<JsValue, WasmPlannerError> {
+  let JsGenerateManyInput {
+    plan: raw_plan,
+    trainees,
+    capacity,
+    objective,
+    options,
+  } = serde_wasm_bindgen::from_value(input)
+    .map_err(|e| PlannerError::InvalidOptions(e.to_string()))?;
+
   info!("Parsing!");
   let (plan, _errors) = WilliStundenplan::parse(&raw_plan);
 
-  let subject_weights = subjects
-    .iter()
-    .cloned()
-    .zip(weights.iter().copied().chain(std::iter::repeat(1.0)))
-    .map(|(kuerzel, gewicht)| FachGewichtung { kuerzel, gewicht })
-    .collect();
+  let result = generate_many(
+    &plan,
+    &trainees,
+    &capacity.unwrap_or_default(),
+    &objective.unwrap_or_default(),
+    &options.unwrap_or_default().into(),
+    &mut WorkerObserver,
+  )?;
+  serde_wasm_bindgen::to_value(&result)
+    .map_err(|e| PlannerError::Serialization(e.to_string()).into())
+}
+
+/// Eingabe von [`wasm_generate`], `None` steht für `undefined` oder `null`
+#[derive(Deserialize)]
+struct JsGenerateInput {
+  plan: String,
+  trainee: Praktikant,
+  objective: Option<Objective>,
+  options: Option<JsSolveOptions>,
+}
 
-  let solution = generate(&plan, &subject_weights, &excluded_teachers);
-  serde_wasm_bindgen::to_value(&solution).unwrap()
+/// Eingabe von [`wasm_generate_many`]
+#[derive(Deserialize)]
+struct JsGenerateManyInput {
+  plan: String,
+  trainees: Vec<Praktikant>,
+  capacity: Option<Capacity>,
+  objective: Option<Objective>,
+  options: Option<JsSolveOptions>,
 }
 
 /// Erstellt einen Stundenplan
@@ -259,139 +973,667 @@ pub fn wasm_generate(
 /// # Parameter
 ///
 /// * `plan` — ein WILLI2-Stundenplan
-/// * `subjects` — eine Liste von Fächerkürzeln im Plan, gepaart mit gewichtungen. Kein Fach darf
-/// zweimal vorkommen.
+/// * `trainee` — Fächer, Festlegungen, Anwesenheit und Grenzen je Tag, siehe [`Praktikant`]
+/// * `objective` — Gewichte der Kostenfunktion, siehe [`Objective`]
+/// * `options` — Zeit- und Knotengrenzen sowie Abbruch der Suche, siehe [`SolveOptions`]
+/// * `observer` — erhält Fortschritt und Zwischenlösungen, siehe [`SolverObserver`]
 ///
 /// # Rückgabe
-/// Eine zwei-dimensionale Liste der Form `[tag][stunde] = [index]`. `index` indiziert die Tabelle
-/// "Stunden im Lehrerplan" (PL) des übergebenen Plans. Theoretisch wäre eine einfache Liste von
-/// Indizes ausreichend, so wird aber der Aufwand sie wieder in eine Tabelle umzubauen gespart.
+/// Die beste gefundene Lösung als [`SolveResult`]. Deren `solution` ist eine zwei-dimensionale
+/// Liste der Form `[tag][stunde] = [index]`. `index` indiziert die Tabelle "Stunden im
+/// Lehrerplan" (PL) des übergebenen Plans. Theoretisch wäre eine einfache Liste von Indizes
+/// ausreichend, so wird aber der Aufwand sie wieder in eine Tabelle umzubauen gespart.
+///
+/// Wird die Suche durch eine Grenze aus `options` beendet, ist `optimal` nicht gesetzt.
+///
+/// # Fehler
+/// Siehe [`PlannerError`]. Ungültige Fächer oder Gewichte werden vor der Suche erkannt, ebenso
+/// Einträge im Lehrerplan, die auf nicht vorhandene Klassen, Tage oder Stunden verweisen.
+/// Widersprechen sich die [`Praktikant::pins`], schlägt die Planung mit
+/// [`PlannerError::ConflictingPins`] fehl.
+/// Gibt es keine gültige Lösung, nennt [`PlannerError::NoFeasiblePlan`] eine minimale Menge von
+/// Vorgaben, die sich widersprechen, soweit die Grenzen aus `options` dafür reichen. Warum
+/// einzelne Stunden leer bleiben, steht in `breakdown.empty_reasons`.
 pub fn generate(
   plan: &WilliStundenplan,
-  subjects: &Vec<FachGewichtung>,
-  excluded_teachers: &Vec<String>,
-) -> Vec<Vec<Option<usize>>> {
-  // NOTE: This assumes each subject only appears once.
-  let classes: Vec<_> = plan.klassen().iter().collect();
-  let days: Vec<_> = plan.tage().iter().collect();
-
-  // Is it worth mapping in the other direction to simplify plan calculation?
-  // [slot] = day, period, day_idx
-  let timeslots: Vec<(&str, usize, usize)> = days
-    .iter()
-    .map(|(day_id, day)| {
-      day
-        .stundenmerkmale
-        .chars()
-        .enumerate()
-        .filter_map(|(i, ch)| {
-          ['v', 'V']
-            .contains(&ch)
-            .then_some((&day.kurz[..], i, *day_id))
-        })
-    })
-    .flatten()
-    .collect();
+  trainee: &Praktikant,
+  objective: &Objective,
+  options: &SolveOptions,
+  observer: &mut dyn SolverObserver,
+) -> Result<SolveResult, PlannerError> {
+  let MultiSolveResult {
+    plans,
+    cost,
+    optimal,
+    stopped,
+    visited,
+    alternatives,
+  } = generate_many(
+    plan,
+    std::slice::from_ref(trainee),
+    &Capacity::default(),
+    objective,
+    options,
+    observer,
+  )?;
+  let TraineePlan {
+    solution,
+    breakdown,
+    ..
+  } = plans.into_iter().next().unwrap();
 
-  let mut filtered_schedule = Array2::default((classes.len(), timeslots.len()));
+  Ok(SolveResult {
+    solution,
+    cost,
+    optimal,
+    stopped,
+    visited,
+    breakdown,
+    alternatives: alternatives
+      .into_iter()
+      .map(|MultiAlternative { plans, cost }| {
+        let TraineePlan {
+          solution,
+          breakdown,
+          ..
+        } = plans.into_iter().next().unwrap();
+        Alternative {
+          solution,
+          cost,
+          breakdown,
+        }
+      })
+      .collect(),
+  })
+}
 
-  for (pl_index, line) in plan.lehrerstunden().iter().enumerate() {
-    let Some(subject_idx) = subjects.iter().position(|s| s.kuerzel == line.fach) else {
-      // Skip line if subject is not relevant to query
-      continue;
-    };
+/// Erstellt Stundenpläne für mehrere Praktikanten gemeinsam
+///
+/// Wie [`generate`], aber für mehrere [`Praktikant`]en. `capacity` begrenzt, wie viele
+/// Praktikanten sich eine Stunde, eine Klasse oder eine Lehrkraft teilen. Es werden zuerst so
+/// wenige Stunden wie möglich wegen dieser Grenzen leer gelassen und dann die Summe der Kosten
+/// aller Pläne minimiert.
+///
+/// # Rückgabe
+/// Ein [`TraineePlan`] je Praktikant in der übergebenen Reihenfolge. Stunden, die wegen der
+/// Kapazität leer bleiben, stehen in `breakdown.blocked_slots`.
+/// Mit [`SolveOptions::alternatives`] kommen bis zu so viele weitere Lösungen hinzu, jede mit
+/// ihren Plänen und Kosten.
+///
+/// # Fehler
+/// Wie bei [`generate`], zusätzlich [`PlannerError::InvalidOptions`] für eine leere Liste von
+/// Praktikanten oder eine Kapazität von 0.
+pub fn generate_many(
+  plan: &WilliStundenplan,
+  trainees: &[Praktikant],
+  capacity: &Capacity,
+  objective: &Objective,
+  options: &SolveOptions,
+  observer: &mut dyn SolverObserver,
+) -> Result<MultiSolveResult, PlannerError> {
+  if trainees.is_empty() {
+    return Err(PlannerError::InvalidOptions(
+      "Keine Praktikanten angegeben".to_string(),
+    ));
+  }
+  for trainee in trainees {
+    validate_subjects(plan, &trainee.subjects)?;
+    trainee.pins.validate(plan)?;
+    trainee.availability.validate()?;
+    trainee.workload.validate()?;
+    trainee.mentors.validate(plan)?;
+    let kuerzel: Vec<String> = trainee.subjects.iter().map(|s| s.kuerzel.clone()).collect();
+    for coverage in &trainee.coverage {
+      coverage.validate(&kuerzel)?;
+    }
+  }
+  capacity.validate()?;
+  objective.validate()?;
+
+  let (problem, timeslots) = Problem::build(plan, trainees, capacity, objective)?;
+
+  let Outcome {
+    best,
+    cost: best_cost,
+    visited: nodes_visited,
+    stopped,
+    optimal,
+  } = problem.solve(&timeslots, options, Excluded::default(), observer)?;
+
+  let Some(solutions) = best else {
+    observer.finished(None, nodes_visited, optimal);
+    return Err(match stopped {
+      Some(reason) => PlannerError::Stopped(reason),
+      None => {
+        let (conflicts, minimal) =
+          diagnostics::conflicts(plan, trainees, capacity, objective, options);
+        PlannerError::NoFeasiblePlan { conflicts, minimal }
+      }
+    });
+  };
+
+  info!(
+    "Cost of best solution: {best_cost} ({})",
+    match stopped {
+      Some(reason) => format!("stopped: {reason}"),
+      None if optimal => "optimal".to_string(),
+      None => "not proven optimal".to_string(),
+    }
+  );
 
-    if excluded_teachers.contains(&line.lehrkraft) {
-     continue;
+  // Weitere Lösungen, jeweils mit eigenen Grenzen. Ohne weitere Lösung oder nach einem Abbruch ist
+  // Schluss.
+  let mut found = vec![solutions];
+  let mut alternatives = vec![];
+  for _ in 0..options.alternatives {
+    if options.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+      break;
     }
 
-    let period_in_day = plan
-      .stunden()
+    let excluded = Excluded::new(&found, options.min_difference);
+    let outcome = problem.solve(&timeslots, options, excluded, &mut ProgressOnly(observer))?;
+    let Some(solutions) = outcome.best else {
+      break;
+    };
+
+    info!("Cost of alternative {}: {}", found.len(), outcome.cost);
+    alternatives.push(MultiAlternative {
+      plans: problem.plans(&solutions, &timeslots),
+      cost: outcome.cost,
+    });
+    found.push(solutions);
+  }
+
+  observer.finished(Some(best_cost), nodes_visited, optimal);
+
+  Ok(MultiSolveResult {
+    plans: problem.plans(&found[0], &timeslots),
+    cost: best_cost,
+    optimal,
+    stopped,
+    visited: nodes_visited,
+    alternatives,
+  })
+}
+
+impl Problem {
+  /// Baut das Problem aus bereits geprüften Angaben auf. Gibt auch die Unterrichtsstunden des
+  /// Rasters zurück, auf die sich die Indizes der Stunden beziehen.
+  fn build(
+    plan: &WilliStundenplan,
+    trainees: &[Praktikant],
+    capacity: &Capacity,
+    objective: &Objective,
+  ) -> Result<(Problem, Vec<Timeslot>), PlannerError> {
+    let classes: Vec<KlasseId> = plan.klassen().keys().map(KlasseId).collect();
+    let class_rows: Vec<Option<&KlassenZeile>> =
+      classes.iter().map(|&id| plan.klasse(id)).collect();
+    let periods: Vec<StundeId> = plan.stundenraster().collect();
+
+    // Is it worth mapping in the other direction to simplify plan calculation?
+    let timeslots: Vec<Timeslot> = plan
+      .tage()
       .iter()
       .enumerate()
-      .find_map(|(id, (_, std))| (std.kurz == line.tag_stunde.stunde).then_some(id))
-      .expect("Ungültige Stunde");
+      .flat_map(|(day, (_, tag))| {
+        // Das n-te Stundenmerkmal gehört zur n-ten Stunde des Rasters
+        tag
+          .stundenmerkmale
+          .chars()
+          .zip(periods.iter())
+          .enumerate()
+          .filter_map(move |(period, (ch, &stunde))| {
+            ['v', 'V'].contains(&ch).then_some(Timeslot {
+              tag: tag.id,
+              stunde,
+              day,
+              period,
+            })
+          })
+      })
+      .collect();
 
-    let Some(slot) = timeslots
+    if timeslots.is_empty() {
+      return Err(PlannerError::EmptyTimeslotGrid);
+    }
+    let non_lesson_periods: Vec<SlotRef> = plan
+      .tage()
       .iter()
-      .position(|(day, p, _)| *p == period_in_day && *day == line.tag_stunde.tag)
-    else {
-      // Skip if this period is excluded from the plan
-      continue;
+      .enumerate()
+      .flat_map(|(day, (_, tag))| {
+        tag
+          .stundenmerkmale
+          .chars()
+          .zip(periods.iter())
+          .enumerate()
+          .filter(|(_, (ch, _))| !['v', 'V'].contains(ch))
+          .map(move |(period, _)| SlotRef { day, period })
+      })
+      .collect();
+
+    // Lage jeder Zeile im Lehrerplan: (class, slot), falls sie in eine Unterrichtsstunde fällt
+    let mut lessons = Vec::with_capacity(plan.lehrerstunden().len());
+    let mut teacher_ids: Vec<LehrkraftId> = vec![];
+    let mut teacher_kuerzel: Vec<String> = vec![];
+    let mut lesson_teacher = Vec::with_capacity(plan.lehrerstunden().len());
+
+    // Die Angaben der Praktikanten nennen Fächer und Lehrkräfte über ihr Kürzel
+    let fach_kuerzel =
+      |line: &LehrerStundenZeile| plan.fach(line.fach).map_or("", |f| &f.kuerzel[..]);
+    let lehrkraft_kuerzel = |line: &LehrerStundenZeile| {
+      plan
+        .lehrkraft(line.lehrkraft)
+        .map_or("", |l| &l.kuerzel[..])
     };
 
-    let class_idx = classes
+    for (pl_index, line) in plan.lehrerstunden().iter().enumerate() {
+      let teacher = match teacher_ids.iter().position(|&id| id == line.lehrkraft) {
+        Some(teacher) => teacher,
+        None => {
+          teacher_ids.push(line.lehrkraft);
+          teacher_kuerzel.push(lehrkraft_kuerzel(line).to_string());
+          teacher_kuerzel.len() - 1
+        }
+      };
+      lesson_teacher.push(teacher);
+
+      if !trainees.iter().any(|t| {
+        t.subjects.iter().any(|s| s.kuerzel == fach_kuerzel(line))
+          && !t
+            .excluded_teachers
+            .iter()
+            .any(|k| k == lehrkraft_kuerzel(line))
+      }) {
+        // Skip line if subject is not relevant to query
+        lessons.push(None);
+        continue;
+      }
+
+      let dangling = |table, id: &dyn std::fmt::Display| PlannerError::DanglingReference {
+        pl_index,
+        table,
+        kuerzel: id.to_string(),
+      };
+
+      let TagStunde { tag, stunde } = line.tag_stunde;
+      if plan.tag(tag).is_none() {
+        return Err(dangling("Tag", &tag));
+      }
+      if plan.stunde(stunde).is_none() {
+        return Err(dangling("Stunde", &stunde));
+      }
+
+      let Some(slot) = timeslots
+        .iter()
+        .position(|t| t.tag == tag && t.stunde == stunde)
+      else {
+        // Skip if this period is excluded from the plan
+        lessons.push(None);
+        continue;
+      };
+
+      let class_idx = classes
+        .iter()
+        .position(|&c| c == line.klasse)
+        .ok_or_else(|| dangling("Klasse", &line.klasse))?;
+
+      lessons.push(Some((class_idx, slot)));
+    }
+
+    // Die Stunden sind nach Tagen sortiert, jeder Tag ist also ein zusammenhängender Bereich
+    let mut days: Vec<Range<usize>> = vec![];
+    let mut slot_day = Vec::with_capacity(timeslots.len());
+    for (slot, timeslot) in timeslots.iter().enumerate() {
+      match days.last_mut() {
+        Some(range) if timeslots[range.start].day == timeslot.day => range.end = slot + 1,
+        _ => days.push(slot..slot + 1),
+      }
+      slot_day.push(days.len() - 1);
+    }
+    let doubles = DoubleLessons::detect(plan, &lessons, &slot_day);
+
+    let trainees: Vec<Trainee> = trainees
       .iter()
-      .position(|(_id, c)| c.kuerzel == line.klasse)
-      .expect("Eintrag für nicht im Plan vorhandene Klasse");
+      .map(|trainee| {
+        let subjects = &trainee.subjects;
+        let mut filtered_schedule: Array2<Vec<(usize, usize)>> =
+          Array2::default((classes.len(), timeslots.len()));
+
+        let available = trainee.availability.slots(plan, &timeslots)?;
+        if !available.contains(&true) {
+          return Err(PlannerError::InvalidOptions(format!(
+            "Praktikant „{}“ ist in keiner Unterrichtsstunde verfügbar",
+            trainee.name
+          )));
+        }
+
+        // [slot] = Unterricht in einem Fach des Praktikanten bzw. davon bei einer nicht
+        // ausgeschlossenen Lehrkraft, nur für die Begründung leerer Stunden
+        let mut in_subjects = vec![false; timeslots.len()];
+        let mut offered = vec![false; timeslots.len()];
+
+        for (pl_index, line) in plan.lehrerstunden().iter().enumerate() {
+          let Some(subject_idx) = subjects
+            .iter()
+            .position(|s| s.kuerzel == fach_kuerzel(line))
+          else {
+            continue;
+          };
+          // Zeilen ohne Praktikanten, der sie besuchen kann, haben keine Lage in `lessons`
+          let Some(slot) = lessons[pl_index].map(|(_, slot)| slot).or_else(|| {
+            let TagStunde { tag, stunde } = line.tag_stunde;
+            timeslots
+              .iter()
+              .position(|t| t.tag == tag && t.stunde == stunde)
+          }) else {
+            continue;
+          };
+          in_subjects[slot] = true;
+
+          // Skip if the trainee is not at school at this time
+          if !available[slot]
+            || trainee
+              .excluded_teachers
+              .iter()
+              .any(|k| k == lehrkraft_kuerzel(line))
+          {
+            continue;
+          }
+          offered[slot] = true;
+          let Some((class_idx, _)) = lessons[pl_index] else {
+            continue;
+          };
+
+          // Mehrere Einträge je Klasse und Stunde bleiben als Alternativen erhalten
+          filtered_schedule[[class_idx, slot]].push((subject_idx, pl_index));
+        }
+
+        let pinned = trainee
+          .pins
+          .apply(&mut filtered_schedule, plan, &timeslots)
+          .map_err(|error| match error {
+            PlannerError::ConflictingPins(message) if !trainee.name.is_empty() => {
+              PlannerError::ConflictingPins(format!("{}: {message}", trainee.name))
+            }
+            error => error,
+          })?;
+
+        let has_candidates = |schedule: &Array2<Vec<(usize, usize)>>, slot| {
+          schedule.column(slot).iter().any(|l| !l.is_empty())
+        };
+        let allowed: Vec<bool> = (0..timeslots.len())
+          .map(|slot| has_candidates(&filtered_schedule, slot))
+          .collect();
+
+        if trainee.pins.double_lessons {
+          doubles.prune(&mut filtered_schedule);
+          if let Some(slot) = (0..timeslots.len())
+            .find(|&slot| pinned[slot] && filtered_schedule.column(slot).iter().all(Vec::is_empty))
+          {
+            let Timeslot { day, period, .. } = timeslots[slot];
+            let prefix = if trainee.name.is_empty() {
+              String::new()
+            } else {
+              format!("{}: ", trainee.name)
+            };
+            return Err(PlannerError::ConflictingPins(format!(
+              "{prefix}Der festgelegte Unterricht in Tag {day}, Stunde {period} gehört zu einer Doppelstunde, deren andere Hälfte nicht besucht werden kann"
+            )));
+          }
+        }
+
+        let required = trainee
+          .pins
+          .required(&filtered_schedule, plan, &timeslots)
+          .map_err(|error| match error {
+            PlannerError::ConflictingPins(message) if !trainee.name.is_empty() => {
+              PlannerError::ConflictingPins(format!("{}: {message}", trainee.name))
+            }
+            error => error,
+          })?;
+        if trainee.mentors.every_subject
+          && let Some(subject) = subjects.iter().enumerate().find_map(|(idx, s)| {
+            (!filtered_schedule.iter().flatten().any(|&(i, _)| i == idx)).then_some(s)
+          })
+        {
+          return Err(PlannerError::InvalidOptions(format!(
+            "Praktikant „{}“ kann in keiner Stunde „{}“ besuchen",
+            trainee.name, subject.kuerzel
+          )));
+        }
+
+        let subject_kuerzel: Vec<String> = subjects.iter().map(|s| s.kuerzel.clone()).collect();
+        let coverage = CoverageGroup::build(
+          &trainee.coverage,
+          &subject_kuerzel,
+          &class_rows,
+          &filtered_schedule,
+        );
+        if let Some(group) = coverage.iter().find(|g| g.possible(0) < g.min_lessons) {
+          let subject = group
+            .subject
+            .map_or(String::new(), |s| format!(" in „{}“", subject_kuerzel[s]));
+          return Err(PlannerError::InvalidOptions(format!(
+            "Praktikant „{}“ kann in „{}“{subject} höchstens {} Stunden besuchen, verlangt sind mindestens {}",
+            trainee.name,
+            group.name,
+            group.possible(0),
+            group.min_lessons
+          )));
+        }
+
+        let forced_empty = ForcedEmpty::new(&filtered_schedule, &days, &trainee.workload);
+        if let Some(min) = trainee.workload.min_per_week {
+          let reachable = forced_empty.max_lessons(0, 0, &days, 0, 0);
+          if reachable < min {
+            return Err(PlannerError::InvalidOptions(format!(
+              "Praktikant „{}“ kann höchstens {reachable} Stunden besuchen, verlangt sind mindestens {min}",
+              trainee.name
+            )));
+          }
+        }
+
+        let weight_sum: f64 = subjects.iter().map(|s| s.gewicht).sum();
+        // Normalize weights, die Ziele beziehen sich nur auf die verfügbaren Stunden bzw. das Ziel
+        // oder die Höchstanzahl je Woche
+        let available_slots = available.iter().filter(|&&a| a).count();
+        let lessons = trainee
+          .workload
+          .target_per_week
+          .or(trainee.workload.max_per_week)
+          .map_or(available_slots, |max| max.min(available_slots)) as f64;
+        let subject_targets: Vec<f64> = subjects
+          .iter()
+          .map(|s| lessons * s.gewicht / weight_sum)
+          .collect();
+
+        let empty_reasons = (0..timeslots.len())
+          .map(|slot| {
+            if has_candidates(&filtered_schedule, slot) {
+              None
+            } else if !available[slot] {
+              Some(EmptyReason::Unavailable)
+            } else if !in_subjects[slot] {
+              Some(EmptyReason::NoLesson)
+            } else if !offered[slot] {
+              Some(EmptyReason::TeachersExcluded)
+            } else if !allowed[slot] {
+              Some(EmptyReason::Forbidden)
+            } else {
+              Some(EmptyReason::BrokenDouble)
+            }
+          })
+          .collect();
+
+        Ok(Trainee {
+          name: trainee.name.clone(),
+          subject_kuerzel,
+          bound: LowerBound::new(&filtered_schedule, &subject_targets, timeslots.len()),
+          forced_empty,
+          subject_targets,
+          schedule: filtered_schedule,
+          pinned,
+          required,
+          double_lessons: trainee.pins.double_lessons,
+          workload: trainee.workload,
+          mentors: trainee.mentors.clone(),
+          preferred_mentor: trainee
+            .mentors
+            .preferred
+            .as_ref()
+            .and_then(|kuerzel| teacher_kuerzel.iter().position(|k| k == kuerzel)),
+          coverage,
+          empty_reasons,
+        })
+      })
+      .collect::<Result<_, PlannerError>>()?;
+    check_pinned_capacity(&trainees, capacity)?;
+
+    let problem = Problem {
+      time_slots: timeslots.len(),
+      days,
+      slot_day,
+      classes: classes.len(),
+      class_kuerzel: classes
+        .iter()
+        .map(|&id| {
+          plan
+            .klasse(id)
+            .map(|k| k.kuerzel.clone())
+            .unwrap_or_default()
+        })
+        .collect(),
+      teacher_kuerzel,
+      lesson_teacher,
+      doubles,
+      non_lesson_periods,
+      blocked: bound::blocked_slots(&trainees, capacity.per_lesson, timeslots.len()),
+      trainees,
+      capacity: *capacity,
+      objective: *objective,
+    };
 
-    filtered_schedule[[class_idx, slot]] = Some((subject_idx, pl_index));
+    Ok((problem, timeslots))
   }
+}
 
-  let weight_sum: f64 = subjects.iter().map(|s| s.gewicht).sum();
+/// Unterricht, der für mehr Praktikanten festgelegt ist, als die [`Capacity`] je Stunde erlaubt,
+/// ist ein Widerspruch, den die Verfahren sonst erst nach langer Suche bemerken würden
+fn check_pinned_capacity(trainees: &[Trainee], capacity: &Capacity) -> Result<(), PlannerError> {
+  // [pl_index] = Anzahl der Praktikanten, für die genau dieser Unterricht festgelegt ist
+  let mut pinned: HashMap<usize, usize> = HashMap::new();
+  for trainee in trainees {
+    for (slot, _) in trainee.pinned.iter().enumerate().filter(|(_, p)| **p) {
+      let mut lessons = trainee.schedule.column(slot).into_iter().flatten();
+      if let (Some(&(_, pl_index)), None) = (lessons.next(), lessons.next()) {
+        *pinned.entry(pl_index).or_default() += 1;
+      }
+    }
+  }
 
-  let problem = Problem {
-    time_slots: timeslots.len(),
-    classes: classes.len(),
-    // Normalize weights
-    subject_weights: subjects.iter().map(|s| s.gewicht / weight_sum).collect(),
-    schedule: filtered_schedule,
-  };
+  match pinned
+    .into_iter()
+    .filter(|&(_, count)| count > capacity.per_lesson)
+    .min()
+  {
+    Some((pl_index, count)) => Err(PlannerError::ConflictingPins(format!(
+      "Eintrag {pl_index} ist für {count} Praktikanten festgelegt, erlaubt sind {}",
+      capacity.per_lesson
+    ))),
+    None => Ok(()),
+  }
+}
 
-  let mut subject_counts = Vec::with_capacity(subjects.len());
-  (0..subject_counts.capacity()).for_each(|_| subject_counts.push(Default::default()));
-
-  let mut current = (0..problem.time_slots).map(|_| None).collect();
-
-  let mut cost = f64::INFINITY.clone();
-  let mut best_used_classes = usize::MAX.clone();
-
-  let solution = {
-    let mut best_solution = None;
-    problem.search(
-      0,
-      &mut current,
-      &mut vec![],
-      &mut subject_counts,
-      &mut best_solution,
-      &mut cost,
-      &mut best_used_classes,
-      &mut 0,
-      &timeslots
-    );
-    best_solution
-    // TODO: Proper error handling
+/// Prüft die Fächerauswahl: Jedes Fach muss im Plan vorhanden sein, darf nur einmal vorkommen und
+/// braucht ein positives Gewicht.
+fn validate_subjects(
+  plan: &WilliStundenplan,
+  subjects: &[FachGewichtung],
+) -> Result<(), PlannerError> {
+  if subjects.is_empty() {
+    return Err(PlannerError::InvalidWeights(
+      "Keine Fächer ausgewählt".to_string(),
+    ));
   }
-  .expect("Keine gültige Lösung gefunden");
 
-  info!(
-    "Cost of best solution: {cost} ({} classes)",
-    best_used_classes
-  );
+  for (i, subject) in subjects.iter().enumerate() {
+    if plan.fach_id(&subject.kuerzel).is_none() {
+      return Err(PlannerError::UnknownSubject(subject.kuerzel.clone()));
+    }
+
+    if subjects[..i].iter().any(|s| s.kuerzel == subject.kuerzel) {
+      return Err(PlannerError::DuplicateSubject(subject.kuerzel.clone()));
+    }
+
+    if !subject.gewicht.is_finite() || subject.gewicht <= 0.0 {
+      return Err(PlannerError::InvalidWeights(format!(
+        "Gewicht {} für „{}“ muss eine positive Zahl sein",
+        subject.gewicht, subject.kuerzel
+      )));
+    }
+  }
+
+  Ok(())
+}
+
+impl Problem {
+  /// Löst das Problem mit dem in `options` gewählten Verfahren. Die beste Lösung muss sich von den
+  /// Lösungen in `excluded` unterscheiden.
+  fn solve(
+    &self,
+    timeslots: &[Timeslot],
+    options: &SolveOptions,
+    excluded: Excluded,
+    observer: &mut dyn SolverObserver,
+  ) -> Result<Outcome, PlannerError> {
+    Ok(match options.backend {
+      Backend::Search => self.solve_search(timeslots, options, excluded, observer),
+      Backend::Milp => {
+        let outcome = self.solve_milp(options, excluded)?;
+        if let Some(solutions) = &outcome.best {
+          observer.solution(&self.plans(solutions, timeslots));
+        }
+        outcome
+      }
+      Backend::LocalSearch => self.solve_local_search(timeslots, options, excluded, observer),
+    })
+  }
 
-  finalize(&solution, &timeslots)
+  /// Bereitet die Belegungen aller Praktikanten für die Rückgabe und den [`SolverObserver`] auf
+  fn plans(&self, solutions: &[Solution], timeslots: &[Timeslot]) -> Vec<TraineePlan> {
+    solutions
+      .iter()
+      .enumerate()
+      .map(|(trainee, solution)| TraineePlan {
+        name: self.trainees[trainee].name.clone(),
+        solution: finalize(solution, timeslots),
+        breakdown: self.breakdown(trainee, solution, timeslots),
+      })
+      .collect()
+  }
 }
 
 #[tracing::instrument]
-fn finalize(solution: &Solution, timeslots: &Vec<(&str, usize, usize)>) -> Vec<Vec<Option<usize>>>{
+fn finalize(solution: &Solution, timeslots: &[Timeslot]) -> Vec<Vec<Option<usize>>> {
   let mut result: Vec<Vec<Option<usize>>> = vec![];
   // Pre-fill seven weekdays
   (0..7).for_each(|_| result.push(vec![]));
 
-  // Yes, I know this objectively sucks. Fix it if you have the time; I know I don't.
   for (i, assignment) in solution.assignments.iter().enumerate() {
     let Some(pl_idx) = assignment else {
       continue;
     };
 
-    let (_day, period, day_id) = timeslots[i];
+    let Timeslot { day, period, .. } = timeslots[i];
 
-    // pluh
-    while result[day_id - 1].len() <= period {
-      result[day_id - 1].push(None);
+    if result.len() <= day {
+      result.resize(day + 1, vec![]);
+    }
+    if result[day].len() <= period {
+      result[day].resize(period + 1, None);
     }
 
-    result[day_id - 1][period] = Some(*pl_idx);
+    result[day][period] = Some(*pl_idx);
   }
 
   result
@@ -405,7 +1647,7 @@ fn finalize(solution: &Solution, timeslots: &Vec<(&str, usize, usize)>) -> Vec<V
 //     .init();
 // }
 
-//// INITIALIZATION ////
+// ==== INITIALIZATION ====
 #[wasm_bindgen(start)]
 pub fn start() -> Result<(), JsValue> {
   // print pretty errors in wasm https://github.com/rustwasm/console_error_panic_hook
@@ -0,0 +1,712 @@
+//! Lokale Suche (Simulated Annealing) für Schulen, bei denen die vollständige Suche nicht in
+//! vertretbarer Zeit fertig wird.
+//!
+//! Ausgehend von einer gierig erstellten Belegung wird die Lösung schrittweise verändert, jeweils
+//! für einen zufälligen Praktikanten:
+//!
+//! * eine Stunde einer anderen möglichen Klasse oder einem anderen Eintrag derselben Klasse
+//!   zuordnen, bevorzugt einer bereits besuchten Klasse,
+//! * eine Stunde leeren,
+//! * eine Stunde in eine leere Stunde verlegen, nur mit einer Höchstanzahl oder einem Ziel je
+//!   Woche im [`Workload`](crate::Workload),
+//! * alle Stunden einer Klasse auf andere, bereits besuchte Klassen verlegen.
+//!
+//! Mit [`Pins::double_lessons`](crate::Pins) ändert jeder Schritt beide Hälften einer
+//! Doppelstunde, siehe `place`.
+//!
+//! Leere Stunden, die eigentlich belegt werden müssten, sind während der Suche erlaubt, kosten
+//! aber [`EMPTY_PENALTY`]. So kann die Suche z.B. eine Klasse aufgeben und die freigewordenen
+//! Stunden anschließend neu verteilen. Die [`Capacity`](crate::Capacity) wird dagegen nie
+//! überschritten. Als beste Lösung gilt wie in der Suche die mit den wenigsten solchen Lücken, bei
+//! Gleichstand die günstigste.
+//!
+//! Bei der Suche nach Alternativen wird nur eine Belegung als beste übernommen, die sich genug von
+//! den bereits gefundenen Lösungen unterscheidet.
+//!
+//! Verstöße gegen den [`Workload`](crate::Workload), unerfüllte Festlegungen ohne feste Stunde,
+//! Fächer ohne Stunde trotz [`Mentors::every_subject`](crate::Mentors), fehlende Stunden bis
+//! [`GradeCoverage::min_lessons`](crate::GradeCoverage) und halbe Doppelstunden sind während der
+//! Suche ebenfalls erlaubt und kosten je Verstoß bzw. Stunde [`VIOLATION_PENALTY`]. Als Lösung gilt
+//! nur eine Belegung ohne Verstöße.
+//!
+//! Festgelegte Stunden werden nie geleert. Bleiben sie in der Startbelegung wegen der Kapazität
+//! leer, gilt eine Belegung erst als Lösung, sobald sie belegt sind.
+//!
+//! Nach jedem Schritt werden nur die Kosten der betroffenen Praktikanten neu berechnet, die der
+//! übrigen stehen in `State::terms`.
+//!
+//! Die Suche endet an der Zeit- bzw. Knotengrenze oder, sobald sich die beste Lösung über
+//! [`STALL_CYCLES`] Zyklen nicht mehr verbessert hat. Letzteres gilt nicht als vorzeitiger Abbruch.
+//! Sie ist über [`SolveOptions::seed`] reproduzierbar, findet aber keine bewiesen optimale Lösung.
+
+use ndarray::Array2;
+use rand::{Rng, SeedableRng, seq::SliceRandom};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+  Outcome, Problem, Progress, Solution, SolveOptions, SolverObserver, Timeslot,
+  alternatives::Excluded,
+  continuity::{ClassLoad, lesson_pairs},
+  options::Limits,
+  trainee::{Lesson, Occupancy},
+  workload::DayLoad,
+};
+
+/// Kosten je Stunde, die leer ist, obwohl eine Klasse möglich wäre
+const EMPTY_PENALTY: f64 = 1.0;
+
+/// Kosten je Verstoß gegen den [`Workload`](crate::Workload). Höher als [`EMPTY_PENALTY`], damit
+/// das Leeren einer Stunde, die eine Grenze überschreitet, sich lohnt.
+const VIOLATION_PENALTY: f64 = 2.0;
+
+/// Anzahl der Schritte, falls weder Zeit- noch Knotengrenze gesetzt sind
+const DEFAULT_ITERATIONS: usize = 200_000;
+
+/// Nach so vielen Schritten wird die Temperatur zurückgesetzt und von der besten Lösung aus
+/// weitergesucht
+const CYCLE: usize = 20_000;
+
+/// Die Suche endet, wenn sich die beste Lösung so viele Zyklen lang nicht verbessert hat. Kleiner
+/// als `DEFAULT_ITERATIONS / CYCLE`, damit das auch ohne Grenzen vor dem Ende greifen kann.
+const STALL_CYCLES: usize = 5;
+const _: () = assert!(STALL_CYCLES * CYCLE < DEFAULT_ITERATIONS);
+
+const START_TEMPERATURE: f64 = 2.0;
+const END_TEMPERATURE: f64 = 0.01;
+
+/// Aktuelle Belegung mit den für die Kosten nötigen Zählern
+#[derive(Debug, Clone)]
+struct State {
+  // [trainee][slot] = besuchte Stunde
+  assignment: Array2<Option<Lesson>>,
+  occupancy: Occupancy,
+  // [trainee] = Anzahl der besuchten Klassen
+  used_classes: Vec<usize>,
+  // [trainee][subject] = count
+  subject_counts: Vec<Vec<usize>>,
+  // Anzahl der Stunden, die leer sind, obwohl eine Klasse möglich wäre, ohne Praktikanten mit
+  // einem Ziel je Woche
+  unfilled: usize,
+  // [trainee] = (Kosten, Verstöße) des Praktikanten, siehe `Problem::terms`
+  terms: Vec<(f64, usize)>,
+}
+
+impl Problem {
+  /// Löst das Problem per Simulated Annealing. `node_limit` aus den [`SolveOptions`] begrenzt die
+  /// Anzahl der Schritte. Endet die Suche, weil sie stagniert, bleibt `stopped` leer.
+  pub(crate) fn solve_local_search(
+    &self,
+    timeslots: &[Timeslot],
+    options: &SolveOptions,
+    excluded: Excluded,
+    observer: &mut dyn SolverObserver,
+  ) -> Outcome {
+    let mut rng = ChaCha8Rng::seed_from_u64(options.seed);
+    let mut limits = Limits::new(options);
+    let unlimited = options.time_limit.is_none() && options.node_limit.is_none();
+
+    // [trainee][slot] = mögliche Stunden
+    let candidates: Vec<Vec<Vec<Lesson>>> = self
+      .trainees
+      .iter()
+      .map(|trainee| {
+        (0..self.time_slots)
+          .map(|slot| trainee.lessons(slot).collect())
+          .collect()
+      })
+      .collect();
+    // (trainee, slot) mit möglichen Klassen
+    let required: Vec<(usize, usize)> = candidates
+      .iter()
+      .enumerate()
+      .flat_map(|(trainee, slots)| {
+        slots
+          .iter()
+          .enumerate()
+          .filter(|(_, c)| !c.is_empty())
+          .map(move |(slot, _)| (trainee, slot))
+      })
+      .collect();
+
+    let mut current = self.greedy(&candidates, &required);
+    self.repair(&mut current);
+    let all: Vec<usize> = (0..self.trainees.len()).collect();
+    self.refresh(&mut current, &all);
+    let mut current_energy = self.energy(&current);
+
+    // (Belegung, Kosten), `None` solange keine Belegung genug von `excluded` abweicht
+    let mut best: Option<(State, f64)> = None;
+    if self.admitted(&current, excluded) {
+      self.report(&current, timeslots, observer);
+      best = Some((current.clone(), self.state_cost(&current)));
+    }
+
+    let mut iteration = 0;
+    // Schritt, in dem `best` zuletzt verbessert wurde
+    let mut improved = 0;
+    // Für das Zurücknehmen eines Schritts: (trainee, slot, vorherige Stunde)
+    let mut undo: Vec<(usize, usize, Option<Lesson>)> = vec![];
+
+    while !required.is_empty() {
+      if best.is_some() && iteration - improved >= STALL_CYCLES * CYCLE {
+        break;
+      }
+      if limits.check(iteration) || (unlimited && iteration >= DEFAULT_ITERATIONS) {
+        break;
+      }
+
+      // Neuer Zyklus: von der besten Lösung aus mit hoher Temperatur weitersuchen
+      if iteration > 0
+        && iteration.is_multiple_of(CYCLE)
+        && let Some((state, _)) = &best
+      {
+        current = state.clone();
+        current_energy = self.energy(&current);
+      }
+      let cooling = (iteration % CYCLE) as f64 / CYCLE as f64;
+      let temperature = START_TEMPERATURE * (END_TEMPERATURE / START_TEMPERATURE).powf(cooling);
+
+      undo.clear();
+      let unfilled = current.unfilled;
+      let (trainee, slot) = *required.choose(&mut rng).unwrap();
+      let trainee_candidates = &candidates[trainee];
+      // Mit einer Höchstanzahl oder einem Ziel je Woche bleiben Stunden leer, welche entscheidet
+      // das Verlegen
+      let workload = &self.trainees[trainee].workload;
+      let moves = if workload.max_per_week.is_some() || !workload.fills_all() {
+        11
+      } else {
+        10
+      };
+      match rng.gen_range(0..moves) {
+        // Stunde neu zuordnen
+        0..6 => {
+          let pool: Vec<Lesson> = trainee_candidates[slot]
+            .iter()
+            .copied()
+            .filter(|&lesson| {
+              current.assignment[[trainee, slot]] != Some(lesson)
+                && current.occupancy.admits(self, trainee, lesson)
+            })
+            .collect();
+          let used: Vec<Lesson> = pool
+            .iter()
+            .copied()
+            .filter(|&(class, ..)| current.occupancy.class_load(trainee, class) > 0)
+            .collect();
+          let pool = if !used.is_empty() && rng.gen_bool(0.5) {
+            &used
+          } else {
+            &pool
+          };
+          if let Some(&lesson) = pool.choose(&mut rng) {
+            self.place(&mut current, trainee, slot, Some(lesson), &mut undo);
+          }
+        }
+        // Stunde leeren
+        6..8 => {
+          if current.assignment[[trainee, slot]].is_some() && !self.trainees[trainee].pinned[slot] {
+            self.place(&mut current, trainee, slot, None, &mut undo);
+          }
+        }
+        // Stunde verlegen
+        10 => {
+          let free: Vec<usize> = (0..self.time_slots)
+            .filter(|&s| {
+              current.assignment[[trainee, s]].is_none() && !trainee_candidates[s].is_empty()
+            })
+            .collect();
+          if current.assignment[[trainee, slot]].is_some()
+            && !self.trainees[trainee].pinned[slot]
+            && let Some(&to) = free.choose(&mut rng)
+          {
+            let pool: Vec<Lesson> = trainee_candidates[to]
+              .iter()
+              .copied()
+              .filter(|&lesson| current.occupancy.admits(self, trainee, lesson))
+              .collect();
+            if let Some(&lesson) = pool.choose(&mut rng) {
+              self.place(&mut current, trainee, slot, None, &mut undo);
+              self.place(&mut current, trainee, to, Some(lesson), &mut undo);
+            }
+          }
+        }
+        // Klasse auflösen
+        _ => {
+          if let Some((class, ..)) = current.assignment[[trainee, slot]] {
+            for (slot, slot_candidates) in trainee_candidates.iter().enumerate() {
+              let Some(lesson) = current.assignment[[trainee, slot]] else {
+                continue;
+              };
+              if lesson.0 != class {
+                continue;
+              }
+              let others: Vec<Lesson> = slot_candidates
+                .iter()
+                .copied()
+                .filter(|&other| {
+                  other.0 != class
+                    && current.occupancy.class_load(trainee, other.0) > 0
+                    && current.occupancy.admits(self, trainee, other)
+                })
+                .collect();
+              if let Some(&other) = others.choose(&mut rng) {
+                self.place(&mut current, trainee, slot, Some(other), &mut undo);
+              }
+            }
+          }
+        }
+      }
+
+      iteration += 1;
+      if undo.is_empty() {
+        continue;
+      }
+
+      let mut changed: Vec<usize> = undo.iter().map(|&(trainee, ..)| trainee).collect();
+      changed.sort_unstable();
+      changed.dedup();
+      let previous: Vec<(f64, usize)> = changed.iter().map(|&t| current.terms[t]).collect();
+      let delta = self.refresh(&mut current, &changed)
+        + EMPTY_PENALTY * (current.unfilled as f64 - unfilled as f64);
+      if delta <= 0.0 || rng.gen_bool((-delta / temperature).exp()) {
+        current_energy += delta;
+      } else {
+        for &(trainee, slot, lesson) in undo.iter().rev() {
+          self.assign(&mut current, trainee, slot, lesson);
+        }
+        for (&t, &terms) in changed.iter().zip(&previous) {
+          current.terms[t] = terms;
+        }
+        continue;
+      }
+
+      let cost = self.state_cost(&current);
+      let improves = best.as_ref().is_none_or(|(state, best_cost)| {
+        current.unfilled < state.unfilled
+          || (current.unfilled == state.unfilled && cost < best_cost - Self::COST_EPSILON)
+      });
+      if improves && self.admitted(&current, excluded) {
+        self.report(&current, timeslots, observer);
+        best = Some((current.clone(), cost));
+        improved = iteration;
+      }
+
+      // Rate-limit progresse messages
+      if iteration.is_multiple_of(3197) {
+        observer.progress(&Progress {
+          best: best.as_ref().map_or(f64::INFINITY, |(_, cost)| *cost),
+          current_cost: current_energy,
+          current_classes: current.used_classes.iter().sum(),
+          visited: iteration,
+        });
+      }
+    }
+
+    let (best, cost) = match best {
+      Some((state, cost)) => (Some(self.to_solutions(&state)), cost),
+      None => (None, f64::INFINITY),
+    };
+    Outcome {
+      best,
+      cost,
+      visited: iteration,
+      stopped: limits.stopped,
+      optimal: false,
+    }
+  }
+
+  /// Startbelegung: Stunden mit wenigen möglichen Klassen zuerst, bevorzugt in bereits besuchten
+  /// Klassen und in dem Fach, das am weitesten hinter seinem Ziel liegt
+  fn greedy(&self, candidates: &[Vec<Vec<Lesson>>], required: &[(usize, usize)]) -> State {
+    let mut state = State {
+      assignment: Array2::default((self.trainees.len(), self.time_slots)),
+      occupancy: Occupancy::new(self),
+      used_classes: vec![0; self.trainees.len()],
+      subject_counts: self
+        .trainees
+        .iter()
+        .map(|t| vec![0; t.subject_targets.len()])
+        .collect(),
+      unfilled: required
+        .iter()
+        .filter(|&&(trainee, _)| self.trainees[trainee].workload.fills_all())
+        .count(),
+      terms: vec![(0.0, 0); self.trainees.len()],
+    };
+
+    let mut order = required.to_vec();
+    order.sort_by_key(|&(trainee, slot)| candidates[trainee][slot].len());
+
+    for (trainee, slot) in order {
+      // Bereits als Hälfte einer Doppelstunde belegt
+      if state.assignment[[trainee, slot]].is_some() {
+        continue;
+      }
+
+      // Tage bzw. Wochen, die schon voll sind, bleiben leer
+      let day = &self.days[self.slot_day[slot]];
+      let lessons = day
+        .clone()
+        .filter(|&s| state.assignment[[trainee, s]].is_some())
+        .count();
+      let workload = &self.trainees[trainee].workload;
+      if workload.max_per_day.is_some_and(|max| lessons >= max)
+        || workload
+          .target_per_week
+          .or(workload.max_per_week)
+          .is_some_and(|max| state.subject_counts[trainee].iter().sum::<usize>() >= max)
+      {
+        continue;
+      }
+
+      let score = |(class, subject, _): Lesson| {
+        let problem = &self.trainees[trainee];
+        let target = problem.subject_targets[subject];
+        let deficit = (target - state.subject_counts[trainee][subject] as f64) / target;
+        let reuse = if state.occupancy.class_load(trainee, class) > 0 {
+          1.0
+        } else {
+          0.0
+        };
+        reuse + deficit
+      };
+
+      // Die andere Hälfte einer Doppelstunde muss ebenfalls frei sein
+      let admits = |lesson: Lesson| {
+        let (.., pl_index) = lesson;
+        state.occupancy.admits(self, trainee, lesson)
+          && self
+            .double_partner(trainee, pl_index)
+            .is_none_or(|(other, partner)| {
+              state.assignment[[trainee, other]].is_none()
+                && candidates[trainee][other]
+                  .iter()
+                  .any(|&l| l.2 == partner && state.occupancy.admits(self, trainee, l))
+            })
+      };
+
+      // Bleibt leer, falls alle Klassen durch andere Praktikanten ausgelastet sind
+      let lesson = candidates[trainee][slot]
+        .iter()
+        .copied()
+        .filter(|&lesson| admits(lesson))
+        .max_by(|&a, &b| score(a).total_cmp(&score(b)));
+      if let Some(lesson) = lesson {
+        self.place(&mut state, trainee, slot, Some(lesson), &mut vec![]);
+      }
+    }
+
+    state
+  }
+
+  /// Macht die Startbelegung mit dem [`Workload`](crate::Workload) verträglich: An Tagen mit
+  /// Verstößen bleibt nur der längste zusammenhängende Block, gekürzt auf die Höchstanzahl. Ist er
+  /// zu kurz, wird der Tag geleert. Tage mit festgelegten Stunden bleiben der Suche überlassen.
+  fn repair(&self, state: &mut State) {
+    for (trainee, problem) in self.trainees.iter().enumerate() {
+      if !problem.workload.limits_days() {
+        continue;
+      }
+
+      for slots in &self.days {
+        let load = DayLoad::new(
+          slots
+            .clone()
+            .map(|s| state.assignment[[trainee, s]].is_some()),
+        );
+        if problem.workload.violations(load) == 0 || slots.clone().any(|s| problem.pinned[s]) {
+          continue;
+        }
+
+        // Längster Block belegter Stunden
+        let mut block = slots.start..slots.start;
+        let mut start = slots.start;
+        for slot in slots.clone() {
+          if state.assignment[[trainee, slot]].is_none() {
+            start = slot + 1;
+          } else if slot + 1 - start > block.len() {
+            block = start..slot + 1;
+          }
+        }
+        let max = problem.workload.max_per_day.unwrap_or(usize::MAX);
+        block.end = block.end.min(block.start.saturating_add(max));
+        if problem
+          .workload
+          .min_per_day
+          .is_some_and(|min| block.len() < min)
+        {
+          block = slots.start..slots.start;
+        }
+
+        for slot in slots.clone() {
+          if !block.contains(&slot) && state.assignment[[trainee, slot]].is_some() {
+            self.place(state, trainee, slot, None, &mut vec![]);
+          }
+        }
+      }
+    }
+  }
+
+  /// Belegt `slot` des Praktikanten mit `lesson` bzw. leert die Stunde und aktualisiert die Zähler
+  fn assign(&self, state: &mut State, trainee: usize, slot: usize, lesson: Option<Lesson>) {
+    let counted = usize::from(self.trainees[trainee].workload.fills_all());
+
+    if let Some(old) = state.assignment[[trainee, slot]] {
+      let (_, subject, _) = old;
+      state.subject_counts[trainee][subject] -= 1;
+      if state.occupancy.remove(self, trainee, old) {
+        state.used_classes[trainee] -= 1;
+      }
+    } else {
+      state.unfilled -= counted;
+    }
+
+    if let Some(new) = lesson {
+      let (_, subject, _) = new;
+      state.subject_counts[trainee][subject] += 1;
+      if state.occupancy.add(self, trainee, new) {
+        state.used_classes[trainee] += 1;
+      }
+    } else {
+      state.unfilled += counted;
+    }
+
+    state.assignment[[trainee, slot]] = lesson;
+  }
+
+  /// Wie [`Problem::assign`], hält aber Doppelstunden zusammen: Mit der bisherigen Stunde wird auch
+  /// ihre andere Hälfte geleert, mit `lesson` auch deren andere Hälfte belegt, soweit die
+  /// [`Capacity`](crate::Capacity) es zulässt. Jede Änderung wird in `undo` vermerkt.
+  fn place(
+    &self,
+    state: &mut State,
+    trainee: usize,
+    slot: usize,
+    lesson: Option<Lesson>,
+    undo: &mut Vec<(usize, usize, Option<Lesson>)>,
+  ) {
+    let mut set = |state: &mut State, slot: usize, lesson: Option<Lesson>| {
+      undo.push((trainee, slot, state.assignment[[trainee, slot]]));
+      self.assign(state, trainee, slot, lesson);
+    };
+    // Stunde der anderen Hälfte, falls die Stunde in `slot` eine belegte Doppelstunde ist
+    let partner_slot = |state: &State, slot: usize| {
+      state.assignment[[trainee, slot]]
+        .and_then(|(.., pl_index)| self.double_partner(trainee, pl_index))
+        .filter(|&(other, partner)| {
+          state.assignment[[trainee, other]].is_some_and(|(.., pl_index)| pl_index == partner)
+        })
+        .map(|(other, _)| other)
+    };
+
+    if let Some(other) = partner_slot(state, slot) {
+      set(state, other, None);
+    }
+    set(state, slot, lesson);
+
+    let Some((other, partner)) =
+      lesson.and_then(|(.., pl_index)| self.double_partner(trainee, pl_index))
+    else {
+      return;
+    };
+    if let Some(further) = partner_slot(state, other) {
+      set(state, further, None);
+    }
+    set(state, other, None);
+    if let Some(second) = self.trainees[trainee]
+      .lessons(other)
+      .find(|&(.., pl_index)| pl_index == partner)
+      && state.occupancy.admits(self, trainee, second)
+    {
+      set(state, other, Some(second));
+    }
+  }
+
+  /// Summe der Kosten aller Praktikanten und Anzahl der Verstöße gegen den `Workload` und
+  /// [`Mentors::every_subject`](crate::Mentors)
+  fn evaluate(&self, state: &State) -> (f64, usize) {
+    state
+      .terms
+      .iter()
+      .fold((0.0, 0), |(cost, violations), &(c, v)| {
+        (cost + c, violations + v)
+      })
+  }
+
+  /// Kosten von `trainee` und Anzahl seiner Verstöße gegen den `Workload` und
+  /// [`Mentors::every_subject`](crate::Mentors)
+  fn terms(&self, state: &State, t: usize) -> (f64, usize) {
+    let (gaps, violations) =
+      self.gaps_and_violations(t, |slot| state.assignment[[t, slot]].is_some());
+    let lessons: Vec<Lesson> = state.assignment.row(t).iter().flatten().copied().collect();
+    let mentors = self.mentor_load(t, lessons.iter().map(|&(.., pl_index)| pl_index));
+    let coverage = self.coverage_counts(
+      t,
+      lessons.iter().map(|&(class, subject, _)| (class, subject)),
+    );
+    (
+      self.cost(
+        t,
+        ClassLoad {
+          classes: state.used_classes[t],
+          pairs: lesson_pairs(lessons.iter().map(|&(class, subject, _)| (class, subject))).len(),
+        },
+        &state.subject_counts[t],
+        gaps,
+        mentors,
+        &coverage,
+      ),
+      violations
+        + self.unmet_required(t, state)
+        + self.missing_subjects(t, &state.subject_counts[t])
+        + self.coverage_violations(t, &coverage)
+        + self.broken_doubles(t, |slot| {
+          state.assignment[[t, slot]].map(|(.., pl_index)| pl_index)
+        }),
+    )
+  }
+
+  /// Berechnet `State::terms` für `trainees` neu und gibt die Änderung der Energie ohne die leeren
+  /// Stunden zurück
+  fn refresh(&self, state: &mut State, trainees: &[usize]) -> f64 {
+    trainees
+      .iter()
+      .map(|&t| {
+        let (cost, violations) = self.terms(state, t);
+        let (old_cost, old_violations) = std::mem::replace(&mut state.terms[t], (cost, violations));
+        cost - old_cost + VIOLATION_PENALTY * (violations as f64 - old_violations as f64)
+      })
+      .sum()
+  }
+
+  /// Summe der Kosten aller Praktikanten
+  fn state_cost(&self, state: &State) -> f64 {
+    self.evaluate(state).0
+  }
+
+  /// Kosten der Belegung einschließlich der Strafen für leere Stunden und Verstöße
+  fn energy(&self, state: &State) -> f64 {
+    let (cost, violations) = self.evaluate(state);
+    cost + EMPTY_PENALTY * state.unfilled as f64 + VIOLATION_PENALTY * violations as f64
+  }
+
+  /// Anzahl der Festlegungen ohne feste Stunde, zu denen `state` für `trainee` keine passende
+  /// Stunde belegt
+  fn unmet_required(&self, trainee: usize, state: &State) -> usize {
+    let problem = &self.trainees[trainee];
+    problem
+      .required
+      .iter()
+      .filter(|required| {
+        !(0..self.time_slots).any(|slot| {
+          state.assignment[[trainee, slot]]
+            .is_some_and(|(_, _, pl_index)| required.matches(pl_index))
+        })
+      })
+      .count()
+  }
+
+  /// `state` belegt alle festgelegten Stunden, hält den `Workload` ein und unterscheidet sich genug
+  /// von den bereits gefundenen Lösungen
+  fn admitted(&self, state: &State, excluded: Excluded) -> bool {
+    if self.evaluate(state).1 > 0 {
+      return false;
+    }
+    let pinned_empty = self.trainees.iter().enumerate().any(|(trainee, problem)| {
+      (0..self.time_slots)
+        .any(|slot| problem.pinned[slot] && state.assignment[[trainee, slot]].is_none())
+    });
+    if pinned_empty {
+      return false;
+    }
+    if excluded.solutions().is_empty() {
+      return true;
+    }
+    let solutions = self.to_solutions(state);
+    excluded.admits(|trainee| &solutions[trainee].assignments)
+  }
+
+  /// Meldet `state` als neue beste Lösung
+  fn report(&self, state: &State, timeslots: &[Timeslot], observer: &mut dyn SolverObserver) {
+    observer.solution(&self.plans(&self.to_solutions(state), timeslots));
+  }
+
+  fn to_solutions(&self, state: &State) -> Vec<Solution> {
+    (0..self.trainees.len())
+      .map(|trainee| Solution {
+        assignments: state
+          .assignment
+          .row(trainee)
+          .iter()
+          .map(|lesson| lesson.map(|(.., pl_index)| pl_index))
+          .collect(),
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use willi::WilliStundenplan;
+
+  use crate::{
+    Backend, Capacity, NoopObserver, Objective, SolveOptions, SolveResult, generate,
+    testing::{plan, solve, trainee},
+  };
+
+  fn local_search(plan: &WilliStundenplan, seed: u64) -> SolveResult {
+    generate(
+      plan,
+      &trainee("A"),
+      &Objective::default(),
+      &SolveOptions {
+        backend: Backend::LocalSearch,
+        time_limit: Some(Duration::from_secs(60)),
+        seed,
+        ..Default::default()
+      },
+      &mut NoopObserver,
+    )
+    .unwrap()
+  }
+
+  /// Auf kleinen Plänen erreicht die lokale Suche das Optimum der vollständigen Suche und hört
+  /// auf, wenn sie nicht mehr vorankommt, lange vor dem Zeitlimit
+  #[test]
+  fn matches_search() {
+    for seed in 1..4 {
+      let plan = plan(seed);
+      let search = solve(
+        &plan,
+        &[trainee("A")],
+        &Capacity::default(),
+        Backend::Search,
+      )
+      .unwrap();
+      let local = local_search(&plan, seed);
+
+      assert!(!local.optimal);
+      assert_eq!(local.stopped, None, "Seed {seed}");
+      assert!(
+        (local.cost - search.cost).abs() < 1e-6,
+        "Seed {seed}: lokale Suche {} ≠ Suche {}",
+        local.cost,
+        search.cost
+      );
+    }
+  }
+
+  /// Derselbe Startwert liefert denselben Plan
+  #[test]
+  fn seeded() {
+    let plan = plan(1);
+    let first = local_search(&plan, 7);
+    let second = local_search(&plan, 7);
+
+    assert_eq!(first.solution, second.solution);
+    assert_eq!(first.visited, second.visited);
+  }
+}
@@ -0,0 +1,282 @@
+//! Lehrkräfte, bei denen ein Praktikant hospitiert (Mentoren).
+//!
+//! Jede Lehrkraft muss mit dem Praktikanten abgestimmt werden. Neben den Klassen zählt die
+//! Kostenfunktion deshalb über [`Objective::mentors`](crate::Objective) auch die Lehrkräfte und
+//! bevorzugt über [`Objective::preferred_mentor`](crate::Objective) Stunden beim Betreuungslehrer.
+//! Die Grenzen in [`Mentors`] sind harte Bedingungen. Die Höchstanzahl Stunden je Lehrkraft prüft
+//! [`Occupancy::admits`](crate::trainee::Occupancy) wie die [`Capacity`](crate::Capacity).
+
+use serde::{Deserialize, Serialize};
+use willi::WilliStundenplan;
+
+use crate::{PlannerError, Problem};
+
+/// Vorgaben zu den Lehrkräften eines Praktikanten
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mentors {
+  /// Kürzel des Betreuungslehrers. Stunden bei anderen Lehrkräften kosten
+  /// [`Objective::preferred_mentor`](crate::Objective).
+  pub preferred: Option<String>,
+  /// Höchstanzahl Stunden bei derselben Lehrkraft. `None` = unbegrenzt
+  pub max_lessons: Option<usize>,
+  /// In jedem Fach wird mindestens eine Stunde besucht, es gibt also je Fach mindestens eine
+  /// Lehrkraft
+  pub every_subject: bool,
+}
+
+impl Mentors {
+  pub(crate) fn validate(&self, plan: &WilliStundenplan) -> Result<(), PlannerError> {
+    if let Some(kuerzel) = &self.preferred
+      && plan.lehrkraft_id(kuerzel).is_none()
+    {
+      return Err(PlannerError::InvalidOptions(format!(
+        "Betreuungslehrer „{kuerzel}“ ist im Plan nicht vorhanden"
+      )));
+    }
+    if self.max_lessons == Some(0) {
+      return Err(PlannerError::InvalidOptions(
+        "„max_lessons“ muss mindestens 1 sein".to_string(),
+      ));
+    }
+
+    Ok(())
+  }
+
+  /// Die Vorgaben schränken die Belegung ein
+  pub(crate) fn limits(&self) -> bool {
+    self.max_lessons.is_some() || self.every_subject
+  }
+}
+
+/// Lehrkräfte einer Belegung, soweit sie in die Kosten eingehen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct MentorLoad {
+  /// Anzahl verschiedener Lehrkräfte
+  pub(crate) mentors: usize,
+  /// Stunden, die nicht beim Betreuungslehrer stattfinden, 0 ohne Betreuungslehrer
+  pub(crate) elsewhere: usize,
+}
+
+impl Problem {
+  /// Lehrkräfte mit der Anzahl ihrer Stunden in der Reihenfolge des ersten Besuchs, wenn ein
+  /// Praktikant die Einträge `lessons` im Lehrerplan besucht
+  pub(crate) fn mentor_lessons(
+    &self,
+    lessons: impl IntoIterator<Item = usize>,
+  ) -> Vec<(usize, usize)> {
+    let mut mentors: Vec<(usize, usize)> = vec![];
+    for pl_index in lessons {
+      let teacher = self.lesson_teacher[pl_index];
+      match mentors.iter_mut().find(|(t, _)| *t == teacher) {
+        Some((_, count)) => *count += 1,
+        None => mentors.push((teacher, 1)),
+      }
+    }
+
+    mentors
+  }
+
+  /// Siehe [`MentorLoad`]
+  pub(crate) fn mentor_load(
+    &self,
+    trainee: usize,
+    lessons: impl IntoIterator<Item = usize>,
+  ) -> MentorLoad {
+    let mentors = self.mentor_lessons(lessons);
+    let total: usize = mentors.iter().map(|(_, count)| count).sum();
+    let elsewhere = self.trainees[trainee]
+      .preferred_mentor
+      .map_or(0, |preferred| {
+        total
+          - mentors
+            .iter()
+            .find(|(t, _)| *t == preferred)
+            .map_or(0, |(_, count)| *count)
+      });
+
+    MentorLoad {
+      mentors: mentors.len(),
+      elsewhere,
+    }
+  }
+
+  /// Fächer von `trainee` ohne eine Stunde, obwohl [`Mentors::every_subject`] gesetzt ist
+  pub(crate) fn missing_subjects(&self, trainee: usize, subject_counts: &[usize]) -> usize {
+    if !self.trainees[trainee].mentors.every_subject {
+      return 0;
+    }
+    subject_counts.iter().filter(|&&count| count == 0).count()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use willi::WilliStundenplan;
+
+  use crate::{
+    Backend, Capacity, FachGewichtung, NoopObserver, Objective, Praktikant, SolveOptions,
+    SolveResult, Workload, generate,
+    testing::{plan, solve, trainee},
+  };
+
+  use super::Mentors;
+
+  const BACKENDS: [Backend; 3] = [Backend::Search, Backend::Milp, Backend::LocalSearch];
+
+  fn solve_with(
+    plan: &WilliStundenplan,
+    trainee: &Praktikant,
+    objective: &Objective,
+    backend: Backend,
+  ) -> SolveResult {
+    generate(
+      plan,
+      trainee,
+      objective,
+      &SolveOptions {
+        backend,
+        ..Default::default()
+      },
+      &mut NoopObserver,
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn max_lessons() {
+    let trainees = [Praktikant {
+      mentors: Mentors {
+        max_lessons: Some(2),
+        ..Default::default()
+      },
+      ..trainee("A")
+    }];
+
+    // Ohne Grenze hat mindestens eine Lehrkraft mehr als 2 Stunden
+    let mut limited = false;
+    for seed in 1..4 {
+      let plan = plan(seed);
+      let free = solve_with(&plan, &trainee("A"), &Objective::default(), Backend::Search);
+      limited |= free
+        .breakdown
+        .mentors
+        .lessons
+        .iter()
+        .any(|&count| count > 2);
+
+      for backend in BACKENDS {
+        let result = solve(&plan, &trainees, &Capacity::default(), backend).unwrap();
+        let mentors = &result.plans[0].breakdown.mentors;
+        assert!(
+          mentors.lessons.iter().all(|&count| count <= 2),
+          "{backend:?}, Seed {seed}: {:?}",
+          mentors.lessons
+        );
+      }
+    }
+    assert!(limited);
+  }
+
+  /// Bei wenigen Stunden je Woche fällt ein Fach mit sehr kleinem Gewicht ohne `every_subject` weg
+  #[test]
+  fn every_subject() {
+    let subjects = vec![
+      FachGewichtung {
+        kuerzel: "M".to_string(),
+        gewicht: 1.0,
+      },
+      FachGewichtung {
+        kuerzel: "D".to_string(),
+        gewicht: 0.01,
+      },
+    ];
+    let free = Praktikant {
+      subjects,
+      workload: Workload {
+        target_per_week: Some(2),
+        ..Default::default()
+      },
+      ..Default::default()
+    };
+    let every = Praktikant {
+      mentors: Mentors {
+        every_subject: true,
+        ..Default::default()
+      },
+      ..free.clone()
+    };
+    let objective = Objective {
+      classes: 10.0,
+      balance: 0.01,
+      ..Default::default()
+    };
+
+    let mut dropped = false;
+    for seed in 1..4 {
+      let plan = plan(seed);
+      let result = solve_with(&plan, &free, &objective, Backend::Search);
+      dropped |= result.breakdown.subjects.iter().any(|s| s.actual == 0);
+
+      for backend in BACKENDS {
+        let result = solve_with(&plan, &every, &objective, backend);
+        assert!(
+          result.breakdown.subjects.iter().all(|s| s.actual > 0),
+          "{backend:?}, Seed {seed}"
+        );
+      }
+    }
+    assert!(dropped);
+  }
+
+  /// Mit Gewicht für Stunden anderer Lehrkräfte wird der Betreuungslehrer nie seltener, auf
+  /// mindestens einem Plan häufiger besucht
+  #[test]
+  fn preferred_mentor() {
+    let mut differs = false;
+    for seed in 1..4 {
+      let plan = plan(seed);
+      let free = solve(
+        &plan,
+        &[trainee("A")],
+        &Capacity::default(),
+        Backend::Search,
+      )
+      .unwrap();
+      // Die Lehrkraft mit den wenigsten Stunden in der Lösung ohne Betreuungslehrer
+      let mentors = &free.plans[0].breakdown.mentors;
+      let (preferred, _) = mentors
+        .kuerzel
+        .iter()
+        .zip(&mentors.lessons)
+        .min_by_key(|&(_, count)| count)
+        .unwrap();
+      let trainee = Praktikant {
+        mentors: Mentors {
+          preferred: Some(preferred.clone()),
+          ..Default::default()
+        },
+        ..trainee("A")
+      };
+
+      for backend in [Backend::Search, Backend::Milp] {
+        let elsewhere = |weight: f64| {
+          let objective = Objective {
+            preferred_mentor: weight,
+            ..Default::default()
+          };
+          let breakdown = solve_with(&plan, &trainee, &objective, backend).breakdown;
+          assert_eq!(breakdown.mentors.preferred.as_ref(), Some(preferred));
+          breakdown.mentors.elsewhere
+        };
+        let (without, with) = (elsewhere(0.0), elsewhere(5.0));
+        assert!(
+          with <= without,
+          "{backend:?}, Seed {seed}: {with} > {without}"
+        );
+        differs |= with < without;
+      }
+    }
+    assert!(differs);
+  }
+}
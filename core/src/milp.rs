@@ -0,0 +1,747 @@
+//! Lösung als gemischt-ganzzahliges lineares Programm mit [`microlp`].
+//!
+//! Statt die Stunden nacheinander durchzuprobieren, wird das gesamte [`Problem`] als MILP
+//! formuliert, für jeden Praktikanten `p`:
+//!
+//! * `x[p, c, t]` — binär, Klasse `c` wird in Stunde `t` besucht. Jede Stunde wird höchstens einmal
+//!   belegt, festgelegte Stunden genau einmal. Hat eine Klasse in `t` mehrere passende Einträge im
+//!   Lehrerplan, gibt es ein `x` je Eintrag.
+//! * `y[p, c]` — binär, Klasse `c` wird überhaupt besucht (`x[p, c, t] <= y[p, c]`).
+//! * `d[p, s]` — Abweichung der Stundenzahl von Fach `s` von ihrem Ziel, linearisiert über
+//!   `d[p, s] >= count[p, s] - target[p, s]` und `d[p, s] >= target[p, s] - count[p, s]`.
+//!
+//! Die [`Capacity`](crate::Capacity) begrenzt die Summe der `x` je Stunde im Lehrerplan und die
+//! Summe der `y` je Klasse. Für die Grenze je Lehrkraft und die Kosten je Lehrkraft kommt
+//! `z[p, m]` hinzu, analog zu `y`, siehe `add_mentors`. Der [`Workload`](crate::Workload) und die
+//! Freistunden werden je Woche bzw. je Tag formuliert, siehe `add_workload`. Für jede Gruppe von
+//! Jahrgangsstufen kommt `u[p, k] >= target[p, k] - count[p, k]` hinzu, siehe `add_coverage`, für
+//! jede Kombination aus Klasse und Fach `v[p, c, s]` analog zu `y`, siehe `add_continuity`.
+//! Festlegungen ohne feste Stunde verlangen mindestens ein passendes `x`, siehe `add_required`.
+//!
+//! Wie in der Suche wird zuerst die Anzahl der belegten Stunden maximiert, ohne die Stunden von
+//! Praktikanten mit [`Workload::target_per_week`](crate::Workload). Bei nur einem Praktikanten
+//! ohne weitere Bedingungen ist das jede Stunde, in der mindestens eine Klasse möglich ist, sonst
+//! wird sie in einem ersten Durchlauf bestimmt. Danach wird bei dieser Anzahl
+//! `classes · Σ y[p, c] + balance · Σ d[p, s] / target[p, s] + gaps · Σ g[p, t] +
+//! mentors · Σ z[p, m] + preferred_mentor · Σ x[p, c, t] + coverage · Σ u[p, k] +
+//! continuity · Σ v[p, c, s] + workload · Σ a[p]` (bei `preferred_mentor` nur Stunden anderer
+//! Lehrkräfte als des Betreuungslehrers) mit den Gewichten aus dem [`Objective`](crate::Objective)
+//! minimiert, also dieselbe Kostenfunktion wie in der Suche. Mit
+//! [`Pins::double_lessons`](crate::Pins) haben beide Hälften einer Doppelstunde dasselbe `x`.
+//! Weitere Bedingungen lassen sich als zusätzliche Zeilen ergänzen, z.B. für jede bereits
+//! gefundene Lösung eine Zeile, die mindestens `min_difference` abweichende Stunden verlangt.
+
+use std::collections::BTreeMap;
+
+use microlp::{ComparisonOp, LinearExpr, OptimizationDirection, Variable};
+use tracing::info;
+
+use crate::{
+  Outcome, PlannerError, Problem, Solution, SolveOptions,
+  alternatives::Excluded,
+  continuity::{ClassLoad, lesson_pairs},
+  options::Limits,
+};
+
+/// Eine mögliche Belegung im MILP: (trainee, class, slot, subject, pl_index, x)
+type Assignment = (usize, usize, usize, usize, usize, Variable);
+
+impl Problem {
+  /// Löst das Problem mit einem MILP-Solver.
+  ///
+  /// Die Grenzen aus den [`SolveOptions`] werden nur vor dem Durchlauf geprüft, ein laufender
+  /// Durchlauf lässt sich nicht unterbrechen. Der Durchlauf zählt als ein Knoten.
+  pub(crate) fn solve_milp(
+    &self,
+    options: &SolveOptions,
+    excluded: Excluded,
+  ) -> Result<Outcome, PlannerError> {
+    let mut limits = Limits::new(options);
+    if limits.check_now(0) {
+      return Ok(Outcome {
+        best: None,
+        cost: f64::INFINITY,
+        visited: 0,
+        stopped: limits.stopped,
+        optimal: false,
+      });
+    }
+
+    let infeasible = Outcome {
+      best: None,
+      cost: f64::INFINITY,
+      visited: 1,
+      stopped: None,
+      optimal: true,
+    };
+
+    let filled = if self.trainees.len() == 1
+      && excluded.solutions().is_empty()
+      && !self.trainees[0].workload.limits()
+      && self.trainees[0].workload.fills_all()
+      && !self.trainees[0].mentors.limits()
+      && self.trainees[0].coverage.iter().all(|g| g.min_lessons == 0)
+      && !self.trainees[0].double_lessons
+    {
+      (0..self.time_slots)
+        .filter(|&slot| self.trainees[0].has_candidates(slot))
+        .count()
+    } else {
+      let (lp, assignments) = self.formulate(None, excluded);
+      let solution = match lp.solve() {
+        Ok(solution) => solution,
+        Err(microlp::Error::Infeasible) => return Ok(infeasible),
+        Err(error) => return Err(PlannerError::Solver(error.to_string())),
+      };
+      assignments
+        .iter()
+        .filter(|&&(trainee, .., x)| {
+          self.trainees[trainee].workload.fills_all() && solution.var_value_rounded(x) > 0.5
+        })
+        .count()
+    };
+
+    let (lp, assignments) = self.formulate(Some(filled), excluded);
+    let solution = match lp.solve() {
+      Ok(solution) => solution,
+      Err(microlp::Error::Infeasible) => return Ok(infeasible),
+      Err(error) => return Err(PlannerError::Solver(error.to_string())),
+    };
+
+    let mut solutions = vec![
+      Solution {
+        assignments: vec![None; self.time_slots],
+      };
+      self.trainees.len()
+    ];
+    let mut used_classes = vec![vec![]; self.trainees.len()];
+    // [trainee] = (class, subject) der besuchten Stunden
+    let mut visited = vec![vec![]; self.trainees.len()];
+    let mut subject_counts: Vec<Vec<usize>> = self
+      .trainees
+      .iter()
+      .map(|t| vec![0; t.subject_targets.len()])
+      .collect();
+
+    for &(trainee, class, slot, subject, pl_index, x) in &assignments {
+      if solution.var_value_rounded(x) < 0.5 {
+        continue;
+      }
+
+      solutions[trainee].assignments[slot] = Some(pl_index);
+      if !used_classes[trainee].contains(&class) {
+        used_classes[trainee].push(class);
+      }
+      subject_counts[trainee][subject] += 1;
+      visited[trainee].push((class, subject));
+    }
+
+    // Kosten wie in der Suche berechnen, damit beide Verfahren vergleichbar bleiben
+    let cost = (0..self.trainees.len())
+      .map(|t| {
+        let (gaps, _) =
+          self.gaps_and_violations(t, |slot| solutions[t].assignments[slot].is_some());
+        let mentors = self.mentor_load(t, solutions[t].assignments.iter().flatten().copied());
+        let coverage = self.coverage_counts(t, visited[t].iter().copied());
+        self.cost(
+          t,
+          ClassLoad {
+            classes: used_classes[t].len(),
+            pairs: lesson_pairs(visited[t].iter().copied()).len(),
+          },
+          &subject_counts[t],
+          gaps,
+          mentors,
+          &coverage,
+        )
+      })
+      .sum();
+    info!("MILP objective {}, cost {cost}", solution.objective());
+
+    Ok(Outcome {
+      best: Some(solutions),
+      cost,
+      visited: 1,
+      stopped: None,
+      optimal: true,
+    })
+  }
+
+  /// Es gibt eine Belegung, die alle harten Bedingungen einhält. Entspricht dem ersten Durchlauf
+  /// ohne Kosten.
+  pub(crate) fn feasible(&self) -> Result<bool, PlannerError> {
+    let (lp, _) = self.formulate(None, Excluded::default());
+    match lp.solve() {
+      Ok(_) => Ok(true),
+      Err(microlp::Error::Infeasible) => Ok(false),
+      Err(error) => Err(PlannerError::Solver(error.to_string())),
+    }
+  }
+
+  /// Stellt das MILP auf. Ohne `filled` wird nur die Anzahl der belegten Stunden maximiert, sonst
+  /// werden bei mindestens `filled` belegten Stunden die Kosten minimiert.
+  fn formulate(
+    &self,
+    filled: Option<usize>,
+    excluded: Excluded,
+  ) -> (microlp::Problem, Vec<Assignment>) {
+    let mut lp = microlp::Problem::new(OptimizationDirection::Minimize);
+    let cost_weight = if filled.is_some() { 1.0 } else { 0.0 };
+
+    // [trainee][class] = y
+    let used: Vec<Vec<Variable>> = self
+      .trainees
+      .iter()
+      .map(|_| {
+        (0..self.classes)
+          .map(|_| lp.add_binary_var(cost_weight * self.objective.classes))
+          .collect()
+      })
+      .collect();
+
+    let mut assignments: Vec<Assignment> = vec![];
+    let mut total = LinearExpr::empty();
+    for (trainee, problem) in self.trainees.iter().enumerate() {
+      for slot in 0..self.time_slots {
+        let mut slot_expr = LinearExpr::empty();
+        let mut any = false;
+
+        for (class, subject, pl_index) in problem.lessons(slot) {
+          let y = used[trainee][class];
+          let elsewhere = problem
+            .preferred_mentor
+            .is_some_and(|preferred| preferred != self.lesson_teacher[pl_index]);
+          let fills_all = problem.workload.fills_all();
+          let x = lp.add_binary_var(match filled {
+            Some(_) if elsewhere => self.objective.preferred_mentor,
+            Some(_) => 0.0,
+            None if fills_all => -1.0,
+            None => 0.0,
+          });
+          lp.add_constraint([(x, 1.0), (y, -1.0)], ComparisonOp::Le, 0.0);
+          slot_expr.add(x, 1.0);
+          if fills_all {
+            total.add(x, 1.0);
+          }
+          any = true;
+
+          assignments.push((trainee, class, slot, subject, pl_index, x));
+        }
+
+        if problem.pinned[slot] {
+          lp.add_constraint(slot_expr, ComparisonOp::Eq, 1.0);
+        } else if any {
+          lp.add_constraint(slot_expr, ComparisonOp::Le, 1.0);
+        }
+      }
+    }
+
+    if let Some(filled) = filled {
+      lp.add_constraint(total, ComparisonOp::Ge, filled as f64);
+    }
+
+    let mentors = self.add_mentors(&mut lp, &assignments, cost_weight);
+    self.add_capacity(&mut lp, &used, &mentors, &assignments);
+    self.add_excluded(&mut lp, &assignments, excluded);
+    self.add_workload(&mut lp, &assignments, cost_weight);
+    self.add_coverage(&mut lp, &assignments, cost_weight);
+    self.add_required(&mut lp, &assignments);
+    self.add_doubles(&mut lp, &assignments);
+    self.add_continuity(&mut lp, &assignments, cost_weight);
+
+    if filled.is_none() {
+      return (lp, assignments);
+    }
+
+    for (trainee, problem) in self.trainees.iter().enumerate() {
+      for (subject, &target) in problem.subject_targets.iter().enumerate() {
+        let deviation = lp.add_var(self.objective.balance / target, (0.0, f64::INFINITY));
+
+        let count: Vec<(Variable, f64)> = assignments
+          .iter()
+          .filter(|&&(t, _, _, s, ..)| t == trainee && s == subject)
+          .map(|&(.., x)| (x, 1.0))
+          .collect();
+
+        // deviation >= count - target
+        lp.add_constraint(
+          count.iter().copied().chain([(deviation, -1.0)]),
+          ComparisonOp::Le,
+          target,
+        );
+        // deviation >= target - count
+        lp.add_constraint(
+          count.iter().copied().chain([(deviation, 1.0)]),
+          ComparisonOp::Ge,
+          target,
+        );
+      }
+    }
+
+    (lp, assignments)
+  }
+
+  /// Zeilen für den [`Workload`](crate::Workload) und die Freistunden je Tag. Mit `f[p, t]` als
+  /// Summe der `x[p, c, t]` begrenzen die Grenzen je Woche bzw. Tag die Summe der `f[p, t]`, dazu:
+  ///
+  /// * `w[p, d]` — binär, Tag `d` hat Unterricht, für die Mindestanzahl Stunden je Tag
+  /// * `s[p, t]`, `e[p, t]` — am Tag von `t` wurde bis einschließlich `t` bzw. ab `t` eine Stunde
+  ///   belegt, über `s[p, t] >= s[p, t - 1]`, `s[p, t] >= f[p, t]` und umgekehrt für `e`
+  /// * `g[p, t] >= s[p, t] + e[p, t] - 1 - f[p, t]` — `t` ist eine Freistunde, mit Gewicht
+  ///   [`Objective::gaps`](crate::Objective)
+  /// * `a[p]` — Abweichung der Summe der `f[p, t]` von
+  ///   [`Workload::target_per_week`](crate::Workload), linearisiert wie `d[p, s]`, mit Gewicht
+  ///   [`Objective::workload`](crate::Objective)
+  fn add_workload(&self, lp: &mut microlp::Problem, assignments: &[Assignment], cost_weight: f64) {
+    // [(trainee, slot)] = x aller Klassen
+    let mut filled: BTreeMap<(usize, usize), Vec<Variable>> = BTreeMap::new();
+    for &(trainee, _, slot, .., x) in assignments {
+      filled.entry((trainee, slot)).or_default().push(x);
+    }
+    let f = |trainee: usize, slot: usize| -> Vec<(Variable, f64)> {
+      filled
+        .get(&(trainee, slot))
+        .map_or(vec![], |xs| xs.iter().map(|&x| (x, 1.0)).collect())
+    };
+
+    for (trainee, problem) in self.trainees.iter().enumerate() {
+      let workload = &problem.workload;
+      let track_gaps = workload.max_gaps.is_some() || self.objective.gaps > 0.0;
+
+      let week: Vec<(Variable, f64)> = (0..self.time_slots)
+        .flat_map(|slot| f(trainee, slot))
+        .collect();
+      if let Some(min) = workload.min_per_week {
+        lp.add_constraint(week.iter().copied(), ComparisonOp::Ge, min as f64);
+      }
+      if let Some(max) = workload.max_per_week
+        && week.len() > max
+      {
+        lp.add_constraint(week.iter().copied(), ComparisonOp::Le, max as f64);
+      }
+      if let Some(target) = workload.target_per_week {
+        // a >= week - target und a >= target - week
+        let a = lp.add_var(cost_weight * self.objective.workload, (0.0, f64::INFINITY));
+        lp.add_constraint(
+          week.iter().copied().chain([(a, -1.0)]),
+          ComparisonOp::Le,
+          target as f64,
+        );
+        lp.add_constraint(
+          week.iter().copied().chain([(a, 1.0)]),
+          ComparisonOp::Ge,
+          target as f64,
+        );
+      }
+
+      for slots in &self.days {
+        let lessons: Vec<(Variable, f64)> =
+          slots.clone().flat_map(|slot| f(trainee, slot)).collect();
+        if lessons.is_empty() {
+          continue;
+        }
+
+        if let Some(max) = workload.max_per_day
+          && lessons.len() > max
+        {
+          lp.add_constraint(lessons.iter().copied(), ComparisonOp::Le, max as f64);
+        }
+
+        if let Some(min) = workload.min_per_day {
+          // lessons <= n · w und lessons >= min · w
+          let w = lp.add_binary_var(0.0);
+          let n = slots.len() as f64;
+          lp.add_constraint(
+            lessons.iter().copied().chain([(w, -n)]),
+            ComparisonOp::Le,
+            0.0,
+          );
+          lp.add_constraint(
+            lessons.iter().copied().chain([(w, -(min as f64))]),
+            ComparisonOp::Ge,
+            0.0,
+          );
+        }
+
+        if !track_gaps {
+          continue;
+        }
+
+        let started: Vec<Variable> = slots.clone().map(|_| lp.add_var(0.0, (0.0, 1.0))).collect();
+        let ending: Vec<Variable> = slots.clone().map(|_| lp.add_var(0.0, (0.0, 1.0))).collect();
+        let mut gaps = vec![];
+
+        for (i, slot) in slots.clone().enumerate() {
+          let f_t = f(trainee, slot);
+          lp.add_constraint(
+            f_t
+              .iter()
+              .map(|&(x, _)| (x, -1.0))
+              .chain([(started[i], 1.0)]),
+            ComparisonOp::Ge,
+            0.0,
+          );
+          lp.add_constraint(
+            f_t
+              .iter()
+              .map(|&(x, _)| (x, -1.0))
+              .chain([(ending[i], 1.0)]),
+            ComparisonOp::Ge,
+            0.0,
+          );
+          if i > 0 {
+            lp.add_constraint(
+              [(started[i], 1.0), (started[i - 1], -1.0)],
+              ComparisonOp::Ge,
+              0.0,
+            );
+            lp.add_constraint(
+              [(ending[i - 1], 1.0), (ending[i], -1.0)],
+              ComparisonOp::Ge,
+              0.0,
+            );
+          }
+
+          // g >= s + e - 1 - f
+          let g = lp.add_var(cost_weight * self.objective.gaps, (0.0, 1.0));
+          lp.add_constraint(
+            f_t
+              .iter()
+              .copied()
+              .chain([(g, 1.0), (started[i], -1.0), (ending[i], -1.0)]),
+            ComparisonOp::Ge,
+            -1.0,
+          );
+          gaps.push(g);
+        }
+
+        if let Some(max) = workload.max_gaps {
+          lp.add_constraint(gaps.iter().map(|&g| (g, 1.0)), ComparisonOp::Le, max as f64);
+        }
+      }
+    }
+  }
+
+  /// Je bereits gefundener Lösung eine Zeile: Die Anzahl der Stunden, die nicht mehr mit der Stunde
+  /// aus dieser Lösung belegt sind, plus die Anzahl der dort leeren, jetzt belegten Stunden muss
+  /// mindestens `min_difference` betragen.
+  fn add_excluded(
+    &self,
+    lp: &mut microlp::Problem,
+    assignments: &[Assignment],
+    excluded: Excluded,
+  ) {
+    for solutions in excluded.solutions() {
+      let mut row = vec![];
+      // Anzahl der belegten Stunden in der ausgeschlossenen Lösung
+      let mut assigned = 0.0;
+
+      for &(trainee, _, slot, _, pl_index, x) in assignments {
+        match solutions[trainee].assignments[slot] {
+          Some(previous) if previous == pl_index => {
+            row.push((x, -1.0));
+            assigned += 1.0;
+          }
+          Some(_) => {}
+          None => row.push((x, 1.0)),
+        }
+      }
+
+      lp.add_constraint(
+        row,
+        ComparisonOp::Ge,
+        excluded.min_difference() as f64 - assigned,
+      );
+    }
+  }
+
+  /// Zeilen für die Gruppen von Jahrgangsstufen: Die Summe der passenden `x` ist mindestens
+  /// `min_lessons`, die fehlenden Stunden bis `target` kosten
+  /// [`Objective::coverage`](crate::Objective).
+  fn add_coverage(&self, lp: &mut microlp::Problem, assignments: &[Assignment], cost_weight: f64) {
+    for (trainee, problem) in self.trainees.iter().enumerate() {
+      for group in &problem.coverage {
+        let count: Vec<(Variable, f64)> = assignments
+          .iter()
+          .filter(|&&(t, class, _, subject, ..)| t == trainee && group.matches(class, subject))
+          .map(|&(.., x)| (x, 1.0))
+          .collect();
+
+        if group.min_lessons > 0 {
+          lp.add_constraint(
+            count.iter().copied(),
+            ComparisonOp::Ge,
+            group.min_lessons as f64,
+          );
+        }
+        if group.target > 0 && self.objective.coverage > 0.0 {
+          // u >= target - count
+          let u = lp.add_var(cost_weight * self.objective.coverage, (0.0, f64::INFINITY));
+          lp.add_constraint(
+            count.iter().copied().chain([(u, 1.0)]),
+            ComparisonOp::Ge,
+            group.target as f64,
+          );
+        }
+      }
+    }
+  }
+
+  /// Je Festlegung ohne feste Stunde eine Zeile: Die Summe der passenden `x` ist mindestens 1
+  fn add_required(&self, lp: &mut microlp::Problem, assignments: &[Assignment]) {
+    for (trainee, problem) in self.trainees.iter().enumerate() {
+      for required in &problem.required {
+        lp.add_constraint(
+          assignments
+            .iter()
+            .filter(|&&(t, .., pl_index, _)| t == trainee && required.matches(pl_index))
+            .map(|&(.., x)| (x, 1.0)),
+          ComparisonOp::Ge,
+          1.0,
+        );
+      }
+    }
+  }
+
+  /// Variablen `v[p, c, s]` für die Kombinationen aus Klasse und Fach mit
+  /// `x[p, c, t] <= v[p, c, s]` und Gewicht [`Objective::continuity`](crate::Objective), nur wenn
+  /// das Gewicht gesetzt ist
+  fn add_continuity(
+    &self,
+    lp: &mut microlp::Problem,
+    assignments: &[Assignment],
+    cost_weight: f64,
+  ) {
+    if self.objective.continuity <= 0.0 {
+      return;
+    }
+
+    // [(trainee, class, subject)] = v
+    let mut pairs: BTreeMap<(usize, usize, usize), Variable> = BTreeMap::new();
+    for &(trainee, class, _, subject, _, x) in assignments {
+      let v = *pairs
+        .entry((trainee, class, subject))
+        .or_insert_with(|| lp.add_binary_var(cost_weight * self.objective.continuity));
+      lp.add_constraint([(x, 1.0), (v, -1.0)], ComparisonOp::Le, 0.0);
+    }
+  }
+
+  /// Zeilen für die Doppelstunden: Die `x` beider Hälften sind gleich
+  fn add_doubles(&self, lp: &mut microlp::Problem, assignments: &[Assignment]) {
+    // [(trainee, pl_index)] = x
+    let xs: BTreeMap<(usize, usize), Variable> = assignments
+      .iter()
+      .map(|&(trainee, .., pl_index, x)| ((trainee, pl_index), x))
+      .collect();
+
+    for &(trainee, _, slot, _, pl_index, x) in assignments {
+      if let Some((other, partner)) = self.double_partner(trainee, pl_index)
+        && other > slot
+        && let Some(&y) = xs.get(&(trainee, partner))
+      {
+        lp.add_constraint([(x, 1.0), (y, -1.0)], ComparisonOp::Eq, 0.0);
+      }
+    }
+  }
+
+  /// Variablen `z[p, m]` für die Lehrkräfte, sofern die [`Capacity`](crate::Capacity) oder die
+  /// Kosten sie brauchen, sowie Zeilen für die [`Mentors`](crate::Mentors): Die Summe der `x` je
+  /// Praktikant und Lehrkraft ist höchstens `max_lessons`, die je Praktikant und Fach mindestens 1.
+  fn add_mentors(
+    &self,
+    lp: &mut microlp::Problem,
+    assignments: &[Assignment],
+    cost_weight: f64,
+  ) -> BTreeMap<(usize, usize), Variable> {
+    let track = self.capacity.per_mentor.is_some() || self.objective.mentors > 0.0;
+
+    // [(trainee, teacher)] = z
+    let mut mentors: BTreeMap<(usize, usize), Variable> = BTreeMap::new();
+    // [(trainee, teacher)] = x
+    let mut lessons: BTreeMap<(usize, usize), Vec<Variable>> = BTreeMap::new();
+    // [(trainee, subject)] = x
+    let mut subjects: BTreeMap<(usize, usize), Vec<Variable>> = BTreeMap::new();
+
+    for &(trainee, _, _, subject, pl_index, x) in assignments {
+      let teacher = self.lesson_teacher[pl_index];
+      lessons.entry((trainee, teacher)).or_default().push(x);
+      subjects.entry((trainee, subject)).or_default().push(x);
+
+      if track {
+        let z = *mentors
+          .entry((trainee, teacher))
+          .or_insert_with(|| lp.add_binary_var(cost_weight * self.objective.mentors));
+        lp.add_constraint([(x, 1.0), (z, -1.0)], ComparisonOp::Le, 0.0);
+      }
+    }
+
+    for (&(trainee, _), xs) in &lessons {
+      if let Some(max) = self.trainees[trainee].mentors.max_lessons
+        && xs.len() > max
+      {
+        lp.add_constraint(xs.iter().map(|&x| (x, 1.0)), ComparisonOp::Le, max as f64);
+      }
+    }
+
+    for (trainee, problem) in self.trainees.iter().enumerate() {
+      if !problem.mentors.every_subject {
+        continue;
+      }
+      for subject in 0..problem.subject_targets.len() {
+        let xs = subjects
+          .get(&(trainee, subject))
+          .map_or(&[][..], Vec::as_slice);
+        lp.add_constraint(xs.iter().map(|&x| (x, 1.0)), ComparisonOp::Ge, 1.0);
+      }
+    }
+
+    mentors
+  }
+
+  /// Zeilen für die [`Capacity`](crate::Capacity). Grenzen, die ohnehin nicht erreicht werden
+  /// können, werden weggelassen.
+  fn add_capacity(
+    &self,
+    lp: &mut microlp::Problem,
+    used: &[Vec<Variable>],
+    mentors: &BTreeMap<(usize, usize), Variable>,
+    assignments: &[Assignment],
+  ) {
+    let capacity = &self.capacity;
+
+    // [pl_index] = x aller Praktikanten in dieser Stunde
+    let mut lessons: BTreeMap<usize, Vec<Variable>> = BTreeMap::new();
+    for &(.., pl_index, x) in assignments {
+      lessons.entry(pl_index).or_default().push(x);
+    }
+
+    for xs in lessons.values() {
+      if xs.len() > capacity.per_lesson {
+        lp.add_constraint(
+          xs.iter().map(|&x| (x, 1.0)),
+          ComparisonOp::Le,
+          capacity.per_lesson as f64,
+        );
+      }
+    }
+
+    if let Some(limit) = capacity.per_class
+      && used.len() > limit
+    {
+      for class in 0..self.classes {
+        lp.add_constraint(
+          used.iter().map(|y| (y[class], 1.0)),
+          ComparisonOp::Le,
+          limit as f64,
+        );
+      }
+    }
+
+    if let Some(limit) = capacity.per_mentor {
+      // [teacher] = z aller Praktikanten bei dieser Lehrkraft
+      let mut teachers: BTreeMap<usize, Vec<Variable>> = BTreeMap::new();
+      for (&(_, teacher), &z) in mentors {
+        teachers.entry(teacher).or_default().push(z);
+      }
+
+      for zs in teachers.values() {
+        if zs.len() > limit {
+          lp.add_constraint(zs.iter().map(|&z| (z, 1.0)), ComparisonOp::Le, limit as f64);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    Backend, CancellationToken, Capacity, NoopObserver, Objective, PlannerError, SolveOptions,
+    StopReason, generate,
+    testing::{plan, solve, trainee},
+  };
+
+  /// Das MILP findet dieselben Kosten wie die vollständige Suche, beide bewiesen optimal
+  #[test]
+  fn matches_search() {
+    for seed in 1..6 {
+      let plan = plan(seed);
+      let search = solve(
+        &plan,
+        &[trainee("A")],
+        &Capacity::default(),
+        Backend::Search,
+      )
+      .unwrap();
+      let milp = solve(&plan, &[trainee("A")], &Capacity::default(), Backend::Milp).unwrap();
+
+      assert!(search.optimal && milp.optimal, "Seed {seed}");
+      assert!(
+        (search.cost - milp.cost).abs() < 1e-6,
+        "Seed {seed}: Suche {} ≠ MILP {}",
+        search.cost,
+        milp.cost
+      );
+    }
+  }
+
+  /// Ist eine Grenze schon vor dem Durchlauf erreicht, wird das MILP gar nicht erst gelöst
+  #[test]
+  fn limits_checked_before_pass() {
+    let plan = plan(1);
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+
+    let cases = [
+      (
+        SolveOptions {
+          node_limit: Some(0),
+          ..Default::default()
+        },
+        StopReason::NodeLimit,
+      ),
+      (
+        SolveOptions {
+          cancel: Some(cancel),
+          ..Default::default()
+        },
+        StopReason::Cancelled,
+      ),
+    ];
+
+    for (options, reason) in cases {
+      let options = SolveOptions {
+        backend: Backend::Milp,
+        ..options
+      };
+      let error = generate(
+        &plan,
+        &trainee("A"),
+        &Objective::default(),
+        &options,
+        &mut NoopObserver,
+      )
+      .unwrap_err();
+      assert!(
+        matches!(error, PlannerError::Stopped(r) if r == reason),
+        "{error:?}"
+      );
+    }
+
+    // Ein Durchlauf zählt als ein Knoten
+    let result = generate(
+      &plan,
+      &trainee("A"),
+      &Objective::default(),
+      &SolveOptions {
+        backend: Backend::Milp,
+        node_limit: Some(1),
+        ..Default::default()
+      },
+      &mut NoopObserver,
+    )
+    .unwrap();
+    assert!(result.optimal);
+    assert_eq!(result.visited, 1);
+  }
+}
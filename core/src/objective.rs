@@ -0,0 +1,285 @@
+//! Gewichtung der einzelnen Bestandteile der Kostenfunktion.
+
+use serde::{Deserialize, Serialize};
+
+use crate::PlannerError;
+
+/// Gewichte der Kostenfunktion, die alle Verfahren minimieren:
+///
+/// `classes · Anzahl der Klassen + balance · Abweichung von der Fächergewichtung
+/// + gaps · Anzahl der Freistunden + mentors · Anzahl der Lehrkräfte
+/// + preferred_mentor · Stunden nicht beim Betreuungslehrer
+/// + coverage · fehlende Stunden in Jahrgangsstufen
+/// + continuity · Anzahl der Kombinationen aus Klasse und Fach
+/// + workload · Abweichung von der angestrebten Anzahl Stunden je Woche`
+///
+/// Die Abweichung ist die Summe der relativen Abweichungen der Stundenzahl jedes Fachs von seinem
+/// Anteil an allen Stunden. Freistunden sind leere Unterrichtsstunden zwischen zwei belegten
+/// Stunden desselben Tages, siehe [`Workload`](crate::Workload). Den Betreuungslehrer legt
+/// [`Mentors::preferred`](crate::Mentors) fest, die fehlenden Stunden beziehen sich auf
+/// [`GradeCoverage::target`](crate::GradeCoverage), das Ziel je Woche setzt
+/// [`Workload::target_per_week`](crate::Workload). Zur Kontinuität siehe
+/// [`FollowedPair`](crate::FollowedPair).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Objective {
+  /// Gewicht je besuchter Klasse
+  pub classes: f64,
+  /// Gewicht der gleichmäßigen Verteilung der Fächer. 0 = Verteilung wird ignoriert
+  pub balance: f64,
+  /// Gewicht je Freistunde. 0 = zusammenhängende Blöcke werden nicht bevorzugt
+  pub gaps: f64,
+  /// Gewicht je Lehrkraft, bei der der Praktikant hospitiert
+  pub mentors: f64,
+  /// Gewicht je Stunde, die nicht beim Betreuungslehrer stattfindet
+  pub preferred_mentor: f64,
+  /// Gewicht je Stunde, die in einer Gruppe von Jahrgangsstufen bis zu ihrem Ziel fehlt
+  pub coverage: f64,
+  /// Gewicht je besuchter Kombination aus Klasse und Fach. Bevorzugt, dieselbe Klasse im selben
+  /// Fach mehrmals zu besuchen.
+  pub continuity: f64,
+  /// Gewicht je Stunde über oder unter [`Workload::target_per_week`](crate::Workload)
+  pub workload: f64,
+}
+
+impl Default for Objective {
+  fn default() -> Self {
+    Objective {
+      classes: 1.0,
+      balance: 3.0,
+      gaps: 0.0,
+      mentors: 0.0,
+      preferred_mentor: 0.0,
+      coverage: 0.0,
+      continuity: 0.0,
+      workload: 1.0,
+    }
+  }
+}
+
+impl Objective {
+  /// Alle Gewichte müssen endlich und nicht negativ sein, sonst wäre die untere Schranke der Suche
+  /// nicht mehr gültig
+  pub(crate) fn validate(&self) -> Result<(), PlannerError> {
+    for (name, weight) in [
+      ("classes", self.classes),
+      ("balance", self.balance),
+      ("gaps", self.gaps),
+      ("mentors", self.mentors),
+      ("preferred_mentor", self.preferred_mentor),
+      ("coverage", self.coverage),
+      ("continuity", self.continuity),
+      ("workload", self.workload),
+    ] {
+      if !weight.is_finite() || weight < 0.0 {
+        return Err(PlannerError::InvalidWeights(format!(
+          "Gewicht „{name}“ der Zielfunktion muss endlich und nicht negativ sein, ist aber {weight}"
+        )));
+      }
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use willi::WilliStundenplan;
+
+  use super::Objective;
+  use crate::{
+    NoopObserver, PlannerError, Praktikant, SolveOptions, SolveResult, Workload, generate,
+    testing::{plan, trainee},
+  };
+
+  fn solve(plan: &WilliStundenplan, objective: &Objective) -> Result<SolveResult, PlannerError> {
+    generate(
+      plan,
+      &trainee("A"),
+      objective,
+      &SolveOptions::default(),
+      &mut NoopObserver,
+    )
+  }
+
+  /// Anzahl der besuchten Klassen und Stunden je Fach (in der Reihenfolge von `subjects`)
+  fn counts(plan: &WilliStundenplan, result: &SolveResult) -> (usize, Vec<usize>) {
+    let mut classes = vec![];
+    let mut subjects = vec![0; 2];
+    for &pl_index in result.solution.iter().flatten().flatten() {
+      let line = &plan.lehrerstunden()[pl_index];
+      if !classes.contains(&line.klasse) {
+        classes.push(line.klasse);
+      }
+      let kuerzel = &plan.fach(line.fach).unwrap().kuerzel;
+      subjects[usize::from(kuerzel == "D")] += 1;
+    }
+    (classes.len(), subjects)
+  }
+
+  /// Anzahl der Unterrichtsstunden im Wochenraster
+  fn time_slots(plan: &WilliStundenplan) -> usize {
+    let periods = plan.stundenraster().count();
+    plan
+      .tage()
+      .iter()
+      .map(|(_, tag)| {
+        tag
+          .stundenmerkmale
+          .chars()
+          .take(periods)
+          .filter(|c| ['v', 'V'].contains(c))
+          .count()
+      })
+      .sum()
+  }
+
+  /// Negative und nicht endliche Gewichte werden vor der Suche abgelehnt
+  #[test]
+  fn invalid_weights_rejected() {
+    let plan = plan(1);
+    for weight in [-1.0, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+      for objective in [
+        Objective {
+          classes: weight,
+          ..Default::default()
+        },
+        Objective {
+          balance: weight,
+          ..Default::default()
+        },
+        Objective {
+          gaps: weight,
+          ..Default::default()
+        },
+        Objective {
+          mentors: weight,
+          ..Default::default()
+        },
+        Objective {
+          preferred_mentor: weight,
+          ..Default::default()
+        },
+        Objective {
+          coverage: weight,
+          ..Default::default()
+        },
+        Objective {
+          continuity: weight,
+          ..Default::default()
+        },
+        Objective {
+          workload: weight,
+          ..Default::default()
+        },
+      ] {
+        let error = solve(&plan, &objective).unwrap_err();
+        assert!(
+          matches!(error, PlannerError::InvalidWeights(_)),
+          "{objective:?}: {error:?}"
+        );
+      }
+    }
+
+    assert!(
+      solve(
+        &plan,
+        &Objective {
+          classes: 0.0,
+          balance: 0.0,
+          gaps: 0.0,
+          mentors: 0.0,
+          preferred_mentor: 0.0,
+          coverage: 0.0,
+          continuity: 0.0,
+          workload: 0.0,
+        }
+      )
+      .is_ok()
+    );
+  }
+
+  /// Nur nach Klassen gewichtet besucht der Plan höchstens so viele Klassen wie nur nach
+  /// Verteilung gewichtet, dafür weicht er höchstens stärker von der Gewichtung ab. Auf
+  /// mindestens einem Plan unterscheiden sich die beiden tatsächlich.
+  #[test]
+  fn balance_changes_plan() {
+    let classes_only = Objective {
+      classes: 1.0,
+      balance: 0.0,
+      gaps: 0.0,
+      mentors: 0.0,
+      preferred_mentor: 0.0,
+      coverage: 0.0,
+      continuity: 0.0,
+      workload: 0.0,
+    };
+    let balance_only = Objective {
+      classes: 0.0,
+      balance: 1.0,
+      gaps: 0.0,
+      mentors: 0.0,
+      preferred_mentor: 0.0,
+      coverage: 0.0,
+      continuity: 0.0,
+      workload: 0.0,
+    };
+
+    let mut differs = false;
+    for seed in 1..6 {
+      let plan = plan(seed);
+      let (classes_a, subjects_a) = counts(&plan, &solve(&plan, &classes_only).unwrap());
+      let (classes_b, subjects_b) = counts(&plan, &solve(&plan, &balance_only).unwrap());
+      // Abweichung wie in der Kostenfunktion, beide Fächer sind gleich gewichtet
+      let target = time_slots(&plan) as f64 / 2.0;
+      let imbalance = |s: &[usize]| s.iter().map(|&c| (c as f64 - target).abs()).sum::<f64>();
+
+      assert!(classes_a <= classes_b, "Seed {seed}");
+      assert!(
+        imbalance(&subjects_b) <= imbalance(&subjects_a),
+        "Seed {seed}"
+      );
+      differs |= classes_a < classes_b || imbalance(&subjects_b) < imbalance(&subjects_a);
+    }
+    assert!(differs);
+  }
+
+  /// Mit höchstens 3 von 5 Stunden am Tag bleiben Lücken möglich. Mit Gewicht für Freistunden hat
+  /// der Plan höchstens so viele wie ohne, auf mindestens einem Plan tatsächlich weniger.
+  #[test]
+  fn gaps_change_plan() {
+    let trainee = Praktikant {
+      workload: Workload {
+        max_per_day: Some(3),
+        ..Default::default()
+      },
+      ..trainee("A")
+    };
+    let gaps = |plan: &WilliStundenplan, gaps: f64| {
+      let objective = Objective {
+        gaps,
+        ..Default::default()
+      };
+      generate(
+        plan,
+        &trainee,
+        &objective,
+        &SolveOptions::default(),
+        &mut NoopObserver,
+      )
+      .unwrap()
+      .breakdown
+      .gaps
+      .count
+    };
+
+    let mut differs = false;
+    for seed in 1..6 {
+      let plan = plan(seed);
+      let (without, with) = (gaps(&plan, 0.0), gaps(&plan, 1.0));
+      assert!(with <= without, "Seed {seed}: {with} > {without}");
+      differs |= with < without;
+    }
+    assert!(differs);
+  }
+}
@@ -0,0 +1,155 @@
+//! Rückmeldungen des Planers während der Suche.
+//!
+//! Im Browser werden Fortschritt und Zwischenlösungen per `postMessage` an den Hauptthread
+//! geschickt ([`WorkerObserver`]), nativ z.B. über einen Channel ([`ChannelObserver`]) oder eine
+//! Callback-Funktion ([`CallbackObserver`]).
+
+use std::sync::mpsc::Sender;
+
+use serde::Serialize;
+use wasm_bindgen::JsCast;
+
+use crate::TraineePlan;
+
+/// Zwischenstand der Suche
+#[derive(Debug, Clone, Serialize)]
+pub struct Progress {
+  /// Kosten der besten bisher gefundenen Lösung
+  pub best: f64,
+  pub current_cost: f64,
+  pub current_classes: usize,
+  /// Anzahl der bisher besuchten Knoten
+  pub visited: usize,
+}
+
+/// Ereignisse, wie sie an den Worker-Hauptthread gesendet werden (`{"type": "progress", ...}`)
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SolverEvent {
+  Progress {
+    progress: Progress,
+  },
+  /// Eine neue beste Lösung mit einem Plan je Praktikant, siehe
+  /// [`MultiSolveResult::plans`](crate::MultiSolveResult)
+  Solution {
+    plans: Vec<TraineePlan>,
+    /// Summe der Kosten aller Pläne
+    cost: f64,
+  },
+  Finished {
+    /// Kosten der besten Lösung, falls eine gefunden wurde
+    cost: Option<f64>,
+    visited: usize,
+    /// Die Lösung ist bewiesen optimal, siehe [`SolveResult::optimal`](crate::SolveResult)
+    optimal: bool,
+  },
+}
+
+/// Empfänger für Rückmeldungen des Planers. Alle Methoden sind optional.
+pub trait SolverObserver {
+  /// Wird regelmäßig während der Suche aufgerufen
+  fn progress(&mut self, _progress: &Progress) {}
+
+  /// Die Suche hat eine Lösung gefunden, die besser ist als alle bisherigen
+  fn solution(&mut self, _plans: &[TraineePlan]) {}
+
+  /// Die Suche ist abgeschlossen
+  fn finished(&mut self, _cost: Option<f64>, _visited: usize, _optimal: bool) {}
+}
+
+/// Ignoriert alle Rückmeldungen
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopObserver;
+
+impl SolverObserver for NoopObserver {}
+
+/// Leitet alle Rückmeldungen als [`SolverEvent`] an eine Funktion weiter
+pub struct CallbackObserver<F: FnMut(SolverEvent)>(pub F);
+
+impl<F: FnMut(SolverEvent)> SolverObserver for CallbackObserver<F> {
+  fn progress(&mut self, progress: &Progress) {
+    (self.0)(SolverEvent::Progress {
+      progress: progress.clone(),
+    });
+  }
+
+  fn solution(&mut self, plans: &[TraineePlan]) {
+    (self.0)(SolverEvent::Solution {
+      plans: plans.to_vec(),
+      cost: plans.iter().map(|p| p.breakdown.total).sum(),
+    });
+  }
+
+  fn finished(&mut self, cost: Option<f64>, visited: usize, optimal: bool) {
+    (self.0)(SolverEvent::Finished {
+      cost,
+      visited,
+      optimal,
+    });
+  }
+}
+
+/// Sendet alle Rückmeldungen als [`SolverEvent`] über einen Channel. Ist der Empfänger bereits
+/// geschlossen, werden die Ereignisse verworfen.
+pub struct ChannelObserver(pub Sender<SolverEvent>);
+
+impl SolverObserver for ChannelObserver {
+  fn progress(&mut self, progress: &Progress) {
+    let _ = self.0.send(SolverEvent::Progress {
+      progress: progress.clone(),
+    });
+  }
+
+  fn solution(&mut self, plans: &[TraineePlan]) {
+    let _ = self.0.send(SolverEvent::Solution {
+      plans: plans.to_vec(),
+      cost: plans.iter().map(|p| p.breakdown.total).sum(),
+    });
+  }
+
+  fn finished(&mut self, cost: Option<f64>, visited: usize, optimal: bool) {
+    let _ = self.0.send(SolverEvent::Finished {
+      cost,
+      visited,
+      optimal,
+    });
+  }
+}
+
+/// Schickt Fortschritt und Zwischenlösungen per `postMessage` aus dem Web Worker. Nur im Browser
+/// verwendbar.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WorkerObserver;
+
+impl WorkerObserver {
+  fn post(&self, event: &SolverEvent) {
+    let global: web_sys::DedicatedWorkerGlobalScope = js_sys::global().unchecked_into();
+    let Ok(message) = serde_wasm_bindgen::to_value(event) else {
+      return;
+    };
+    let _ = global.post_message(&message);
+  }
+}
+
+impl SolverObserver for WorkerObserver {
+  fn progress(&mut self, progress: &Progress) {
+    self.post(&SolverEvent::Progress {
+      progress: progress.clone(),
+    });
+  }
+
+  fn solution(&mut self, plans: &[TraineePlan]) {
+    self.post(&SolverEvent::Solution {
+      plans: plans.to_vec(),
+      cost: plans.iter().map(|p| p.breakdown.total).sum(),
+    });
+  }
+
+  fn finished(&mut self, cost: Option<f64>, visited: usize, optimal: bool) {
+    self.post(&SolverEvent::Finished {
+      cost,
+      visited,
+      optimal,
+    });
+  }
+}
@@ -0,0 +1,259 @@
+//! Wahl des Verfahrens und Grenzen für die Laufzeit der Suche.
+//!
+//! Die erschöpfende Suche kann bei großen Schulen sehr lange laufen. Mit [`SolveOptions`] lässt sie
+//! sich nach einer Zeit- oder Knotengrenze oder über ein [`CancellationToken`] vorzeitig beenden;
+//! [`generate`](crate::generate) liefert dann die beste bis dahin gefundene Lösung. Im Browser
+//! läuft die Suche in einem Worker, der keine Nachrichten empfangen kann, solange er rechnet; der
+//! Abbruch wird dort über ein gemeinsames Flag in einem `SharedArrayBuffer` signalisiert.
+
+use std::{
+  fmt,
+  sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+  },
+  time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+
+/// Kann aus einem anderen Thread heraus gesetzt werden, um eine laufende Suche abzubrechen.
+/// Klone teilen sich denselben Zustand.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+  flag: Arc<AtomicBool>,
+  /// `Int32Array` über einem `SharedArrayBuffer`, abgebrochen wird, sobald das erste Element
+  /// nicht mehr 0 ist
+  #[cfg(target_arch = "wasm32")]
+  shared: Option<js_sys::Int32Array>,
+}
+
+impl CancellationToken {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Liest den Abbruch zusätzlich aus dem ersten Element von `flag`, das der Hauptthread des
+  /// Browsers mit `Atomics.store` setzt
+  #[cfg(target_arch = "wasm32")]
+  pub fn from_shared(flag: js_sys::Int32Array) -> Self {
+    CancellationToken {
+      flag: Arc::default(),
+      shared: Some(flag),
+    }
+  }
+
+  pub fn cancel(&self) {
+    self.flag.store(true, Ordering::Relaxed);
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    #[cfg(target_arch = "wasm32")]
+    if let Some(shared) = &self.shared
+      && js_sys::Atomics::load(shared, 0).is_ok_and(|value| value != 0)
+    {
+      return true;
+    }
+
+    self.flag.load(Ordering::Relaxed)
+  }
+}
+
+/// Verfahren, mit dem [`generate`](crate::generate) die Lösung bestimmt
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+  /// Tiefensuche mit Branch and Bound. Bei mehreren Praktikanten mit Grenzen je Klasse oder
+  /// Lehrkraft oft deutlich langsamer als [`Backend::Milp`].
+  #[default]
+  Search,
+  /// Gemischt-ganzzahliges lineares Programm (MILP), liefert ohne vorzeitigen Abbruch stets eine
+  /// bewiesen optimale Lösung. Die Grenzen werden nur vor dem Durchlauf geprüft.
+  Milp,
+  /// Simulated Annealing, findet auch bei großen Schulen schnell eine gute, aber nicht bewiesen
+  /// optimale Lösung
+  LocalSearch,
+}
+
+/// Einstellungen für [`generate`](crate::generate). Standardmäßig läuft die Suche ohne Grenzen bis
+/// zum Ende.
+#[derive(Debug, Clone, Default)]
+pub struct SolveOptions {
+  pub backend: Backend,
+  /// Maximale Laufzeit der Suche
+  pub time_limit: Option<Duration>,
+  /// Maximale Anzahl zu besuchender Knoten, Schritte der lokalen Suche bzw. gelöster
+  /// MILP-Durchläufe
+  pub node_limit: Option<usize>,
+  pub cancel: Option<CancellationToken>,
+  /// Startwert für den Zufallsgenerator der lokalen Suche
+  pub seed: u64,
+  /// Anzahl weiterer Lösungen neben der besten, siehe [`Alternative`](crate::Alternative). Zeit-
+  /// und Knotengrenze gelten für jede Lösung einzeln.
+  pub alternatives: usize,
+  /// Jede weitere Lösung unterscheidet sich von allen vorherigen in mindestens so vielen Stunden,
+  /// über alle Praktikanten gezählt. 0 und 1 liefern die nächstbesten Lösungen.
+  pub min_difference: usize,
+}
+
+/// [`SolveOptions`] in der Form, in der sie aus JavaScript an
+/// [`wasm_generate`](crate::wasm_generate) übergeben werden. Alle Felder sind optional.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct JsSolveOptions {
+  backend: Backend,
+  time_limit_ms: Option<f64>,
+  node_limit: Option<usize>,
+  /// `Int32Array` über einem `SharedArrayBuffer`, siehe [`CancellationToken::from_shared`]
+  #[serde(deserialize_with = "serde_wasm_bindgen::preserve::deserialize")]
+  cancel: JsValue,
+  seed: u64,
+  alternatives: usize,
+  min_difference: usize,
+}
+
+impl From<JsSolveOptions> for SolveOptions {
+  fn from(options: JsSolveOptions) -> Self {
+    SolveOptions {
+      backend: options.backend,
+      time_limit: options
+        .time_limit_ms
+        .map(|ms| Duration::from_secs_f64(ms.max(0.0) / 1000.0)),
+      node_limit: options.node_limit,
+      cancel: shared_cancel(options.cancel),
+      seed: options.seed,
+      alternatives: options.alternatives,
+      min_difference: options.min_difference,
+    }
+  }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn shared_cancel(flag: JsValue) -> Option<CancellationToken> {
+  use wasm_bindgen::JsCast;
+
+  flag
+    .dyn_into::<js_sys::Int32Array>()
+    .ok()
+    .map(CancellationToken::from_shared)
+}
+
+/// Ohne JavaScript gibt es keinen `SharedArrayBuffer`
+#[cfg(not(target_arch = "wasm32"))]
+fn shared_cancel(_flag: JsValue) -> Option<CancellationToken> {
+  None
+}
+
+/// Grund, aus dem die Suche vorzeitig beendet wurde
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+  TimeLimit,
+  NodeLimit,
+  Cancelled,
+}
+
+impl fmt::Display for StopReason {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      StopReason::TimeLimit => "Zeitlimit erreicht",
+      StopReason::NodeLimit => "Knotenlimit erreicht",
+      StopReason::Cancelled => "abgebrochen",
+    })
+  }
+}
+
+/// Wanduhr, die auch in `wasm32-unknown-unknown` funktioniert, wo [`std::time::Instant`] nicht
+/// verfügbar ist
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Stopwatch {
+  #[cfg(target_arch = "wasm32")]
+  start_ms: f64,
+  #[cfg(not(target_arch = "wasm32"))]
+  start: std::time::Instant,
+}
+
+impl Stopwatch {
+  pub(crate) fn start() -> Self {
+    Stopwatch {
+      #[cfg(target_arch = "wasm32")]
+      start_ms: js_sys::Date::now(),
+      #[cfg(not(target_arch = "wasm32"))]
+      start: std::time::Instant::now(),
+    }
+  }
+
+  pub(crate) fn elapsed(&self) -> Duration {
+    #[cfg(target_arch = "wasm32")]
+    return Duration::from_secs_f64((js_sys::Date::now() - self.start_ms).max(0.0) / 1000.0);
+    #[cfg(not(target_arch = "wasm32"))]
+    return self.start.elapsed();
+  }
+}
+
+/// Überwacht die Grenzen aus [`SolveOptions`] während der Suche
+#[derive(Debug)]
+pub(crate) struct Limits<'a> {
+  options: &'a SolveOptions,
+  stopwatch: Stopwatch,
+  /// Anzahl der Aufrufe von [`Limits::check`]
+  checks: usize,
+  /// Gesetzt, sobald eine Grenze erreicht wurde. Die Suche kehrt dann ohne weitere Knoten zurück.
+  pub(crate) stopped: Option<StopReason>,
+}
+
+impl<'a> Limits<'a> {
+  // Die Uhr wird nur in diesem Abstand abgefragt, `Date::now` ist im Browser vergleichsweise teuer
+  const CLOCK_INTERVAL: usize = 1024;
+
+  pub(crate) fn new(options: &'a SolveOptions) -> Self {
+    Limits {
+      options,
+      stopwatch: Stopwatch::start(),
+      checks: 0,
+      stopped: None,
+    }
+  }
+
+  /// Prüft alle Grenzen und merkt sich ggf. den Grund für den Abbruch
+  pub(crate) fn check(&mut self, nodes_visited: usize) -> bool {
+    if self.stopped.is_some() {
+      return true;
+    }
+    self.checks += 1;
+    let clock = self.checks.is_multiple_of(Self::CLOCK_INTERVAL);
+    self.check_limits(nodes_visited, clock)
+  }
+
+  /// Wie [`Limits::check`], fragt aber auf jeden Fall die Uhr ab. Für Verfahren mit wenigen, dafür
+  /// teuren Schritten.
+  pub(crate) fn check_now(&mut self, nodes_visited: usize) -> bool {
+    if self.stopped.is_some() {
+      return true;
+    }
+    self.check_limits(nodes_visited, true)
+  }
+
+  fn check_limits(&mut self, nodes_visited: usize, clock: bool) -> bool {
+    if self
+      .options
+      .cancel
+      .as_ref()
+      .is_some_and(|c| c.is_cancelled())
+    {
+      self.stopped = Some(StopReason::Cancelled);
+    } else if self.options.node_limit.is_some_and(|n| nodes_visited >= n) {
+      self.stopped = Some(StopReason::NodeLimit);
+    } else if clock
+      && self
+        .options
+        .time_limit
+        .is_some_and(|t| self.stopwatch.elapsed() >= t)
+    {
+      self.stopped = Some(StopReason::TimeLimit);
+    }
+
+    self.stopped.is_some()
+  }
+}
@@ -0,0 +1,500 @@
+//! Festgelegte und verbotene Stunden.
+//!
+//! Koordinatoren passen Pläne schrittweise an und wollen, dass ihre Entscheidungen einen neuen
+//! Durchlauf überstehen. Verbotene Stunden werden vor der Suche aus dem Plan des Praktikanten
+//! entfernt. Eine Festlegung mit Eintrag oder Stunde schränkt jede Stunde im Raster, in der
+//! passender Unterricht stattfindet, auf diesen Unterricht ein; die Stunde darf dann auch nicht
+//! leer bleiben. Eine Festlegung nur über Klasse, Lehrkraft oder Tag verlangt dagegen mindestens
+//! eine passende Stunde, siehe [`RequiredLesson`]. Widersprüche zwischen den Angaben werden schon
+//! hier als [`PlannerError::ConflictingPins`] gemeldet. Mit [`Pins::double_lessons`] werden
+//! außerdem Doppelstunden nur ganz besucht.
+
+use std::fmt;
+
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+use willi::{LehrerStundenZeile, WilliStundenplan};
+
+use crate::{PlannerError, Timeslot};
+
+/// Beschreibt Unterricht im Lehrerplan. Eine Zeile passt, wenn sie alle angegebenen Merkmale
+/// erfüllt, z.B. `{ class: "9c", day: 4 }` für jeden Unterricht der 9c am Freitag.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LessonFilter {
+  /// Index der Zeile im Lehrerplan
+  pub pl_index: Option<usize>,
+  /// Kürzel der Klasse
+  pub class: Option<String>,
+  /// Kürzel der Lehrkraft
+  pub teacher: Option<String>,
+  /// Tag wie in der Ausgabe von [`generate`](crate::generate), d.h. die Zeile
+  pub day: Option<usize>,
+  /// Stunde im Tagesraster, d.h. die Spalte in der Ausgabe
+  pub period: Option<usize>,
+}
+
+impl LessonFilter {
+  /// Als Festlegung gilt der Filter für jede passende Stunde, nicht nur für eine davon
+  fn all_slots(&self) -> bool {
+    self.pl_index.is_some() || self.period.is_some()
+  }
+
+  fn matches(
+    &self,
+    plan: &WilliStundenplan,
+    pl_index: usize,
+    line: &LehrerStundenZeile,
+    timeslot: &Timeslot,
+  ) -> bool {
+    self.pl_index.is_none_or(|i| i == pl_index)
+      && self
+        .class
+        .as_ref()
+        .is_none_or(|k| plan.klasse(line.klasse).is_some_and(|z| z.kuerzel == *k))
+      && self.teacher.as_ref().is_none_or(|k| {
+        plan
+          .lehrkraft(line.lehrkraft)
+          .is_some_and(|l| l.kuerzel == *k)
+      })
+      && self.day.is_none_or(|d| d == timeslot.day)
+      && self.period.is_none_or(|p| p == timeslot.period)
+  }
+
+  fn validate(&self, plan: &WilliStundenplan) -> Result<(), PlannerError> {
+    let invalid = |message: String| Err(PlannerError::InvalidOptions(message));
+
+    if *self == LessonFilter::default() {
+      return invalid("Festlegung ohne Angaben".to_string());
+    }
+    if let Some(pl_index) = self.pl_index
+      && pl_index >= plan.lehrerstunden().len()
+    {
+      return invalid(format!("Eintrag {pl_index} fehlt im Lehrerplan"));
+    }
+    if let Some(kuerzel) = &self.class
+      && plan.klasse_id(kuerzel).is_none()
+    {
+      return invalid(format!("Klasse „{kuerzel}“ ist im Plan nicht vorhanden"));
+    }
+    if let Some(kuerzel) = &self.teacher
+      && plan.lehrkraft_id(kuerzel).is_none()
+    {
+      return invalid(format!("Lehrkraft „{kuerzel}“ ist im Plan nicht vorhanden"));
+    }
+
+    Ok(())
+  }
+}
+
+impl fmt::Display for LessonFilter {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mut parts = vec![];
+    if let Some(pl_index) = self.pl_index {
+      parts.push(format!("Eintrag {pl_index}"));
+    }
+    if let Some(kuerzel) = &self.class {
+      parts.push(format!("Klasse {kuerzel}"));
+    }
+    if let Some(kuerzel) = &self.teacher {
+      parts.push(format!("Lehrkraft {kuerzel}"));
+    }
+    if let Some(day) = self.day {
+      parts.push(format!("Tag {day}"));
+    }
+    if let Some(period) = self.period {
+      parts.push(format!("Stunde {period}"));
+    }
+    f.write_str(&parts.join(", "))
+  }
+}
+
+/// Festgelegte und verbotene Stunden eines Praktikanten
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Pins {
+  /// Mit `pl_index` oder `period` besucht der Praktikant in jeder Stunde, in der passender
+  /// Unterricht stattfindet, genau diesen Unterricht. Ein einzelner Eintrag im Lehrerplan wird
+  /// also immer besucht. Ohne beides besucht er mindestens eine passende Stunde, z.B. mit
+  /// `{ class: "9c", day: 4 }` irgendeine Stunde der 9c am Freitag.
+  pub pinned: Vec<LessonFilter>,
+  /// Passender Unterricht wird nie besucht
+  pub forbidden: Vec<LessonFilter>,
+  /// Doppelstunden werden ganz oder gar nicht besucht. Als Doppelstunde gelten aufeinanderfolgende
+  /// Einträge derselben Lehrkraft in derselben Klasse, deren Fach die Eigenschaft `D` hat oder
+  /// deren U-Zeile `doppmin` bzw. `doppmax` angibt.
+  pub double_lessons: bool,
+}
+
+impl Pins {
+  pub(crate) fn validate(&self, plan: &WilliStundenplan) -> Result<(), PlannerError> {
+    self
+      .pinned
+      .iter()
+      .chain(&self.forbidden)
+      .try_for_each(|filter| filter.validate(plan))
+  }
+
+  /// Entfernt verbotenen Unterricht aus `schedule` (`[class][slot] = [(subject, pl_index)]`)
+  /// und schränkt Stunden mit festgelegtem Unterricht ein, siehe [`LessonFilter::all_slots`].
+  /// Gibt je Stunde zurück, ob sie belegt werden muss.
+  pub(crate) fn apply(
+    &self,
+    schedule: &mut Array2<Vec<(usize, usize)>>,
+    plan: &WilliStundenplan,
+    timeslots: &[Timeslot],
+  ) -> Result<Vec<bool>, PlannerError> {
+    let lines = plan.lehrerstunden();
+    let matches = |filter: &LessonFilter, &(_, pl_index): &(usize, usize), slot: usize| {
+      filter.matches(plan, pl_index, &lines[pl_index], &timeslots[slot])
+    };
+    let conflict = |message: String| Err(PlannerError::ConflictingPins(message));
+
+    let original = schedule.clone();
+    for filter in &self.forbidden {
+      for ((_, slot), entry) in schedule.indexed_iter_mut() {
+        entry.retain(|e| !matches(filter, e, slot));
+      }
+    }
+    let allowed = schedule.clone();
+
+    // [slot] = Festlegung, die diese Stunde einschränkt
+    let mut pinned_by: Vec<Option<&LessonFilter>> = vec![None; timeslots.len()];
+    for pin in self.pinned.iter().filter(|pin| pin.all_slots()) {
+      let mut any = false;
+
+      for (slot, timeslot) in timeslots.iter().enumerate() {
+        if !original
+          .column(slot)
+          .iter()
+          .flatten()
+          .any(|e| matches(pin, e, slot))
+        {
+          continue;
+        }
+        any = true;
+
+        let mut column = schedule.column_mut(slot);
+        for entry in column.iter_mut() {
+          entry.retain(|e| matches(pin, e, slot));
+        }
+
+        if column.iter().all(Vec::is_empty) {
+          let at = format!("Tag {}, Stunde {}", timeslot.day, timeslot.period);
+          // Ohne die andere Festlegung wäre die Stunde möglich, sonst liegt es an einem Verbot
+          return match pinned_by[slot] {
+            Some(other)
+              if allowed
+                .column(slot)
+                .iter()
+                .flatten()
+                .any(|e| matches(pin, e, slot)) =>
+            {
+              conflict(format!(
+                "„{other}“ und „{pin}“ verlangen in {at} verschiedenen Unterricht"
+              ))
+            }
+            _ => {
+              let forbidden = self
+                .forbidden
+                .iter()
+                .find(|f| {
+                  original
+                    .column(slot)
+                    .iter()
+                    .flatten()
+                    .any(|e| matches(pin, e, slot) && matches(f, e, slot))
+                })
+                .map_or(String::new(), |f| format!(" („{f}“)"));
+              conflict(format!("„{pin}“ ist in {at} verboten{forbidden}"))
+            }
+          };
+        }
+        pinned_by[slot] = Some(pin);
+      }
+
+      if !any {
+        return conflict(format!(
+          "„{pin}“ passt zu keinem Unterricht, den der Praktikant besuchen kann"
+        ));
+      }
+    }
+
+    Ok(pinned_by.iter().map(Option::is_some).collect())
+  }
+
+  /// Festlegungen, die nur mindestens eine passende Stunde verlangen, für den fertig gefilterten
+  /// `schedule`
+  pub(crate) fn required(
+    &self,
+    schedule: &Array2<Vec<(usize, usize)>>,
+    plan: &WilliStundenplan,
+    timeslots: &[Timeslot],
+  ) -> Result<Vec<RequiredLesson>, PlannerError> {
+    let lines = plan.lehrerstunden();
+
+    self
+      .pinned
+      .iter()
+      .filter(|pin| !pin.all_slots())
+      .map(|pin| {
+        let mut required = RequiredLesson {
+          lessons: vec![false; lines.len()],
+          possible: vec![0; timeslots.len() + 1],
+        };
+        for slot in (0..timeslots.len()).rev() {
+          let mut possible = false;
+          for &(_, pl_index) in schedule.column(slot).iter().flatten() {
+            if pin.matches(plan, pl_index, &lines[pl_index], &timeslots[slot]) {
+              required.lessons[pl_index] = true;
+              possible = true;
+            }
+          }
+          required.possible[slot] = required.possible[slot + 1] + usize::from(possible);
+        }
+
+        if required.possible[0] == 0 {
+          return Err(PlannerError::ConflictingPins(format!(
+            "„{pin}“ passt zu keinem Unterricht, den der Praktikant besuchen kann"
+          )));
+        }
+        Ok(required)
+      })
+      .collect()
+  }
+}
+
+/// Festlegung ohne Eintrag und Stunde: Der Praktikant besucht mindestens einen passenden
+/// Unterricht
+#[derive(Debug)]
+pub(crate) struct RequiredLesson {
+  // [pl_index] = Unterricht passt zur Festlegung
+  lessons: Vec<bool>,
+  // [slot] = Anzahl der Stunden ab `slot`, in denen passender Unterricht möglich ist
+  possible: Vec<usize>,
+}
+
+impl RequiredLesson {
+  /// Der Eintrag `pl_index` erfüllt die Festlegung
+  pub(crate) fn matches(&self, pl_index: usize) -> bool {
+    self.lessons[pl_index]
+  }
+
+  /// Anzahl der Stunden ab `slot`, in denen passender Unterricht möglich ist
+  pub(crate) fn possible(&self, slot: usize) -> usize {
+    self.possible[slot]
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use willi::{SyntheticParams, WilliStundenplan};
+
+  use crate::{
+    Backend, Capacity, PlannerError, Praktikant,
+    testing::{plan, solve, trainee},
+  };
+
+  use super::{LessonFilter, Pins};
+
+  /// Unterricht, den der Praktikant ohne Festlegungen besuchen kann, als `(day, period, pl_index)`
+  fn lessons(plan: &WilliStundenplan) -> Vec<(usize, usize, usize)> {
+    let days: Vec<_> = plan.tage().iter().map(|(_, tag)| tag.id).collect();
+    let periods: Vec<_> = plan.stundenraster().collect();
+    let mut lessons: Vec<(usize, usize, usize)> = plan
+      .lehrerstunden()
+      .iter()
+      .enumerate()
+      .filter(|(_, line)| {
+        plan
+          .fach(line.fach)
+          .is_some_and(|fach| ["M", "D"].contains(&&fach.kuerzel[..]))
+      })
+      .filter_map(|(pl_index, line)| {
+        let day = days.iter().position(|&d| d == line.tag_stunde.tag)?;
+        let period = periods.iter().position(|&p| p == line.tag_stunde.stunde)?;
+        Some((day, period, pl_index))
+      })
+      .collect();
+    lessons.sort();
+    lessons
+  }
+
+  fn pl(pl_index: usize) -> LessonFilter {
+    LessonFilter {
+      pl_index: Some(pl_index),
+      ..Default::default()
+    }
+  }
+
+  fn class_of(plan: &WilliStundenplan, pl_index: usize) -> String {
+    let line = &plan.lehrerstunden()[pl_index];
+    plan.klasse(line.klasse).unwrap().kuerzel.clone()
+  }
+
+  fn solve_pinned(plan: &WilliStundenplan, trainee: Praktikant) -> Result<(), PlannerError> {
+    solve(plan, &[trainee], &Capacity::default(), Backend::Search).map(|_| ())
+  }
+
+  /// Zwei Festlegungen auf verschiedenen Unterricht in derselben Stunde
+  #[test]
+  fn two_lessons_in_one_slot() {
+    let (plan, a, b) = (1..10)
+      .find_map(|seed| {
+        let plan = plan(seed);
+        let lessons = lessons(&plan);
+        let pair = lessons
+          .windows(2)
+          .find(|w| w[0].0 == w[1].0 && w[0].1 == w[1].1)
+          .map(|w| (w[0].2, w[1].2));
+        pair.map(|(a, b)| (plan, a, b))
+      })
+      .expect("Kein Plan mit zwei möglichen Stunden gleichzeitig");
+
+    let result = solve_pinned(
+      &plan,
+      Praktikant {
+        pins: Pins {
+          pinned: vec![pl(a), pl(b)],
+          ..Default::default()
+        },
+        ..trainee("A")
+      },
+    );
+    assert!(matches!(result, Err(PlannerError::ConflictingPins(_))));
+  }
+
+  /// Festgelegter Unterricht in einer verbotenen Klasse
+  #[test]
+  fn pinned_and_forbidden() {
+    let plan = plan(1);
+    let (_, _, pl_index) = lessons(&plan)[0];
+
+    let result = solve_pinned(
+      &plan,
+      Praktikant {
+        pins: Pins {
+          pinned: vec![pl(pl_index)],
+          forbidden: vec![LessonFilter {
+            class: Some(class_of(&plan, pl_index)),
+            ..Default::default()
+          }],
+          ..Default::default()
+        },
+        ..trainee("A")
+      },
+    );
+    assert!(matches!(result, Err(PlannerError::ConflictingPins(_))));
+  }
+
+  /// Festlegung auf eine Lehrkraft, deren Unterricht ausgeschlossen ist
+  #[test]
+  fn pinned_teacher_excluded() {
+    let plan = plan(1);
+    let (_, _, pl_index) = lessons(&plan)[0];
+    let line = &plan.lehrerstunden()[pl_index];
+    let teacher = plan.lehrkraft(line.lehrkraft).unwrap().kuerzel.clone();
+
+    let result = solve_pinned(
+      &plan,
+      Praktikant {
+        excluded_teachers: vec![teacher.clone()],
+        pins: Pins {
+          pinned: vec![LessonFilter {
+            teacher: Some(teacher),
+            ..Default::default()
+          }],
+          ..Default::default()
+        },
+        ..trainee("A")
+      },
+    );
+    assert!(matches!(result, Err(PlannerError::ConflictingPins(_))));
+  }
+
+  /// Alle Verfahren besuchen festgelegten Unterricht, mindestens eine Stunde der festgelegten
+  /// Klasse am festgelegten Tag und keinen verbotenen Unterricht, auch wenn die Lösung ohne
+  /// Festlegungen anders aussähe
+  #[test]
+  fn respected_by_all_backends() {
+    // Mit mehr Klassen gibt es in den meisten Stunden eine Auswahl
+    let plan = WilliStundenplan::synthetic(&SyntheticParams {
+      tage: 2,
+      stunden: 5,
+      klassen: 8,
+      lehrkraefte: 20,
+      seed: 2,
+      ..Default::default()
+    });
+    let lessons = lessons(&plan);
+    let unpinned = solve(
+      &plan,
+      &[trainee("A")],
+      &Capacity::default(),
+      Backend::Search,
+    )
+    .unwrap();
+    let free_solution = &unpinned.plans[0].solution;
+    let free: Vec<usize> = free_solution.iter().flatten().flatten().copied().collect();
+
+    // Jeweils Unterricht, den die Lösung ohne Festlegungen nicht enthält
+    let &(_, _, pinned) = lessons
+      .iter()
+      .find(|&&(day, _, pl_index)| day == 0 && !free.contains(&pl_index))
+      .unwrap();
+    let class = lessons
+      .iter()
+      .filter(|&&(day, period, _)| day == 1 && period > 0)
+      .map(|&(_, _, pl_index)| class_of(&plan, pl_index))
+      .find(|class| {
+        !lessons.iter().any(|&(day, _, pl_index)| {
+          day == 1 && free.contains(&pl_index) && class_of(&plan, pl_index) == *class
+        })
+      })
+      .unwrap();
+    // Die verbotene Stunde wäre sonst belegt
+    assert!(free_solution[1].first().is_some_and(Option::is_some));
+
+    let trainees = [Praktikant {
+      pins: Pins {
+        pinned: vec![
+          pl(pinned),
+          LessonFilter {
+            class: Some(class.clone()),
+            day: Some(1),
+            ..Default::default()
+          },
+        ],
+        forbidden: vec![LessonFilter {
+          day: Some(1),
+          period: Some(0),
+          ..Default::default()
+        }],
+        ..Default::default()
+      },
+      ..trainee("A")
+    }];
+
+    for backend in [Backend::Search, Backend::Milp, Backend::LocalSearch] {
+      let result = solve(&plan, &trainees, &Capacity::default(), backend).unwrap();
+      let solution = &result.plans[0].solution;
+      let visited: Vec<usize> = solution.iter().flatten().flatten().copied().collect();
+
+      assert!(visited.contains(&pinned), "{backend:?}");
+      assert!(
+        solution[1]
+          .iter()
+          .flatten()
+          .any(|&pl_index| class_of(&plan, pl_index) == class),
+        "{backend:?}"
+      );
+      assert!(
+        solution
+          .get(1)
+          .and_then(|day| day.first())
+          .is_none_or(Option::is_none),
+        "{backend:?}"
+      );
+    }
+  }
+}
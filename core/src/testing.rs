@@ -0,0 +1,65 @@
+//! Gemeinsame Hilfen für die Tests: kleine synthetische Pläne, auf denen auch die vollständige
+//! Suche im Debug-Build schnell fertig wird.
+
+use willi::{SyntheticParams, WilliStundenplan};
+
+use crate::{
+  Backend, Capacity, FachGewichtung, MultiSolveResult, NoopObserver, Objective, PlannerError,
+  Praktikant, SolveOptions, generate_many,
+};
+
+/// Plan mit 2 Tagen à 5 Stunden und 4 Klassen
+pub(crate) fn plan(seed: u64) -> WilliStundenplan {
+  WilliStundenplan::synthetic(&SyntheticParams {
+    tage: 2,
+    stunden: 5,
+    klassen: 4,
+    lehrkraefte: 12,
+    seed,
+    ..Default::default()
+  })
+}
+
+/// Praktikant mit den Fächern Mathematik und Deutsch
+pub(crate) fn trainee(name: &str) -> Praktikant {
+  Praktikant {
+    name: name.to_string(),
+    subjects: ["M", "D"]
+      .into_iter()
+      .map(|kuerzel| FachGewichtung {
+        kuerzel: kuerzel.to_string(),
+        gewicht: 1.0,
+      })
+      .collect(),
+    ..Default::default()
+  }
+}
+
+/// Löst ohne Grenzen mit `backend`
+pub(crate) fn solve(
+  plan: &WilliStundenplan,
+  trainees: &[Praktikant],
+  capacity: &Capacity,
+  backend: Backend,
+) -> Result<MultiSolveResult, PlannerError> {
+  generate_many(
+    plan,
+    trainees,
+    capacity,
+    &Objective::default(),
+    &SolveOptions {
+      backend,
+      ..Default::default()
+    },
+    &mut NoopObserver,
+  )
+}
+
+/// Anzahl der Stunden über alle Praktikanten, die leer bleiben, obwohl Unterricht möglich wäre
+pub(crate) fn unfilled(result: &MultiSolveResult) -> usize {
+  result
+    .plans
+    .iter()
+    .map(|plan| plan.breakdown.blocked_slots.len())
+    .sum()
+}
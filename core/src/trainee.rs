@@ -0,0 +1,342 @@
+//! Mehrere Praktikanten, die gemeinsam geplant werden.
+//!
+//! Jeder Praktikant hat eigene Fächer, Gewichte und ausgeschlossene Lehrkräfte. Damit nicht alle
+//! in derselben Stunde sitzen, begrenzt [`Capacity`], wie viele Praktikanten sich eine Stunde,
+//! eine Klasse oder eine Lehrkraft teilen. Kann ein Praktikant deswegen eine Stunde nicht belegen,
+//! bleibt sie leer. Alle Verfahren minimieren zuerst die Anzahl solcher Stunden und erst danach die
+//! Kosten.
+
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  Availability, EmptyReason, FachGewichtung, GradeCoverage, Mentors, Pins, PlannerError, Problem,
+  Workload, bound::LowerBound, coverage::CoverageGroup, pins::RequiredLesson,
+  workload::ForcedEmpty,
+};
+
+/// Angaben zu einem Praktikanten für [`generate`](crate::generate) und
+/// [`generate_many`](crate::generate_many)
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Praktikant {
+  /// Name zur Anzeige, wird unverändert in das Ergebnis übernommen
+  pub name: String,
+  /// Fächerkürzel im Plan, gepaart mit Gewichtungen. Kein Fach darf zweimal vorkommen.
+  pub subjects: Vec<FachGewichtung>,
+  /// Kürzel von Lehrkräften, deren Unterricht für diesen Praktikanten nicht berücksichtigt wird
+  pub excluded_teachers: Vec<String>,
+  /// Festgelegter und verbotener Unterricht
+  pub pins: Pins,
+  /// Tage und Zeiten, zu denen der Praktikant an der Schule ist
+  pub availability: Availability,
+  /// Grenzen für Stunden je Woche und Tag sowie für Freistunden
+  pub workload: Workload,
+  /// Vorgaben zu den Lehrkräften, bei denen der Praktikant hospitiert
+  pub mentors: Mentors,
+  /// Anforderungen an Jahrgangsstufen und Schultypen
+  pub coverage: Vec<GradeCoverage>,
+}
+
+/// Obergrenzen dafür, wie viele Praktikanten sich eine Stunde, eine Klasse oder eine Lehrkraft
+/// teilen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Capacity {
+  /// Praktikanten je Unterrichtsstunde, d.h. je Eintrag im Lehrerplan
+  pub per_lesson: usize,
+  /// Praktikanten, die dieselbe Klasse besuchen. `None` = unbegrenzt
+  pub per_class: Option<usize>,
+  /// Praktikanten, die bei derselben Lehrkraft hospitieren. `None` = unbegrenzt
+  pub per_mentor: Option<usize>,
+}
+
+impl Default for Capacity {
+  fn default() -> Self {
+    Capacity {
+      per_lesson: 1,
+      per_class: None,
+      per_mentor: None,
+    }
+  }
+}
+
+impl Capacity {
+  /// Eine Grenze von 0 würde jede Belegung verbieten
+  pub(crate) fn validate(&self) -> Result<(), PlannerError> {
+    for (name, limit) in [
+      ("per_lesson", Some(self.per_lesson)),
+      ("per_class", self.per_class),
+      ("per_mentor", self.per_mentor),
+    ] {
+      if limit == Some(0) {
+        return Err(PlannerError::InvalidOptions(format!(
+          "Kapazität „{name}“ muss mindestens 1 sein"
+        )));
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Eine mögliche Stunde eines Praktikanten: (class, subject, pl_index)
+pub(crate) type Lesson = (usize, usize, usize);
+
+/// Der Teil des [`Problem`]s, der nur einen Praktikanten betrifft
+#[derive(Debug)]
+pub(crate) struct Trainee {
+  pub(crate) name: String,
+  // [subject] = angestrebte Anzahl Stunden, d.h. Gewicht mal Anzahl der verfügbaren Stunden
+  pub(crate) subject_targets: Vec<f64>,
+  // [subject] = Kürzel
+  pub(crate) subject_kuerzel: Vec<String>,
+  // [class][slot] = [(subject, pl_index)], meist höchstens ein Eintrag. Mehrere Einträge gibt es
+  // z.B. bei geteilten Klassen oder Doppelbesetzung, der Praktikant besucht dann einen davon.
+  pub(crate) schedule: Array2<Vec<(usize, usize)>>,
+  // [slot] = Stunde ist festgelegt und darf nicht leer bleiben
+  pub(crate) pinned: Vec<bool>,
+  // Festlegungen, von denen mindestens eine passende Stunde besucht wird
+  pub(crate) required: Vec<RequiredLesson>,
+  // Siehe `Pins::double_lessons`
+  pub(crate) double_lessons: bool,
+  pub(crate) workload: Workload,
+  pub(crate) forced_empty: ForcedEmpty,
+  pub(crate) mentors: Mentors,
+  // Lehrkraft aus `Mentors::preferred`
+  pub(crate) preferred_mentor: Option<usize>,
+  pub(crate) coverage: Vec<CoverageGroup>,
+  pub(crate) bound: LowerBound,
+  // [slot] = Grund, falls in der Stunde kein Unterricht möglich ist
+  pub(crate) empty_reasons: Vec<Option<EmptyReason>>,
+}
+
+impl Trainee {
+  /// In `slot` findet Unterricht in einem Fach des Praktikanten statt
+  pub(crate) fn has_candidates(&self, slot: usize) -> bool {
+    self
+      .schedule
+      .column(slot)
+      .iter()
+      .any(|lessons| !lessons.is_empty())
+  }
+
+  /// Alle Stunden, die der Praktikant in `slot` besuchen kann
+  pub(crate) fn lessons(&self, slot: usize) -> impl Iterator<Item = Lesson> + '_ {
+    self
+      .schedule
+      .column(slot)
+      .into_iter()
+      .enumerate()
+      .flat_map(|(class, lessons)| {
+        lessons
+          .iter()
+          .map(move |&(subject, pl_index)| (class, subject, pl_index))
+      })
+  }
+}
+
+/// Wie viele Praktikanten welche Stunden, Klassen und Lehrkräfte belegen. Wird von allen
+/// Verfahren verwendet, um die [`Capacity`] einzuhalten.
+#[derive(Debug, Clone)]
+pub(crate) struct Occupancy {
+  // [pl_index] = Anzahl der Praktikanten in dieser Stunde
+  lessons: Vec<usize>,
+  // [trainee][class] = Anzahl der Stunden des Praktikanten in dieser Klasse
+  class_load: Array2<usize>,
+  // [class] = Anzahl der Praktikanten in dieser Klasse
+  class_trainees: Vec<usize>,
+  // [trainee][teacher] = Anzahl der Stunden des Praktikanten bei dieser Lehrkraft
+  mentor_load: Array2<usize>,
+  // [teacher] = Anzahl der Praktikanten bei dieser Lehrkraft
+  mentor_trainees: Vec<usize>,
+}
+
+impl Occupancy {
+  pub(crate) fn new(problem: &Problem) -> Self {
+    let trainees = problem.trainees.len();
+    Occupancy {
+      lessons: vec![0; problem.lesson_teacher.len()],
+      class_load: Array2::zeros((trainees, problem.classes)),
+      class_trainees: vec![0; problem.classes],
+      mentor_load: Array2::zeros((trainees, problem.teacher_kuerzel.len())),
+      mentor_trainees: vec![0; problem.teacher_kuerzel.len()],
+    }
+  }
+
+  /// Anzahl der Stunden, die `trainee` in `class` belegt
+  pub(crate) fn class_load(&self, trainee: usize, class: usize) -> usize {
+    self.class_load[[trainee, class]]
+  }
+
+  /// Anzahl der Stunden, die `trainee` bei `teacher` belegt
+  pub(crate) fn mentor_load(&self, trainee: usize, teacher: usize) -> usize {
+    self.mentor_load[[trainee, teacher]]
+  }
+
+  /// `trainee` kann die Stunde `lesson` besuchen, ohne eine Grenze zu überschreiten. Dazu zählt
+  /// auch [`Mentors::max_lessons`].
+  pub(crate) fn admits(&self, problem: &Problem, trainee: usize, lesson: Lesson) -> bool {
+    let (class, _, pl_index) = lesson;
+    let teacher = problem.lesson_teacher[pl_index];
+    let capacity = &problem.capacity;
+
+    self.lessons[pl_index] < capacity.per_lesson
+      && problem.trainees[trainee]
+        .mentors
+        .max_lessons
+        .is_none_or(|max| self.mentor_load[[trainee, teacher]] < max)
+      && (self.class_load[[trainee, class]] > 0
+        || capacity
+          .per_class
+          .is_none_or(|limit| self.class_trainees[class] < limit))
+      && (self.mentor_load[[trainee, teacher]] > 0
+        || capacity
+          .per_mentor
+          .is_none_or(|limit| self.mentor_trainees[teacher] < limit))
+  }
+
+  /// Trägt `trainee` in die Stunde `lesson` ein. Gibt zurück, ob die Klasse für den Praktikanten
+  /// neu ist.
+  pub(crate) fn add(&mut self, problem: &Problem, trainee: usize, lesson: Lesson) -> bool {
+    let (class, _, pl_index) = lesson;
+    let teacher = problem.lesson_teacher[pl_index];
+
+    self.lessons[pl_index] += 1;
+    if self.mentor_load[[trainee, teacher]] == 0 {
+      self.mentor_trainees[teacher] += 1;
+    }
+    self.mentor_load[[trainee, teacher]] += 1;
+
+    let new_class = self.class_load[[trainee, class]] == 0;
+    if new_class {
+      self.class_trainees[class] += 1;
+    }
+    self.class_load[[trainee, class]] += 1;
+
+    new_class
+  }
+
+  /// Macht [`Occupancy::add`] rückgängig. Gibt zurück, ob der Praktikant die Klasse danach nicht
+  /// mehr besucht.
+  pub(crate) fn remove(&mut self, problem: &Problem, trainee: usize, lesson: Lesson) -> bool {
+    let (class, _, pl_index) = lesson;
+    let teacher = problem.lesson_teacher[pl_index];
+
+    self.lessons[pl_index] -= 1;
+    self.mentor_load[[trainee, teacher]] -= 1;
+    if self.mentor_load[[trainee, teacher]] == 0 {
+      self.mentor_trainees[teacher] -= 1;
+    }
+
+    self.class_load[[trainee, class]] -= 1;
+    let dropped = self.class_load[[trainee, class]] == 0;
+    if dropped {
+      self.class_trainees[class] -= 1;
+    }
+
+    dropped
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::{HashMap, HashSet};
+
+  use willi::WilliStundenplan;
+
+  use crate::{
+    Backend, Capacity, MultiSolveResult,
+    testing::{plan, solve, trainee, unfilled},
+  };
+
+  const BACKENDS: [Backend; 3] = [Backend::Search, Backend::Milp, Backend::LocalSearch];
+
+  /// Löst für drei Praktikanten mit denselben Fächern
+  fn solve_three(
+    plan: &WilliStundenplan,
+    capacity: &Capacity,
+    backend: Backend,
+  ) -> MultiSolveResult {
+    let trainees = [trainee("A"), trainee("B"), trainee("C")];
+    solve(plan, &trainees, capacity, backend).unwrap()
+  }
+
+  /// Größte Anzahl von Praktikanten, die sich einen Wert von `key` teilen
+  fn max_shared<K: std::hash::Hash + Eq>(
+    plan: &WilliStundenplan,
+    result: &MultiSolveResult,
+    key: impl Fn(usize) -> K,
+  ) -> usize {
+    let lines = plan.stunden_lehrerplan();
+    let mut shared: HashMap<K, HashSet<usize>> = HashMap::new();
+    for (trainee, trainee_plan) in result.plans.iter().enumerate() {
+      for &pl_index in trainee_plan.solution.iter().flatten().flatten() {
+        assert!(pl_index < lines.len());
+        shared.entry(key(pl_index)).or_default().insert(trainee);
+      }
+    }
+    shared.values().map(HashSet::len).max().unwrap_or(0)
+  }
+
+  /// Keine Unterrichtsstunde wird von mehr Praktikanten besucht als `per_lesson` erlaubt. Passen
+  /// alle hinein, bleibt keine Stunde leer.
+  #[test]
+  fn per_lesson() {
+    for per_lesson in [1, 2, 3] {
+      let plan = plan(1);
+      let capacity = Capacity {
+        per_lesson,
+        ..Default::default()
+      };
+      for backend in BACKENDS {
+        let result = solve_three(&plan, &capacity, backend);
+        let shared = max_shared(&plan, &result, |pl_index| pl_index);
+        assert!(shared <= per_lesson, "{backend:?}: {shared} > {per_lesson}");
+        if per_lesson == 3 {
+          assert_eq!(unfilled(&result), 0, "{backend:?}");
+        }
+      }
+    }
+  }
+
+  /// Keine Klasse wird von mehr Praktikanten besucht als `per_class` erlaubt
+  #[test]
+  fn per_class() {
+    let plan = plan(2);
+    let lines = plan.stunden_lehrerplan();
+    let capacity = Capacity {
+      per_lesson: 3,
+      per_class: Some(1),
+      ..Default::default()
+    };
+    for backend in BACKENDS {
+      let result = solve_three(&plan, &capacity, backend);
+      assert_eq!(
+        max_shared(&plan, &result, |pl_index| lines[pl_index].klasse),
+        1,
+        "{backend:?}"
+      );
+    }
+  }
+
+  /// Keine Lehrkraft wird von mehr Praktikanten begleitet als `per_mentor` erlaubt
+  #[test]
+  fn per_mentor() {
+    let plan = plan(3);
+    let lines = plan.stunden_lehrerplan();
+    let capacity = Capacity {
+      per_lesson: 3,
+      per_mentor: Some(1),
+      ..Default::default()
+    };
+    for backend in BACKENDS {
+      let result = solve_three(&plan, &capacity, backend);
+      assert_eq!(
+        max_shared(&plan, &result, |pl_index| lines[pl_index].lehrkraft),
+        1,
+        "{backend:?}"
+      );
+    }
+  }
+}
@@ -0,0 +1,430 @@
+//! Belastung des Praktikanten: Stunden je Woche und je Tag sowie Freistunden.
+//!
+//! Grundlage ist die Liste der Unterrichtsstunden, die [`generate`](crate::generate) aus den
+//! `TagZeile`n ableitet. Als Freistunde zählt jede Unterrichtsstunde zwischen der ersten und der
+//! letzten belegten Stunde eines Tages, die der Praktikant nicht belegt. Pausen und andere Stunden
+//! ohne Unterricht zählen nicht.
+//!
+//! Die Grenzen im [`Workload`] sind harte Bedingungen. Stunden, die ihretwegen leer bleiben,
+//! behandeln alle Verfahren wie Stunden, die die [`Capacity`](crate::Capacity) blockiert: Es werden
+//! so viele Stunden belegt, wie die Grenzen zulassen, welche davon bestimmt die Kostenfunktion. Mit
+//! z.B. 18 bis 22 Stunden je Woche werden also 22 Stunden belegt, sofern der Plan das hergibt, und
+//! nie weniger als 18.
+//!
+//! Mit [`Workload::target_per_week`] entscheidet dagegen die Kostenfunktion auch über die Anzahl:
+//! Leere Stunden des Praktikanten zählen nicht mehr als unbelegt, jede Stunde über oder unter dem
+//! Ziel kostet [`Objective::workload`](crate::Objective). Mit 18 bis 22 Stunden und dem Ziel 20
+//! werden also meist 20 Stunden belegt, weniger oder mehr nur, wenn das die übrigen Kosten genug
+//! senkt. [`Objective::gaps`](crate::Objective) bevorzugt darüber hinaus zusammenhängende Blöcke.
+
+use std::ops::Range;
+
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use crate::{PlannerError, Problem};
+
+/// Grenzen für die Belastung eines Praktikanten. `None` = unbegrenzt
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workload {
+  /// Mindestanzahl Stunden in der Woche. Ein fester Wert ist `min_per_week = max_per_week`.
+  pub min_per_week: Option<usize>,
+  /// Höchstanzahl Stunden in der Woche
+  pub max_per_week: Option<usize>,
+  /// Angestrebte Anzahl Stunden in der Woche, zwischen `min_per_week` und `max_per_week`. Jede
+  /// Stunde Abweichung kostet [`Objective::workload`](crate::Objective).
+  pub target_per_week: Option<usize>,
+  /// Mindestanzahl Stunden an Tagen mit Unterricht. Tage ganz ohne Unterricht sind erlaubt.
+  pub min_per_day: Option<usize>,
+  /// Höchstanzahl Stunden je Tag
+  pub max_per_day: Option<usize>,
+  /// Höchstanzahl Freistunden je Tag
+  pub max_gaps: Option<usize>,
+}
+
+impl Workload {
+  pub(crate) fn validate(&self) -> Result<(), PlannerError> {
+    let invalid = |message: String| Err(PlannerError::InvalidOptions(message));
+
+    for (name, max) in [
+      ("max_per_week", self.max_per_week),
+      ("max_per_day", self.max_per_day),
+    ] {
+      if max == Some(0) {
+        return invalid(format!("„{name}“ muss mindestens 1 sein"));
+      }
+    }
+    for ((min_name, max_name), min, max) in [
+      (
+        ("min_per_week", "max_per_week"),
+        self.min_per_week,
+        self.max_per_week,
+      ),
+      (
+        ("min_per_day", "max_per_day"),
+        self.min_per_day,
+        self.max_per_day,
+      ),
+    ] {
+      if let (Some(min), Some(max)) = (min, max)
+        && min > max
+      {
+        return invalid(format!(
+          "„{min_name}“ ({min}) ist größer als „{max_name}“ ({max})"
+        ));
+      }
+    }
+    if let Some(target) = self.target_per_week
+      && (self.min_per_week.is_some_and(|min| target < min)
+        || self.max_per_week.is_some_and(|max| target > max))
+    {
+      return invalid(format!(
+        "„target_per_week“ ({target}) liegt außerhalb von „min_per_week“ bis „max_per_week“"
+      ));
+    }
+
+    Ok(())
+  }
+
+  /// Leere Stunden zählen als unbelegt, die Verfahren belegen also so viele Stunden wie möglich.
+  /// Nur ohne [`Workload::target_per_week`].
+  pub(crate) fn fills_all(&self) -> bool {
+    self.target_per_week.is_none()
+  }
+
+  /// Abweichung einer Woche mit insgesamt `lessons` Stunden von `target_per_week`
+  pub(crate) fn deviation(&self, lessons: usize) -> usize {
+    self
+      .target_per_week
+      .map_or(0, |target| lessons.abs_diff(target))
+  }
+
+  /// Die Grenzen je Tag schränken die Belegung ein
+  pub(crate) fn limits_days(&self) -> bool {
+    self.min_per_day.is_some() || self.max_per_day.is_some() || self.max_gaps.is_some()
+  }
+
+  /// Irgendeine Grenze schränkt die Belegung ein
+  pub(crate) fn limits(&self) -> bool {
+    self.limits_days() || self.min_per_week.is_some() || self.max_per_week.is_some()
+  }
+
+  /// Umfang der Verstöße einer Woche mit insgesamt `lessons` Stunden gegen die Grenzen je Woche
+  pub(crate) fn week_violations(&self, lessons: usize) -> usize {
+    let too_few = self
+      .min_per_week
+      .map_or(0, |min| min.saturating_sub(lessons));
+    let too_many = self
+      .max_per_week
+      .map_or(0, |max| lessons.saturating_sub(max));
+
+    too_few + too_many
+  }
+
+  /// Umfang der Verstöße eines Tages mit `load` gegen die Grenzen, 0 = alle eingehalten
+  pub(crate) fn violations(&self, load: DayLoad) -> usize {
+    let too_few = match self.min_per_day {
+      Some(min) if load.lessons > 0 => min.saturating_sub(load.lessons),
+      _ => 0,
+    };
+    let too_many = self
+      .max_per_day
+      .map_or(0, |max| load.lessons.saturating_sub(max));
+    let gaps = self.max_gaps.map_or(0, |max| load.gaps.saturating_sub(max));
+
+    too_few + too_many + gaps
+  }
+}
+
+/// Stunden und Freistunden an einem Tag
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct DayLoad {
+  pub(crate) lessons: usize,
+  pub(crate) gaps: usize,
+}
+
+impl DayLoad {
+  /// Wertet die Belegung eines Tages in der Reihenfolge des Rasters aus
+  pub(crate) fn new(filled: impl IntoIterator<Item = bool>) -> Self {
+    let mut load = DayLoad::default();
+    // Leere Stunden seit der letzten belegten
+    let mut pending = 0;
+
+    for filled in filled {
+      if !filled {
+        pending += 1;
+        continue;
+      }
+      if load.lessons > 0 {
+        load.gaps += pending;
+      }
+      pending = 0;
+      load.lessons += 1;
+    }
+
+    load
+  }
+}
+
+/// Für die Schranke der Suche: Stunden mit möglichem Unterricht, die wegen des [`Workload`]
+/// mindestens leer bleiben
+#[derive(Debug)]
+pub(crate) struct ForcedEmpty {
+  // [slot] = Anzahl der Stunden ab `slot` mit möglichem Unterricht
+  candidates: Vec<usize>,
+  // [day] = Anzahl der Stunden ab Beginn von `day`, die mindestens leer bleiben
+  from_day: Vec<usize>,
+  min_per_day: usize,
+  max_per_day: usize,
+  max_per_week: usize,
+}
+
+impl ForcedEmpty {
+  pub(crate) fn new(
+    schedule: &Array2<Vec<(usize, usize)>>,
+    days: &[Range<usize>],
+    workload: &Workload,
+  ) -> Self {
+    let time_slots = schedule.ncols();
+    let mut candidates = vec![0; time_slots + 1];
+    for slot in (0..time_slots).rev() {
+      let possible = schedule
+        .column(slot)
+        .iter()
+        .any(|lessons| !lessons.is_empty());
+      candidates[slot] = candidates[slot + 1] + usize::from(possible);
+    }
+
+    let mut forced = ForcedEmpty {
+      candidates,
+      from_day: vec![0; days.len() + 1],
+      min_per_day: workload.min_per_day.unwrap_or(0),
+      max_per_day: workload.max_per_day.unwrap_or(usize::MAX),
+      max_per_week: workload.max_per_week.unwrap_or(usize::MAX),
+    };
+    for (day, slots) in days.iter().enumerate().rev() {
+      forced.from_day[day] = forced.from_day[day + 1] + forced.in_day(slots.clone(), 0);
+    }
+
+    forced
+  }
+
+  /// Leere Stunden in `slots`, wenn am selben Tag schon `lessons` Stunden belegt sind
+  fn in_day(&self, slots: Range<usize>, lessons: usize) -> usize {
+    let remaining = self.candidates[slots.start] - self.candidates[slots.end];
+    if lessons == 0 && remaining < self.min_per_day {
+      // Der Tag bleibt ganz leer
+      remaining
+    } else {
+      (lessons + remaining).saturating_sub(self.max_per_day)
+    }
+  }
+
+  /// Leere Stunden ab `slot` am Tag `day`, an dem bereits `day_lessons` Stunden belegt sind, bei
+  /// insgesamt `week_lessons` belegten Stunden
+  pub(crate) fn from(
+    &self,
+    slot: usize,
+    day: usize,
+    days: &[Range<usize>],
+    day_lessons: usize,
+    week_lessons: usize,
+  ) -> usize {
+    let by_days = self.in_day(slot..days[day].end, day_lessons) + self.from_day[day + 1];
+    let open = self.max_per_week.saturating_sub(week_lessons);
+
+    by_days.max(self.candidates[slot].saturating_sub(open))
+  }
+
+  /// Höchstens erreichbare Anzahl Stunden in der Woche, Argumente wie bei [`ForcedEmpty::from`]
+  pub(crate) fn max_lessons(
+    &self,
+    slot: usize,
+    day: usize,
+    days: &[Range<usize>],
+    day_lessons: usize,
+    week_lessons: usize,
+  ) -> usize {
+    week_lessons + self.candidates[slot] - self.from(slot, day, days, day_lessons, week_lessons)
+  }
+}
+
+impl Problem {
+  /// Belastung jedes Tages, an dem Unterricht stattfindet. `filled(slot)` gibt an, ob der
+  /// Praktikant die Stunde belegt.
+  pub(crate) fn day_loads(&self, filled: impl Fn(usize) -> bool) -> impl Iterator<Item = DayLoad> {
+    self
+      .days
+      .iter()
+      .map(move |slots: &Range<usize>| DayLoad::new(slots.clone().map(&filled)))
+  }
+
+  /// Summe der Freistunden über alle Tage und Umfang der Verstöße gegen den [`Workload`]
+  pub(crate) fn gaps_and_violations(
+    &self,
+    trainee: usize,
+    filled: impl Fn(usize) -> bool,
+  ) -> (usize, usize) {
+    let workload = &self.trainees[trainee].workload;
+    let (gaps, violations, lessons) =
+      self
+        .day_loads(filled)
+        .fold((0, 0, 0), |(gaps, violations, lessons), load| {
+          (
+            gaps + load.gaps,
+            violations + workload.violations(load),
+            lessons + load.lessons,
+          )
+        });
+
+    (gaps, violations + workload.week_violations(lessons))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use ndarray::Array2;
+
+  use crate::{
+    Backend, Capacity, PlannerError, Praktikant,
+    testing::{plan, solve, trainee},
+  };
+
+  use super::{DayLoad, ForcedEmpty, Workload};
+
+  #[test]
+  fn day_load() {
+    let load = DayLoad::new([false, true, false, false, true, false]);
+    assert_eq!(
+      load,
+      DayLoad {
+        lessons: 2,
+        gaps: 2
+      }
+    );
+    assert_eq!(DayLoad::new([false; 3]), DayLoad::default());
+  }
+
+  /// Tag 0 mit 3 Stunden mit Unterricht, Tag 1 mit einer von 2
+  #[test]
+  fn forced_empty() {
+    let schedule = Array2::from_shape_fn((1, 5), |(_, slot)| {
+      (slot != 4).then_some((0, slot)).into_iter().collect()
+    });
+    let days = [0..3, 3..5];
+    let workload = Workload {
+      min_per_day: Some(2),
+      max_per_day: Some(2),
+      ..Default::default()
+    };
+    let forced = ForcedEmpty::new(&schedule, &days, &workload);
+
+    // Eine Stunde an Tag 0 zu viel, Tag 1 bleibt ganz leer
+    assert_eq!(forced.from(0, 0, &days, 0, 0), 2);
+    assert_eq!(forced.from(1, 0, &days, 1, 1), 2);
+    assert_eq!(forced.from(2, 0, &days, 2, 2), 2);
+    assert_eq!(forced.from(3, 1, &days, 0, 2), 1);
+    assert_eq!(forced.from(3, 1, &days, 1, 3), 0);
+    assert_eq!(forced.max_lessons(0, 0, &days, 0, 0), 2);
+
+    // Mit höchstens einer Stunde je Woche bleiben alle übrigen leer
+    let forced = ForcedEmpty::new(
+      &schedule,
+      &days,
+      &Workload {
+        max_per_week: Some(1),
+        ..workload
+      },
+    );
+    assert_eq!(forced.from(0, 0, &days, 0, 0), 3);
+    assert_eq!(forced.from(1, 0, &days, 1, 1), 3);
+    assert_eq!(forced.max_lessons(0, 0, &days, 0, 0), 1);
+  }
+
+  #[test]
+  fn respected_by_all_backends() {
+    let workload = Workload {
+      min_per_week: Some(4),
+      max_per_week: Some(5),
+      min_per_day: Some(2),
+      max_per_day: Some(3),
+      max_gaps: Some(0),
+      ..Default::default()
+    };
+    let trainees = [Praktikant {
+      workload,
+      ..trainee("A")
+    }];
+
+    for seed in 1..4 {
+      let plan = plan(seed);
+      for backend in [Backend::Search, Backend::Milp, Backend::LocalSearch] {
+        let result = solve(&plan, &trainees, &Capacity::default(), backend).unwrap();
+        for day in &result.plans[0].solution {
+          let load = DayLoad::new(day.iter().map(Option::is_some));
+          assert_eq!(
+            workload.violations(load),
+            0,
+            "{backend:?}, Seed {seed}: {load:?}"
+          );
+          assert!(load.lessons == 0 || (2..=3).contains(&load.lessons));
+          assert_eq!(load.gaps, 0);
+        }
+        let lessons = result.plans[0].solution.iter().flatten().flatten().count();
+        assert!(
+          (4..=5).contains(&lessons),
+          "{backend:?}, Seed {seed}: {lessons}"
+        );
+      }
+    }
+  }
+
+  /// Ein Ziel unter der Anzahl möglicher Stunden wird genau erreicht, obwohl mehr Stunden frei
+  /// wären
+  #[test]
+  fn target_met() {
+    let trainees = [Praktikant {
+      workload: Workload {
+        target_per_week: Some(4),
+        ..Default::default()
+      },
+      ..trainee("A")
+    }];
+
+    for seed in 1..4 {
+      let plan = plan(seed);
+      for backend in [Backend::Search, Backend::Milp, Backend::LocalSearch] {
+        let result = solve(&plan, &trainees, &Capacity::default(), backend).unwrap();
+        let breakdown = &result.plans[0].breakdown;
+        let lessons = result.plans[0].solution.iter().flatten().flatten().count();
+        assert_eq!(lessons, 4, "{backend:?}, Seed {seed}");
+        assert_eq!(breakdown.workload.lessons, 4);
+        assert_eq!(breakdown.workload.deviation, 0);
+        assert!(breakdown.empty_slots.len() > breakdown.blocked_slots.len());
+      }
+    }
+  }
+
+  #[test]
+  fn target_outside_limits_rejected() {
+    let workload = Workload {
+      min_per_week: Some(4),
+      max_per_week: Some(6),
+      target_per_week: Some(7),
+      ..Default::default()
+    };
+    assert!(matches!(
+      workload.validate(),
+      Err(PlannerError::InvalidOptions(_))
+    ));
+    assert!(
+      Workload {
+        target_per_week: Some(5),
+        ..workload
+      }
+      .validate()
+      .is_ok()
+    );
+  }
+}
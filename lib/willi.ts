@@ -4,9 +4,12 @@ import { WilliStundenplan } from "willi";
  * are dead files. */
 export function computeSubjectCounts(plan: WilliStundenplan): { [kuerzel: string]: number } {
   const result = {};
+  const faecher = plan.faecher;
 
   for (const entry of Object.values(plan.stunden_lehrerplan())) {
-    result[entry.fach] = (result[entry.fach] || 0) + 1;
+    const kuerzel = faecher[entry.fach]?.kuerzel;
+    if (kuerzel === undefined) continue;
+    result[kuerzel] = (result[kuerzel] || 0) + 1;
   }
 
   return result;
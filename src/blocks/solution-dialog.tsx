@@ -32,6 +32,10 @@ export default function SolutionDialog({
     if (!solution || !plan) return [];
 
     const pl_lines = plan.stunden_lehrerplan();
+    // PL-Zeilen verweisen über IDs, angezeigt werden die Kürzel
+    const faecher = plan.faecher;
+    const klassen = plan.klassen;
+    const lehrkraefte = plan.lehrkraefte;
 
     const longest = solution.assignments
       .map((day) => day.length)
@@ -41,7 +45,13 @@ export default function SolutionDialog({
       solution.assignments.map((day) => {
         const idx = day[period];
         if (!idx) return null;
-        return pl_lines[idx];
+        const line = pl_lines[idx];
+        return {
+          raum: line.raum,
+          fach: faecher[line.fach]?.kuerzel,
+          klasse: klassen[line.klasse]?.kuerzel,
+          lehrkraft: lehrkraefte[line.lehrkraft]?.kuerzel,
+        };
       }),
     );
   }, [solution, plan]);
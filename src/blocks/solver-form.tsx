@@ -123,8 +123,15 @@ export default function SolverForm({
     setSolution(null);
     setStartTime(Date.now());
     setProgress(0);
-    startWorker(plan, subjects, excluded_teachers);
-  }, [setSolution, startWorker]);
+    startWorker({
+      plan,
+      trainee: {
+        subjects: subjects.map((kuerzel) => ({ kuerzel, gewicht: 1 })),
+        excluded_teachers,
+      },
+      options: anytimeEnabled ? { time_limit_ms: anytime } : undefined,
+    });
+  }, [setSolution, startWorker, anytime, anytimeEnabled]);
 
   const abort = useCallback(() => {
     abortWorker();
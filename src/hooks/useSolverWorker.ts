@@ -1,21 +1,95 @@
 import { useEffect, useRef, useState, useCallback } from "react";
+import type { GenerateInput, JsSolveOptions } from "planner-core";
 import MyWorker from "../worker?worker";
 
+/** Inputs of a single run, forwarded to `wasm_generate`. Everything but the plan and the trainee's
+ * subjects is optional and falls back to the solver's defaults. The cancel flag is added here. */
+export interface SolverInput extends Omit<GenerateInput, "options"> {
+  options?: Omit<JsSolveOptions, "cancel">;
+}
+
+export interface CostBreakdown {
+  total: number;
+  classes: { kuerzel: string[]; count: number; cost: number };
+  subjects: {
+    kuerzel: string;
+    actual: number;
+    target: number;
+    imbalance: number;
+    cost: number;
+  }[];
+  gaps: { count: number; cost: number };
+  mentors: {
+    kuerzel: string[];
+    lessons: number[];
+    count: number;
+    cost: number;
+    preferred: string | null;
+    elsewhere: number;
+    elsewhere_cost: number;
+  };
+  coverage: {
+    name: string;
+    subject: string | null;
+    lessons: number;
+    min_lessons: number;
+    target: number;
+    shortfall: number;
+    cost: number;
+  }[];
+  continuity: {
+    pairs: { class: string; subject: string; slots: { day: number; period: number }[] }[];
+    count: number;
+    cost: number;
+  };
+  workload: {
+    lessons: number;
+    target: number | null;
+    deviation: number;
+    cost: number;
+  };
+  empty_slots: { day: number; period: number }[];
+  blocked_slots: { day: number; period: number }[];
+  dropped: { slot: { day: number; period: number }; pl_index: number; chosen: number }[];
+  empty_reasons: { slot: { day: number; period: number }; reason: EmptyReason }[];
+}
+
+export type EmptyReason =
+  | "no_lesson_period"
+  | "unavailable"
+  | "no_lesson"
+  | "teachers_excluded"
+  | "forbidden"
+  | "broken_double"
+  | "left_empty";
+
+export interface Alternative {
+  solution: (number | null)[][];
+  cost: number;
+  breakdown: CostBreakdown;
+}
+
 export function useSolverWorker(
-  onSolution: (solution: (number | null)[][]) => void,
-  onFinal: (solution: (number | null)[][]) => void,
+  onSolution: (
+    solution: (number | null)[][],
+    breakdown: CostBreakdown,
+  ) => void,
+  onFinal: (
+    solution: (number | null)[][],
+    breakdown: CostBreakdown,
+    alternatives: Alternative[],
+  ) => void,
 ): {
   worker: Worker | null;
-  start: (
-    plan: string,
-    subjects: string[],
-    excluded_teachers: string[],
-  ) => void;
+  start: (input: SolverInput) => void;
   working: boolean;
   abort: () => void;
 } {
   const workerRef = useRef<Worker | null>(null);
   const [working, setWorking] = useState(false);
+  // Flag the solver polls while it runs, the worker itself cannot receive messages until it is
+  // done. Only available if the page is cross-origin isolated.
+  const cancelRef = useRef<Int32Array | null>(null);
 
   const startWorker = useCallback(() => {
     const worker = new MyWorker();
@@ -23,13 +97,20 @@ export function useSolverWorker(
     worker.addEventListener("message", (e: MessageEvent) => {
       // NOTE: progress updates are handled directly by the progress component to
       // avoid re-running components higher up in the hierarchy
+      // The worker plans a single trainee, so every solution holds exactly one plan
       if (e.data.type === "solution") {
-        onSolution(e.data.solution);
+        const [plan] = e.data.plans;
+        onSolution(plan.solution, plan.breakdown);
       }
 
       if (e.data.type === "final") {
         setWorking(false);
-        onFinal(e.data.solution);
+        onFinal(e.data.solution, e.data.breakdown, e.data.alternatives ?? []);
+      }
+
+      if (e.data.type === "error") {
+        setWorking(false);
+        console.error("Solver error:", e.data.error);
       }
     });
 
@@ -44,30 +125,39 @@ export function useSolverWorker(
     startWorker();
   }, [startWorker]);
 
-  // TODO: handle error — at the very least, the spinner should stop
-
   const start = useCallback(
-    (plan: string, subjects: string[], excluded_teachers: string[]) => {
+    (input: SolverInput) => {
       setWorking(true);
+      const cancel =
+        typeof SharedArrayBuffer !== "undefined" && self.crossOriginIsolated
+          ? new Int32Array(new SharedArrayBuffer(Int32Array.BYTES_PER_ELEMENT))
+          : null;
+      cancelRef.current = cancel;
       workerRef.current?.postMessage({
         type: "start",
-        subjects,
-        plan,
-        excluded_teachers,
+        input: {
+          ...input,
+          options: { ...input.options, cancel: cancel ?? undefined },
+        },
       });
     },
     [workerRef],
   );
 
-  // TODO: abort
   const abort = useCallback(
     () => {
+      if (cancelRef.current) {
+        // The solver stops at its next check and sends its best solution so far as "final"
+        Atomics.store(cancelRef.current, 0, 1);
+        return;
+      }
+
+      // Without a shared flag the only way to stop is to discard the worker and its progress
       workerRef.current?.terminate();
       setWorking(false);
-      // TODO: Does this really make that much sense?
       startWorker();
     },
-    [workerRef, startWorker]
+    [workerRef, cancelRef, startWorker]
   );
 
   return {
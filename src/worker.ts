@@ -1,23 +1,33 @@
-import { wasm_generate } from "planner-core";
+import { wasm_generate, PlannerError } from "planner-core";
 
 self.addEventListener("message", (e) => {
   if (e.data.type === "start") {
-    const { plan, subjects, excluded_teachers } = e.data;
+    const { input } = e.data;
 
-    console.log("starting!", e.data);
+    console.log("starting!", input);
 
-    const solution = wasm_generate(
-      plan,
-      subjects,
-      new Float64Array(),
-      excluded_teachers,
-    );
+    let result;
+    try {
+      result = wasm_generate(input);
+    } catch (error) {
+      if (!(error instanceof PlannerError)) throw error;
 
-    console.log("done!", solution);
+      self.postMessage({
+        type: "error",
+        error: { kind: error.kind, message: error.message },
+      });
+      return;
+    }
+
+    console.log("done!", result);
 
     self.postMessage({
       type: "final",
-      solution: solution,
+      solution: result.solution,
+      optimal: result.optimal,
+      stopped: result.stopped,
+      breakdown: result.breakdown,
+      alternatives: result.alternatives,
     });
   }
 });
@@ -5,6 +5,12 @@ import { resolve } from "path";
 import wasm from "vite-plugin-wasm";
 import topLevelAwait from 'vite-plugin-top-level-await';
 
+// SharedArrayBuffer, used to stop a running solver, requires cross-origin isolation
+const isolationHeaders = {
+  "Cross-Origin-Opener-Policy": "same-origin",
+  "Cross-Origin-Embedder-Policy": "require-corp",
+};
+
 // https://vite.dev/config/
 export default defineConfig({
   base: "/praktikumsplaner/",
@@ -16,6 +22,8 @@ export default defineConfig({
       "planner-core": resolve(__dirname, "./core/pkg"),
     },
   },
+  server: { headers: isolationHeaders },
+  preview: { headers: isolationHeaders },
   worker: {
     plugins: () => [wasm(), topLevelAwait({
       // The export name of top-level await promise for each chunk module
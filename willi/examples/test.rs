@@ -1,21 +1,35 @@
-use std::{fs::File, io::Read};
+use std::{env, fs::File, io::Read};
 
-use willi::WilliStundenplan;
+use willi::{SyntheticParams, WilliStundenplan};
 
 fn main() {
-  let mut raw_source = vec![];
-  File::open("/Users/jake/Downloads/AKG2025.BAL")
-    .unwrap()
-    .read_to_end(&mut raw_source)
-    .unwrap();
-  let reencoded_source: String = raw_source.iter().map(|&c| c as char).collect();
+  // Ohne Pfad wird ein synthetischer Plan verwendet
+  let plan = match env::args().nth(1) {
+    Some(path) => {
+      let mut raw_source = vec![];
+      File::open(path)
+        .unwrap()
+        .read_to_end(&mut raw_source)
+        .unwrap();
+      let reencoded_source: String = raw_source.iter().map(|&c| c as char).collect();
 
-  let (_plan, errors) = WilliStundenplan::parse(&reencoded_source[..]);
+      let (plan, errors) = WilliStundenplan::parse(&reencoded_source[..]);
 
-  if !errors.is_empty() {
-    eprintln!("Encountered parse errors:");
-    for error in errors {
-      println!("{}", error.1);
+      if !errors.is_empty() {
+        eprintln!("Encountered parse errors:");
+        for error in errors {
+          println!("{}", error.1);
+        }
+      }
+
+      plan
     }
-  }
+    None => WilliStundenplan::synthetic(&SyntheticParams::default()),
+  };
+
+  println!(
+    "{} Klassen, {} Stunden im Lehrerplan",
+    plan.klassen().iter().count(),
+    plan.lehrerstunden().len()
+  );
 }
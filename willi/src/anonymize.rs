@@ -0,0 +1,243 @@
+//! Anonymisierung eines [`WilliStundenplan`].
+//!
+//! Lehrkräfte, Klassen, Räume und Schuldaten werden durch Pseudonyme ersetzt, alle Verweise
+//! über Kürzel werden konsistent mit umgeschrieben. U- und PL-Zeilen verweisen über IDs und
+//! bleiben daher gültig. Fächer, Tage, Stunden und die Struktur des Stundenplans bleiben
+//! unverändert.
+//!
+//! Bei gleichem `seed` ergibt sich dieselbe Zuordnung.
+
+use std::collections::HashMap;
+
+use rand::{SeedableRng, seq::SliceRandom};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{RaumId, WilliStundenplan};
+
+/// Zuordnung Original → Pseudonym für eine Art von Kürzeln.
+#[derive(Debug, Clone, Default)]
+pub struct Pseudonyme {
+  prefix: &'static str,
+  map: HashMap<String, String>,
+}
+
+impl Pseudonyme {
+  /// Vergibt Pseudonyme für `known` in zufälliger (aber durch `rng` festgelegter) Reihenfolge,
+  /// damit die Nummerierung keine Rückschlüsse auf die ursprüngliche Reihenfolge zulässt.
+  fn new<'a>(
+    prefix: &'static str,
+    known: impl Iterator<Item = &'a str>,
+    rng: &mut ChaCha8Rng,
+  ) -> Self {
+    let mut originals: Vec<&str> = vec![];
+    for kuerzel in known {
+      if !originals.contains(&kuerzel) {
+        originals.push(kuerzel);
+      }
+    }
+    originals.shuffle(rng);
+
+    let mut pseudonyme = Pseudonyme {
+      prefix,
+      map: HashMap::new(),
+    };
+    for kuerzel in originals {
+      pseudonyme.get(kuerzel);
+    }
+    pseudonyme
+  }
+
+  /// Pseudonym für `original`. Unbekannte Kürzel (z.B. Verweise ohne eigene Tabellenzeile)
+  /// erhalten die nächste freie Nummer.
+  pub fn get(&mut self, original: &str) -> String {
+    if let Some(pseudonym) = self.map.get(original) {
+      return pseudonym.clone();
+    }
+
+    let pseudonym = format!("{}{:03}", self.prefix, self.map.len() + 1);
+    self.map.insert(original.to_string(), pseudonym.clone());
+    pseudonym
+  }
+
+  fn rewrite(&mut self, field: &mut String) {
+    *field = self.get(field);
+  }
+
+  fn rewrite_opt(&mut self, field: &mut Option<String>) {
+    if let Some(field) = field {
+      self.rewrite(field);
+    }
+  }
+
+  fn rewrite_raum(&mut self, field: &mut Option<RaumId>) {
+    if let Some(RaumId(field)) = field {
+      self.rewrite(field);
+    }
+  }
+
+  /// Die vergebenen Pseudonyme, nach Originalkürzel geschlüsselt.
+  pub fn zuordnung(&self) -> &HashMap<String, String> {
+    &self.map
+  }
+}
+
+/// Ergebnis von [`WilliStundenplan::anonymize`].
+#[derive(Debug, Clone)]
+pub struct Anonymisierung {
+  pub plan: WilliStundenplan,
+  pub lehrkraefte: Pseudonyme,
+  pub klassen: Pseudonyme,
+  pub raeume: Pseudonyme,
+}
+
+impl WilliStundenplan {
+  /// Erstellt eine anonymisierte Kopie des Plans.
+  ///
+  /// Ersetzt werden Kürzel und Namen der Lehrkräfte (`L001`, …, ohne Anrede), Kürzel und Namen der
+  /// Klassen (`K001`, …) sowie Raumkürzel (`R001`, …). Schulname und -nummer werden ersetzt bzw.
+  /// entfernt, ebenso Schülerzahlen und ASV-Kennungen. Die Zuordnungen werden mit zurückgegeben,
+  /// damit Fehlerberichte intern wieder aufgelöst werden können.
+  pub fn anonymize(&self, seed: u64) -> Anonymisierung {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut plan = self.clone();
+
+    let mut lehrkraefte = Pseudonyme::new(
+      "L",
+      self.lehrkraefte.iter().map(|(_, l)| &l.kuerzel[..]),
+      &mut rng,
+    );
+    let mut klassen = Pseudonyme::new(
+      "K",
+      self.klassen.iter().map(|(_, k)| &k.kuerzel[..]),
+      &mut rng,
+    );
+    let mut raeume = Pseudonyme::new(
+      "R",
+      self
+        .klassen
+        .iter()
+        .filter_map(|(_, k)| k.klassenraum.as_deref())
+        .chain(
+          self
+            .unterrichtseinheiten
+            .iter()
+            .filter_map(|(_, u)| u.raum.as_ref().map(|r| &r.0[..])),
+        )
+        .chain(
+          self
+            .stunden_lehrerplan
+            .iter()
+            .filter_map(|pl| pl.raum.as_ref().map(|r| &r.0[..])),
+        ),
+      &mut rng,
+    );
+
+    if let Some(schuldaten) = &mut plan.schuldaten {
+      schuldaten.schulname = "Schule".to_string();
+      schuldaten.titel1 = None;
+      schuldaten.titel2 = None;
+      schuldaten.schulnummer = None;
+    }
+
+    for lehrkraft in plan.lehrkraefte.values_mut() {
+      lehrkraefte.rewrite(&mut lehrkraft.kuerzel);
+      lehrkraft.kurz = lehrkraft.kurz.as_ref().map(|_| lehrkraft.kuerzel.clone());
+      lehrkraft.name = lehrkraft
+        .name
+        .as_ref()
+        .map(|_| format!("Lehrkraft {}", lehrkraft.kuerzel));
+      lehrkraft.vorname = lehrkraft.vorname.as_ref().map(|_| "Vorname".to_string());
+      lehrkraft.anrede = None;
+    }
+
+    for klasse in plan.klassen.values_mut() {
+      klassen.rewrite(&mut klasse.kuerzel);
+      klasse.kurz = klasse.kurz.as_ref().map(|_| klasse.kuerzel.clone());
+      klasse.name = klasse.name.as_ref().map(|_| klasse.kuerzel.clone());
+      raeume.rewrite_opt(&mut klasse.klassenraum);
+      lehrkraefte.rewrite_opt(&mut klasse.klassenleiter);
+      lehrkraefte.rewrite_opt(&mut klasse.zweitklassenleiter);
+      klassen.rewrite_opt(&mut klasse.stammklasse);
+      klasse.schule = None;
+      klasse.asv_klasse = None;
+      klasse.schuelerzahl = None;
+      klasse.weiblich = None;
+      klasse.rk = None;
+      klasse.ev = None;
+      klasse.sonst = None;
+      klasse.fahr = None;
+    }
+
+    for unterricht in plan.unterrichtseinheiten.values_mut() {
+      lehrkraefte.rewrite_opt(&mut unterricht.lehrerbezeichner);
+      klassen.rewrite_opt(&mut unterricht.klassenbezeichner);
+      raeume.rewrite_raum(&mut unterricht.raum);
+      unterricht.schuelerzahl = None;
+    }
+
+    for stunde in plan.stunden_lehrerplan.iter_mut() {
+      raeume.rewrite_raum(&mut stunde.raum);
+    }
+
+    Anonymisierung {
+      plan,
+      lehrkraefte,
+      klassen,
+      raeume,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::testing;
+
+  #[test]
+  fn seeded() {
+    let plan = testing::plan();
+    let first = plan.anonymize(7);
+    let second = plan.anonymize(7);
+
+    assert_eq!(
+      first.lehrkraefte.zuordnung(),
+      second.lehrkraefte.zuordnung()
+    );
+    assert_eq!(first.klassen.zuordnung(), second.klassen.zuordnung());
+    assert_eq!(
+      serde_json::to_string(&first.plan).unwrap(),
+      serde_json::to_string(&second.plan).unwrap()
+    );
+  }
+
+  #[test]
+  fn no_originals_left() {
+    let plan = testing::plan();
+    let anonymisierung = plan.anonymize(7);
+    let json = serde_json::to_string(&anonymisierung.plan).unwrap();
+
+    // Kürzel als vollständige JSON-Strings, da sie kurz sind
+    for kuerzel in ["MUE", "SCH", "WEB", "Mü", "Sc", "We", "5a", "6b"] {
+      assert!(
+        !json.contains(&format!("\"{kuerzel}\"")),
+        "{kuerzel} in {json}"
+      );
+    }
+    for name in [
+      "Müller", "Schmidt", "Weber", "Anna", "Bernd", "Clara", "Frau", "Herr",
+    ] {
+      assert!(!json.contains(name), "{name} in {json}");
+    }
+    for name in ["Testschule", "Klasse 5a", "Klasse 6b"] {
+      assert!(!json.contains(name), "{name} in {json}");
+    }
+
+    // Verweise zeigen weiterhin auf dieselben Zeilen
+    let plan = &anonymisierung.plan;
+    let weber = plan.lehrkraefte.get(4).unwrap();
+    assert_eq!(weber.kuerzel, anonymisierung.lehrkraefte.zuordnung()["WEB"]);
+    let lehrkraft = plan.stunden_lehrerplan[3].lehrkraft;
+    assert_eq!(plan.lehrkraft(lehrkraft).unwrap().kuerzel, weber.kuerzel);
+    assert_eq!(weber.anrede, None);
+    assert_eq!(plan.faecher.get(1).unwrap().kuerzel, "M");
+  }
+}
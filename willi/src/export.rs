@@ -0,0 +1,447 @@
+//! Export eines [`WilliStundenplan`] nach JSON und CSV.
+//!
+//! Beide Formate teilen sich ein gemeinsames Schema (Version [`SCHEMA_VERSION`]):
+//!
+//! ```text
+//! {
+//!   "schema": 2,
+//!   "willi_version": 5 | null,
+//!   "schuldaten": { ...W-Zeile... } | null,
+//!   "faecher":              { "<id>": { ...F-Zeile... } },
+//!   "klassen":              { "<id>": { ...K-Zeile... } },
+//!   "lehrkraefte":          { "<id>": { ...L-Zeile... } },
+//!   "tage":                 { "<id>": { ...T-Zeile... } },
+//!   "stunden":              { "<id>": { ...S-Zeile... } },
+//!   "unterrichtseinheiten": { "<id>": { ...U-Zeile... } },
+//!   "stunden_lehrerplan":   { "<index>": { ...PL-Zeile... } }
+//! }
+//! ```
+//!
+//! Tabellen sind nach ihrer numerischen WILLI-ID geschlüsselt, der Lehrerplan nach dem PL-Index,
+//! den auch der Planer verwendet. Jede Zeile enthält ihre Felder unter den Namen der Rust-Structs
+//! (darunter `id`, identisch mit dem Schlüssel). Verweise auf Lehrkräfte, Fächer, Klassen, Tage
+//! und Stunden sind schon beim Einlesen zu IDs aufgelöst, bei PL-Zeilen also auch
+//! `tag_stunde.tag` und `tag_stunde.stunde`.
+//!
+//! Im CSV-Export wird jede Tabelle zu einer Datei `<tabelle>.csv` mit Kopfzeile. Die erste Spalte
+//! `id` enthält den Tabellenschlüssel (bzw. den PL-Index), verschachtelte Felder werden mit Punkt
+//! getrennt (`tag_stunde.tag`).
+
+use std::{
+  collections::BTreeMap,
+  fs::{self, File},
+  io::{Seek, Write},
+  path::Path,
+};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use thiserror::Error;
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+use crate::{LehrerStundenZeile, SparseVec, UnterrichtsZeile, WilliStundenplan};
+
+/// Version des Export-Schemas. Wird bei inkompatiblen Änderungen erhöht.
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+  #[error("Ein-/Ausgabefehler — {0}")]
+  Io(#[from] std::io::Error),
+  #[error("JSON-Fehler — {0}")]
+  Json(#[from] serde_json::Error),
+  #[error("CSV-Fehler — {0}")]
+  Csv(#[from] csv::Error),
+  #[error("ZIP-Fehler — {0}")]
+  Zip(#[from] zip::result::ZipError),
+}
+
+#[derive(Serialize)]
+struct Export<'a> {
+  schema: u32,
+  willi_version: Option<usize>,
+  schuldaten: Option<&'a crate::SchuldatenZeile>,
+  faecher: &'a SparseVec<crate::FachZeile>,
+  klassen: &'a SparseVec<crate::KlassenZeile>,
+  lehrkraefte: &'a SparseVec<crate::LehrkraftZeile>,
+  tage: &'a SparseVec<crate::TagZeile>,
+  stunden: &'a SparseVec<crate::StundenZeile>,
+  unterrichtseinheiten: &'a SparseVec<UnterrichtsZeile>,
+  stunden_lehrerplan: BTreeMap<usize, &'a LehrerStundenZeile>,
+}
+
+/// Tabelle des CSV-Exports: Dateiname (ohne Endung) und flache Zeilen
+type CsvTable = (String, Vec<Map<String, Value>>);
+
+impl WilliStundenplan {
+  fn export(&self) -> Export<'_> {
+    Export {
+      schema: SCHEMA_VERSION,
+      willi_version: self.header.as_ref().map(|h| h.version),
+      schuldaten: self.schuldaten.as_ref(),
+      faecher: &self.faecher,
+      klassen: &self.klassen,
+      lehrkraefte: &self.lehrkraefte,
+      tage: &self.tage,
+      stunden: &self.stunden,
+      unterrichtseinheiten: &self.unterrichtseinheiten,
+      stunden_lehrerplan: self.stunden_lehrerplan.iter().enumerate().collect(),
+    }
+  }
+
+  /// Der Plan im Export-Schema (siehe [Modul-Dokumentation](crate::export)).
+  pub fn to_json_value(&self) -> Result<Value, ExportError> {
+    Ok(serde_json::to_value(self.export())?)
+  }
+
+  /// Schreibt den Plan als JSON im Export-Schema.
+  pub fn write_json<W: Write>(&self, writer: W) -> Result<(), ExportError> {
+    serde_json::to_writer_pretty(writer, &self.export())?;
+    Ok(())
+  }
+
+  /// Schreibt jede Tabelle als `<tabelle>.csv` in das Verzeichnis `dir`. Das Verzeichnis wird bei
+  /// Bedarf angelegt, vorhandene Dateien werden überschrieben.
+  pub fn write_csv_dir(&self, dir: impl AsRef<Path>) -> Result<(), ExportError> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+
+    for (name, rows) in self.csv_tables()? {
+      write_csv(File::create(dir.join(format!("{name}.csv")))?, &rows)?;
+    }
+
+    Ok(())
+  }
+
+  /// Schreibt jede Tabelle als `<tabelle>.csv` in ein ZIP-Archiv.
+  pub fn write_csv_zip<W: Write + Seek>(&self, writer: W) -> Result<W, ExportError> {
+    let mut zip = ZipWriter::new(writer);
+
+    for (name, rows) in self.csv_tables()? {
+      zip.start_file(format!("{name}.csv"), SimpleFileOptions::default())?;
+      write_csv(&mut zip, &rows)?;
+    }
+
+    Ok(zip.finish()?)
+  }
+
+  /// Tabellen des Export-Schemas als Liste flacher Zeilen, jeweils mit führender `id`-Spalte.
+  fn csv_tables(&self) -> Result<Vec<CsvTable>, ExportError> {
+    let Value::Object(export) = self.to_json_value()? else {
+      unreachable!("Export wird immer als Objekt serialisiert");
+    };
+
+    let mut tables = vec![];
+
+    for (name, value) in export {
+      let rows = match value {
+        // Einzelne Zeile (Schuldaten)
+        Value::Object(row) if name == "schuldaten" => vec![flatten(row)],
+        // Tabelle, nach ID geschlüsselt
+        Value::Object(table) => table
+          .into_iter()
+          .map(|(id, row)| {
+            let Value::Object(row) = row else {
+              unreachable!("Tabellenzeilen werden immer als Objekt serialisiert");
+            };
+            // Schuldaten- und PL-Zeilen haben keine eigene ID, daher den Tabellenschlüssel
+            // verwenden
+            let mut flat = Map::new();
+            flat.insert("id".to_string(), Value::String(id));
+            flat.extend(flatten(row).into_iter().filter(|(key, _)| key != "id"));
+            flat
+          })
+          .collect(),
+        // Metadaten und fehlende Schuldaten
+        _ => continue,
+      };
+
+      tables.push((name, rows));
+    }
+
+    Ok(tables)
+  }
+}
+
+/// Verschachtelte Objekte in Punkt-getrennte Spalten auflösen.
+fn flatten(row: Map<String, Value>) -> Map<String, Value> {
+  let mut flat = Map::new();
+
+  for (key, value) in row {
+    match value {
+      Value::Object(inner) => {
+        for (inner_key, inner_value) in flatten(inner) {
+          flat.insert(format!("{key}.{inner_key}"), inner_value);
+        }
+      }
+      value => {
+        flat.insert(key, value);
+      }
+    }
+  }
+
+  flat
+}
+
+fn write_csv<W: Write>(writer: W, rows: &[Map<String, Value>]) -> Result<(), ExportError> {
+  // Spalten in Reihenfolge ihres ersten Auftretens
+  let mut columns: Vec<&str> = vec![];
+  for key in rows.iter().flat_map(Map::keys) {
+    if !columns.contains(&&key[..]) {
+      columns.push(key);
+    }
+  }
+  // Leere Tabellen erhalten zumindest die Schlüsselspalte
+  if columns.is_empty() {
+    columns.push("id");
+  }
+
+  let mut csv = csv::Writer::from_writer(writer);
+  csv.write_record(&columns)?;
+
+  for row in rows {
+    csv.write_record(columns.iter().map(|column| cell(row.get(*column))))?;
+  }
+
+  csv.flush()?;
+  Ok(())
+}
+
+/// Inhalt einer CSV-Zelle; fehlende Werte und `null` bleiben leer.
+fn cell(value: Option<&Value>) -> String {
+  match value {
+    None | Some(Value::Null) => String::new(),
+    Some(Value::String(s)) => s.clone(),
+    Some(other) => other.to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    fs,
+    io::{Cursor, Read},
+  };
+
+  use serde_json::Value;
+  use zip::ZipArchive;
+
+  use super::*;
+  use crate::{SyntheticParams, testing};
+
+  /// Verweise als IDs: Feld der Zeile (JSON-Pointer) und Zieltabelle
+  const UNTERRICHT_REFS: [(&str, &str); 3] = [
+    ("/lehrkraft", "lehrkraefte"),
+    ("/fach", "faecher"),
+    ("/klasse", "klassen"),
+  ];
+  const LEHRERSTUNDEN_REFS: [(&str, &str); 5] = [
+    ("/tag_stunde/tag", "tage"),
+    ("/tag_stunde/stunde", "stunden"),
+    ("/lehrkraft", "lehrkraefte"),
+    ("/klasse", "klassen"),
+    ("/fach", "faecher"),
+  ];
+
+  const TABLES: [&str; 8] = [
+    "schuldaten",
+    "faecher",
+    "klassen",
+    "lehrkraefte",
+    "tage",
+    "stunden",
+    "unterrichtseinheiten",
+    "stunden_lehrerplan",
+  ];
+
+  fn keys(table: &Value) -> Vec<&str> {
+    table.as_object().unwrap().keys().map(|k| &k[..]).collect()
+  }
+
+  fn read_csv(source: &str) -> (Vec<String>, Vec<Vec<String>>) {
+    let mut reader = csv::Reader::from_reader(source.as_bytes());
+    let header = reader.headers().unwrap().iter().map(String::from).collect();
+    let rows = reader
+      .records()
+      .map(|record| record.unwrap().iter().map(String::from).collect())
+      .collect();
+    (header, rows)
+  }
+
+  #[test]
+  fn json_round_trip() {
+    let synthetic = WilliStundenplan::synthetic(&SyntheticParams::default());
+    let mut buffer = vec![];
+    synthetic.write_json(&mut buffer).unwrap();
+    let json: Value = serde_json::from_slice(&buffer).unwrap();
+    assert_eq!(json, synthetic.to_json_value().unwrap());
+    assert_eq!(keys(&json["klassen"]).len(), 24);
+
+    let plan = testing::plan();
+    let mut buffer = vec![];
+    plan.write_json(&mut buffer).unwrap();
+    let json: Value = serde_json::from_slice(&buffer).unwrap();
+
+    assert_eq!(json, plan.to_json_value().unwrap());
+    assert_eq!(json["schema"], SCHEMA_VERSION);
+    assert_eq!(json["willi_version"], 5);
+    assert_eq!(json["schuldaten"]["schulname"], "Testschule");
+    assert_eq!(keys(&json["klassen"]), ["1", "3"]);
+    assert_eq!(keys(&json["lehrkraefte"]), ["1", "2", "4"]);
+    assert_eq!(keys(&json["stunden_lehrerplan"]), ["0", "1", "2", "3"]);
+    assert_eq!(json["klassen"]["3"]["kuerzel"], "6b");
+    assert_eq!(json["stunden_lehrerplan"]["2"]["tag_stunde"]["tag"], 2);
+  }
+
+  /// Prüft, dass alle Verweise auf vorhandene Zeilen zeigen, und liefert ihre Anzahl.
+  fn check_references(json: &Value) -> usize {
+    let mut resolved = 0;
+
+    for (table, refs) in [
+      ("unterrichtseinheiten", &UNTERRICHT_REFS[..]),
+      ("stunden_lehrerplan", &LEHRERSTUNDEN_REFS[..]),
+    ] {
+      for row in json[table].as_object().unwrap().values() {
+        for &(pointer, target) in refs {
+          let id = row.pointer(pointer).and_then(Value::as_u64);
+          let id = id.unwrap_or_else(|| panic!("{table}{pointer}: keine ID in {row}"));
+          assert!(
+            json[target][id.to_string()].is_object(),
+            "{table}{pointer}: {target} {id} fehlt"
+          );
+          resolved += 1;
+        }
+      }
+    }
+
+    resolved
+  }
+
+  #[test]
+  fn references_resolve() {
+    let json = testing::plan().to_json_value().unwrap();
+    assert_eq!(check_references(&json), 3 * 3 + 4 * 5);
+
+    // PL4: „Mo 1“, WEB, 6b, M
+    let row = &json["stunden_lehrerplan"]["3"];
+    assert_eq!(
+      json["tage"][row["tag_stunde"]["tag"].to_string()]["kurz"],
+      "Mo"
+    );
+    assert_eq!(
+      json["stunden"][row["tag_stunde"]["stunde"].to_string()]["kurz"],
+      "1"
+    );
+    assert_eq!(
+      json["lehrkraefte"][row["lehrkraft"].to_string()]["kuerzel"],
+      "WEB"
+    );
+    assert_eq!(json["klassen"][row["klasse"].to_string()]["kuerzel"], "6b");
+    assert_eq!(json["faecher"][row["fach"].to_string()]["kuerzel"], "M");
+    assert_eq!(json["unterrichtseinheiten"]["3"]["lehrkraft"], 4);
+  }
+
+  #[test]
+  fn synthetic_references_resolve() {
+    let plan = WilliStundenplan::synthetic(&SyntheticParams::default());
+    let json = plan.to_json_value().unwrap();
+
+    let einheiten = plan.unterrichtseinheiten.iter().count();
+    assert!(einheiten > 0);
+    assert_eq!(
+      check_references(&json),
+      3 * einheiten + 5 * plan.stunden_lehrerplan.len()
+    );
+  }
+
+  #[test]
+  fn csv_round_trip() {
+    for (idx, plan) in [
+      testing::plan(),
+      WilliStundenplan::synthetic(&SyntheticParams::default()),
+    ]
+    .iter()
+    .enumerate()
+    {
+      csv_round_trip_for(plan, &format!("willi-export-{}-{idx}", std::process::id()));
+    }
+  }
+
+  fn csv_round_trip_for(plan: &WilliStundenplan, dir_name: &str) {
+    let dir = std::env::temp_dir().join(dir_name);
+    plan.write_csv_dir(&dir).unwrap();
+    let archive = plan.write_csv_zip(Cursor::new(vec![])).unwrap();
+    let mut archive = ZipArchive::new(archive).unwrap();
+
+    let names: Vec<_> = (0..archive.len())
+      .map(|idx| archive.name_for_index(idx).unwrap().to_string())
+      .collect();
+    let expected: Vec<_> = TABLES.iter().map(|t| format!("{t}.csv")).collect();
+    assert_eq!(names, expected);
+
+    for (name, rows) in plan.csv_tables().unwrap() {
+      let from_dir = fs::read_to_string(dir.join(format!("{name}.csv"))).unwrap();
+      let mut from_zip = String::new();
+      archive
+        .by_name(&format!("{name}.csv"))
+        .unwrap()
+        .read_to_string(&mut from_zip)
+        .unwrap();
+      assert_eq!(from_dir, from_zip, "{name}.csv");
+
+      // Jede Zelle entspricht dem Wert der exportierten Zeile
+      let (header, records) = read_csv(&from_dir);
+      assert_eq!(header[0], "id", "{name}.csv");
+      assert_eq!(records.len(), rows.len(), "{name}.csv");
+      for (record, row) in records.iter().zip(&rows) {
+        assert_eq!(record.len(), header.len());
+        for (column, value) in header.iter().zip(record) {
+          assert_eq!(*value, cell(row.get(column)), "{name}.csv, Spalte {column}");
+        }
+      }
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn csv_layout_is_stable() {
+    let plan = testing::plan();
+    let tables = plan.csv_tables().unwrap();
+    let names: Vec<_> = tables.iter().map(|(name, _)| &name[..]).collect();
+    assert_eq!(names, TABLES);
+
+    let mut buffer = vec![];
+    let (_, rows) = &tables[7];
+    write_csv(&mut buffer, rows).unwrap();
+    let (header, records) = read_csv(std::str::from_utf8(&buffer).unwrap());
+
+    assert_eq!(
+      header,
+      [
+        "id",
+        "tag_stunde.tag",
+        "tag_stunde.stunde",
+        "lehrkraft",
+        "klasse",
+        "fach",
+        "raum",
+        "fixierung",
+      ]
+    );
+    assert_eq!(
+      records.iter().map(|r| &r[0][..]).collect::<Vec<_>>(),
+      ["0", "1", "2", "3"]
+    );
+    assert_eq!(records[0], ["0", "1", "1", "1", "1", "1", "", ""]);
+    assert_eq!(records[2], ["2", "2", "3", "1", "1", "1", "", ""]);
+
+    // Ein zweiter Export liefert byteweise dieselben Dateien
+    for ((name, rows), (_, again)) in tables.iter().zip(plan.csv_tables().unwrap()) {
+      let (mut first, mut second) = (vec![], vec![]);
+      write_csv(&mut first, rows).unwrap();
+      write_csv(&mut second, &again).unwrap();
+      assert_eq!(first, second, "{name}.csv");
+    }
+  }
+}
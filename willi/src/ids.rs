@@ -0,0 +1,184 @@
+//! Typisierte IDs für die Tabellen eines [`WilliStundenplan`].
+//!
+//! In der BAL-Datei steht die ID als Präfix mit Nummer (`K12`), Verweise zwischen Tabellen laufen
+//! dagegen über Kürzel. Die Verweise von U- und PL-Zeilen auf Lehrkräfte, Fächer, Klassen, Tage
+//! und Stunden werden beim Einlesen zu IDs aufgelöst. Die Newtypes sorgen dafür, dass z.B. eine
+//! Klassen-ID nicht versehentlich als Stundenindex verwendet wird; aufgelöst werden sie über die
+//! Methoden auf [`WilliStundenplan`].
+
+use std::fmt;
+
+use serde::{
+  Deserialize, Deserializer, Serialize,
+  de::{self, Visitor},
+};
+
+use crate::{FachZeile, KlassenZeile, LehrkraftZeile, StundenZeile, TagZeile, WilliStundenplan};
+
+macro_rules! id_type {
+  ($(#[$meta:meta])* $name:ident, $prefix:literal) => {
+    $(#[$meta])*
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+    #[serde(transparent)]
+    pub struct $name(pub usize);
+
+    impl $name {
+      /// Präfix der Zeilenart in der BAL-Datei
+      pub const PREFIX: &'static str = $prefix;
+    }
+
+    impl fmt::Display for $name {
+      fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", $prefix, self.0)
+      }
+    }
+
+    impl From<$name> for usize {
+      fn from(id: $name) -> usize {
+        id.0
+      }
+    }
+
+    /// Akzeptiert sowohl die Zahl als auch die Schreibweise der BAL-Datei (`K12`)
+    impl<'de> Deserialize<'de> for $name {
+      fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IdVisitor;
+
+        impl Visitor<'_> for IdVisitor {
+          type Value = $name;
+
+          fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "eine ID der Form {}<nummer>", $prefix)
+          }
+
+          fn visit_u64<E: de::Error>(self, v: u64) -> Result<$name, E> {
+            usize::try_from(v).map($name).map_err(E::custom)
+          }
+
+          fn visit_i64<E: de::Error>(self, v: i64) -> Result<$name, E> {
+            usize::try_from(v).map($name).map_err(E::custom)
+          }
+
+          fn visit_f64<E: de::Error>(self, v: f64) -> Result<$name, E> {
+            if v >= 0.0 && v.fract() == 0.0 {
+              Ok($name(v as usize))
+            } else {
+              Err(E::invalid_value(de::Unexpected::Float(v), &self))
+            }
+          }
+
+          fn visit_str<E: de::Error>(self, v: &str) -> Result<$name, E> {
+            v.strip_prefix($prefix)
+              .unwrap_or(v)
+              .parse()
+              .map($name)
+              .map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+          }
+        }
+
+        deserializer.deserialize_any(IdVisitor)
+      }
+    }
+  };
+}
+
+id_type!(
+  /// ID einer K-Zeile
+  KlasseId,
+  "K"
+);
+id_type!(
+  /// ID einer F-Zeile
+  FachId,
+  "F"
+);
+id_type!(
+  /// ID einer L-Zeile
+  LehrkraftId,
+  "L"
+);
+id_type!(
+  /// ID einer U-Zeile
+  UnterrichtId,
+  "U"
+);
+id_type!(
+  /// ID einer T-Zeile
+  TagId,
+  "T"
+);
+id_type!(
+  /// ID einer S-Zeile
+  StundeId,
+  "S"
+);
+
+/// Raumkürzel. Solange R-Zeilen nicht geparst werden, gibt es keine numerischen Raum-IDs; Räume
+/// werden in K-, U- und PL-Zeilen ohnehin nur über ihr Kürzel referenziert.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RaumId(pub String);
+
+impl fmt::Display for RaumId {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.0)
+  }
+}
+
+impl From<&str> for RaumId {
+  fn from(kuerzel: &str) -> Self {
+    RaumId(kuerzel.to_string())
+  }
+}
+
+impl WilliStundenplan {
+  pub fn klasse(&self, id: KlasseId) -> Option<&KlassenZeile> {
+    self.klassen.get(id.0)
+  }
+
+  pub fn klasse_id(&self, kuerzel: &str) -> Option<KlasseId> {
+    self
+      .klassen
+      .iter()
+      .find(|(_, k)| k.kuerzel == kuerzel)
+      .map(|(id, _)| KlasseId(id))
+  }
+
+  pub fn fach(&self, id: FachId) -> Option<&FachZeile> {
+    self.faecher.get(id.0)
+  }
+
+  pub fn fach_id(&self, kuerzel: &str) -> Option<FachId> {
+    self
+      .faecher
+      .iter()
+      .find(|(_, f)| f.kuerzel == kuerzel)
+      .map(|(id, _)| FachId(id))
+  }
+
+  pub fn lehrkraft(&self, id: LehrkraftId) -> Option<&LehrkraftZeile> {
+    self.lehrkraefte.get(id.0)
+  }
+
+  pub fn lehrkraft_id(&self, kuerzel: &str) -> Option<LehrkraftId> {
+    self
+      .lehrkraefte
+      .iter()
+      .find(|(_, l)| l.kuerzel == kuerzel)
+      .map(|(id, _)| LehrkraftId(id))
+  }
+
+  pub fn tag(&self, id: TagId) -> Option<&TagZeile> {
+    self.tage.get(id.0)
+  }
+
+  pub fn stunde(&self, id: StundeId) -> Option<&StundenZeile> {
+    self.stunden.get(id.0)
+  }
+
+  /// Die Stunden in der Reihenfolge des Tagesrasters. Die `n`-te Stunde entspricht dem `n`-ten
+  /// Zeichen in [`TagZeile::stundenmerkmale`].
+  pub fn stundenraster(&self) -> impl Iterator<Item = StundeId> {
+    self.stunden.keys().map(StundeId)
+  }
+}
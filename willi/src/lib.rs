@@ -1,13 +1,35 @@
-use std::{collections::HashMap, io::Cursor, str::FromStr};
+use std::{
+  collections::HashMap,
+  fmt,
+  io::Cursor,
+  marker::PhantomData,
+  ops::{Index, IndexMut},
+  str::FromStr,
+};
 
 use csv::{Position, ReaderBuilder, StringRecord};
 use js_sys::{Object, Reflect};
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::{
+  Deserialize, Deserializer, Serialize, Serializer,
+  de::{self, IntoDeserializer, MapAccess, Visitor},
+};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use thiserror::Error;
-use tracing::{debug, error, warn};
+use tracing::warn;
 use wasm_bindgen::prelude::*;
 
+pub mod anonymize;
+pub mod export;
+pub mod ids;
+pub mod synthetic;
+#[cfg(test)]
+mod testing;
+
+pub use anonymize::Anonymisierung;
+pub use export::ExportError;
+pub use ids::{FachId, KlasseId, LehrkraftId, RaumId, StundeId, TagId, UnterrichtId};
+pub use synthetic::{SyntheticFach, SyntheticParams};
+
 #[derive(Debug, Error)]
 pub enum ParseError {
   #[error("Ungültige Kopfzeile — Keine Versionsdaten verfügbar.")]
@@ -18,6 +40,8 @@ pub enum ParseError {
   MissingType,
   #[error("Ungültige Datenzeile — {0}\n\tZeile: {1:?}")]
   BadLine(csv::Error, Option<csv::StringRecord>),
+  #[error("Unbekannter Verweis — {0} „{1}“ ist nicht vorhanden\n\tZeile: {2:?}")]
+  UnknownReference(&'static str, String, csv::StringRecord),
 }
 
 #[wasm_bindgen]
@@ -74,6 +98,26 @@ impl WilliStundenplan {
       .flexible(true)
       .from_reader(Cursor::new(body));
 
+    // So that each invocation can have a different target type. Needs to be a macro due to continue.
+    macro_rules! deserialize {
+      ($rec:ident) => {
+        match $rec.deserialize(None) {
+          Ok(record) => record,
+          Err(err) => {
+            errors.push((
+              err.position().or($rec.position()).cloned(),
+              ParseError::BadLine(err, Some($rec)),
+            ));
+            continue;
+          }
+        }
+      };
+    }
+
+    // U- und PL-Zeilen verweisen über Kürzel auf Lehrkräfte, Fächer und Klassen, die auch erst
+    // später in der Datei stehen dürfen. Sie werden daher erst nach allen anderen Zeilen gelesen.
+    let mut deferred = vec![];
+
     for result in csv_reader.records() {
       let record = match result {
         Ok(rec) => rec,
@@ -105,22 +149,6 @@ impl WilliStundenplan {
           .unwrap_or((type_col.into(), 0))
       };
 
-      // So that each invocation can have a different target type. Needs to be a macro due to continue.
-      macro_rules! deserialize {
-        ($rec:ident) => {
-          match $rec.deserialize(None) {
-            Ok(record) => record,
-            Err(err) => {
-              errors.push((
-                err.position().or(record.position()).cloned(),
-                ParseError::BadLine(err, Some($rec)),
-              ));
-              continue;
-            }
-          }
-        };
-      }
-
       macro_rules! overwrite_warn {
         ($expr:expr) => {
           if let Some(old) = $expr {
@@ -134,8 +162,8 @@ impl WilliStundenplan {
           plan.schuldaten = Some(deserialize!(record));
         }
         ("F", id) => overwrite_warn!(plan.faecher.insert(id, deserialize!(record))),
-        ("U", id) => overwrite_warn!(plan.unterrichtseinheiten.insert(id, deserialize!(record))),
-        ("PL", _) => plan.stunden_lehrerplan.push(deserialize!(record)),
+        ("U", id) => deferred.push((Some(id), record)),
+        ("PL", _) => deferred.push((None, record)),
         ("K", id) => overwrite_warn!(plan.klassen.insert(id, deserialize!(record))),
         ("T", id) => overwrite_warn!(plan.tage.insert(id, deserialize!(record))),
         ("S", id) => overwrite_warn!(plan.stunden.insert(id, deserialize!(record))),
@@ -144,6 +172,56 @@ impl WilliStundenplan {
       }
     }
 
+    let lehrkraefte = index_by(&plan.lehrkraefte, |l| &l.kuerzel);
+    let faecher = index_by(&plan.faecher, |f| &f.kuerzel);
+    let klassen = index_by(&plan.klassen, |k| &k.kuerzel);
+    let tage = index_by(&plan.tage, |t| &t.kurz);
+    let stunden = index_by(&plan.stunden, |s| &s.kurz);
+
+    for (id, record) in deferred {
+      // U: ID, Lehrkraft, Fach, Klasse, …; PL: "PL", Tag/Stunde, Lehrkraft, Klasse, Fach, …
+      let resolved = match id {
+        Some(_) => resolve_refs(
+          &record,
+          1,
+          &[
+            &[("Lehrkraft", &lehrkraefte)],
+            &[("Fach", &faecher)],
+            &[("Klasse", &klassen)],
+          ],
+        ),
+        None => resolve_refs(
+          &record,
+          1,
+          &[
+            &[("Tag", &tage), ("Stunde", &stunden)],
+            &[("Lehrkraft", &lehrkraefte)],
+            &[("Klasse", &klassen)],
+            &[("Fach", &faecher)],
+          ],
+        )
+        // PL-Zeilen haben keine eigene ID und werden über ihren Index angesprochen
+        .map(|resolved| resolved.iter().skip(1).collect()),
+      };
+      let mut resolved: StringRecord = match resolved {
+        Ok(resolved) => resolved,
+        Err(err) => {
+          errors.push((record.position().cloned(), err));
+          continue;
+        }
+      };
+      resolved.set_position(record.position().cloned());
+
+      match id {
+        Some(id) => {
+          if let Some(old) = plan.unterrichtseinheiten.insert(id, deserialize!(resolved)) {
+            warn!("Record {id} was overwritten! Old record: {:?}", old)
+          }
+        }
+        None => plan.stunden_lehrerplan.push(deserialize!(resolved)),
+      }
+    }
+
     (plan, errors)
   }
 
@@ -159,6 +237,10 @@ impl WilliStundenplan {
     &self.stunden_lehrerplan
   }
 
+  pub fn unterrichtseinheiten(&self) -> &SparseVec<UnterrichtsZeile> {
+    &self.unterrichtseinheiten
+  }
+
   pub fn stunden(&self) -> &SparseVec<StundenZeile> {
     &self.stunden
   }
@@ -193,6 +275,15 @@ impl WilliStundenplan {
     to_js_object!(self.faecher.iter())
   }
 
+  #[wasm_bindgen(
+    getter,
+    js_name = klassen,
+    unchecked_return_type = "{[id:string]:KlassenZeile}"
+  )]
+  pub fn js_klassen(&self) -> JsValue {
+    to_js_object!(self.klassen.iter())
+  }
+
   #[wasm_bindgen(getter, unchecked_return_type = "{[id:string]:UnterrichtsZeile}")]
   pub fn unterrichte(&self) -> JsValue {
     to_js_object!(self.unterrichtseinheiten.iter())
@@ -208,7 +299,10 @@ impl WilliStundenplan {
   }
 
   pub fn to_js(&self) -> JsValue {
-    serde_wasm_bindgen::to_value(self).unwrap()
+    // Tabellen als einfache Objekte statt als ES-`Map`
+    self
+      .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+      .unwrap()
   }
 
   pub fn from_js(value: JsValue) -> Self {
@@ -280,7 +374,7 @@ impl FromStr for WilliHeader {
   }
 }
 
-//// WILLI TABLES ////
+// ==== WILLI TABLES ====
 
 // W-Zeile
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -303,8 +397,8 @@ pub struct SchuldatenZeile {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[wasm_bindgen]
 pub struct TagZeile {
-  #[allow(dead_code)]
-  id: String,
+  #[wasm_bindgen(skip)]
+  pub id: TagId,
   #[wasm_bindgen(getter_with_clone)]
   pub kurz: String,
   #[wasm_bindgen(getter_with_clone)]
@@ -324,31 +418,42 @@ pub struct TagZeile {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[wasm_bindgen]
 pub struct StundenZeile {
-  #[allow(dead_code)]
-  id: String,
+  #[wasm_bindgen(skip)]
+  pub id: StundeId,
   #[wasm_bindgen(getter_with_clone)]
   pub kurz: String,
   #[wasm_bindgen(getter_with_clone)]
   pub lang: String,
-  // TODO: Parse field
+  /// Uhrzeit, z.B. „07:55“, siehe [`StundenZeile::beginn_minuten`]
   #[wasm_bindgen(getter_with_clone)]
   pub von: String,
-  // TODO: Parse field
   #[wasm_bindgen(getter_with_clone)]
   pub bis: String,
 }
 
+impl StundenZeile {
+  /// Beginn der Stunde in Minuten nach Mitternacht, siehe [`uhrzeit_minuten`]
+  pub fn beginn_minuten(&self) -> Option<u32> {
+    uhrzeit_minuten(&self.von)
+  }
+
+  /// Ende der Stunde in Minuten nach Mitternacht, siehe [`uhrzeit_minuten`]
+  pub fn ende_minuten(&self) -> Option<u32> {
+    uhrzeit_minuten(&self.bis)
+  }
+}
+
 // TODO: TRnnzz
 // TODO: Qnn
 // TODO: MP
 // TODO: Enn
 
 // L-Zeile
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[wasm_bindgen]
 pub struct LehrkraftZeile {
-  #[allow(dead_code)]
-  id: String,
+  #[wasm_bindgen(skip)]
+  pub id: LehrkraftId,
   #[wasm_bindgen(getter_with_clone)]
   pub kuerzel: String,
   #[serde(default)]
@@ -402,11 +507,11 @@ pub struct LehrkraftZeile {
 // TODO: Gnn
 
 // F-Zeile
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[wasm_bindgen]
 pub struct FachZeile {
-  #[allow(dead_code)]
-  id: String,
+  #[wasm_bindgen(skip)]
+  pub id: FachId,
   #[wasm_bindgen(getter_with_clone)]
   pub kuerzel: String,
   #[serde(default)]
@@ -488,11 +593,11 @@ pub enum Wertung {
 
 // TODO: Knn
 // K-Zeile
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[wasm_bindgen]
 pub struct KlassenZeile {
-  #[allow(dead_code)]
-  id: String,
+  #[wasm_bindgen(skip)]
+  pub id: KlasseId,
   #[wasm_bindgen(getter_with_clone)]
   pub kuerzel: String,
   #[wasm_bindgen(getter_with_clone)]
@@ -552,20 +657,20 @@ pub struct KlassenZeile {
 // TODO: Jnn
 
 // U-Zeile
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[wasm_bindgen]
 pub struct UnterrichtsZeile {
-  #[allow(dead_code)]
-  id: String,
-  /// Kuerzel der Lehrkraft
-  #[wasm_bindgen(getter_with_clone)]
-  pub lehrkraft: String,
-  /// Kuerzel des Fachs
-  #[wasm_bindgen(getter_with_clone)]
-  pub fach: String,
-  /// Kuerzel der Klasse
-  #[wasm_bindgen(getter_with_clone)]
-  pub klasse: String,
+  #[wasm_bindgen(skip)]
+  pub id: UnterrichtId,
+  /// Lehrkraft, in der Datei über ihr Kürzel angegeben
+  #[wasm_bindgen(skip)]
+  pub lehrkraft: LehrkraftId,
+  /// Fach, in der Datei über sein Kürzel angegeben
+  #[wasm_bindgen(skip)]
+  pub fach: FachId,
+  /// Klasse, in der Datei über ihr Kürzel angegeben
+  #[wasm_bindgen(skip)]
+  pub klasse: KlasseId,
   #[wasm_bindgen(getter_with_clone)]
   #[serde(default)]
   pub kopplung: Option<String>,
@@ -595,9 +700,9 @@ pub struct UnterrichtsZeile {
   #[serde(default)]
   pub fachraumgruppe: Option<usize>,
   /// Kuerzel des fest vorgesehenen Raums
-  #[wasm_bindgen(getter_with_clone)]
+  #[wasm_bindgen(skip)]
   #[serde(default)]
-  pub raum: Option<String>,
+  pub raum: Option<RaumId>,
   /// Minimale anzahl Doppelstnden
   #[serde(default)]
   pub doppmin: Option<usize>,
@@ -646,6 +751,30 @@ pub struct UnterrichtsZeile {
   pub asv_bereich: Option<String>,
 }
 
+/// Verweise als numerische IDs für JavaScript, aufzulösen über die Tabellen des Plans
+#[wasm_bindgen]
+impl UnterrichtsZeile {
+  #[wasm_bindgen(getter)]
+  pub fn lehrkraft(&self) -> usize {
+    self.lehrkraft.0
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn fach(&self) -> usize {
+    self.fach.0
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn klasse(&self) -> usize {
+    self.klasse.0
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn raum(&self) -> Option<String> {
+    self.raum.as_ref().map(|r| r.0.clone())
+  }
+}
+
 // TODO: Bnn
 // TODO: VLnn
 // TODO: VSnn
@@ -654,48 +783,97 @@ pub struct UnterrichtsZeile {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[wasm_bindgen]
 pub struct LehrerStundenZeile {
-  #[allow(dead_code)]
-  id: String,
   #[wasm_bindgen(getter_with_clone)]
   pub tag_stunde: TagStunde,
-  #[wasm_bindgen(getter_with_clone)]
-  pub lehrkraft: String,
-  #[wasm_bindgen(getter_with_clone)]
-  pub klasse: String,
-  #[wasm_bindgen(getter_with_clone)]
-  pub fach: String,
+  #[wasm_bindgen(skip)]
+  pub lehrkraft: LehrkraftId,
+  #[wasm_bindgen(skip)]
+  pub klasse: KlasseId,
+  #[wasm_bindgen(skip)]
+  pub fach: FachId,
   #[serde(default)]
-  #[wasm_bindgen(getter_with_clone)]
-  pub raum: Option<String>,
+  #[wasm_bindgen(skip)]
+  pub raum: Option<RaumId>,
   #[serde(default)]
   #[wasm_bindgen(getter_with_clone)]
   pub fixierung: Option<String>,
 }
 
-#[derive(Clone, Debug, Serialize)]
+/// Verweise als numerische IDs für JavaScript, aufzulösen über die Tabellen des Plans
+#[wasm_bindgen]
+impl LehrerStundenZeile {
+  #[wasm_bindgen(getter)]
+  pub fn lehrkraft(&self) -> usize {
+    self.lehrkraft.0
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn klasse(&self) -> usize {
+    self.klasse.0
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn fach(&self) -> usize {
+    self.fach.0
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn raum(&self) -> Option<String> {
+    self.raum.as_ref().map(|r| r.0.clone())
+  }
+}
+
+/// Lage einer PL-Zeile, in der Datei als Kurznamen von Tag und Stunde angegeben (z.B. „Mo 3“)
+#[derive(Clone, Copy, Debug, Serialize)]
 #[wasm_bindgen]
 pub struct TagStunde {
-  #[wasm_bindgen(getter_with_clone)]
-  pub tag: String,
-  #[wasm_bindgen(getter_with_clone)]
-  pub stunde: String,
+  #[wasm_bindgen(skip)]
+  pub tag: TagId,
+  #[wasm_bindgen(skip)]
+  pub stunde: StundeId,
 }
 
+/// Liest die beim Einlesen aufgelöste Form „<Tag-ID> <Stunden-ID>“ sowie die serialisierte Form
+/// `{ tag, stunde }`, damit z.B. [`WilliStundenplan::to_js`] wieder eingelesen werden kann
 impl<'de> Deserialize<'de> for TagStunde {
   fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
   where
     D: serde::Deserializer<'de>,
   {
-    let string = String::deserialize(deserializer)?;
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+      Text(String),
+      Ids { tag: TagId, stunde: StundeId },
+    }
+
+    let string = match Raw::deserialize(deserializer)? {
+      Raw::Text(string) => string,
+      Raw::Ids { tag, stunde } => return Ok(TagStunde { tag, stunde }),
+    };
 
-    let Some((tag, stunde)) = string
-      .split_once(" ")
-      .map(|(t, s)| (t.to_string(), s.to_string()))
-    else {
+    let Some((tag, stunde)) = string.split_once(" ") else {
       return Err(serde::de::Error::custom("Ungültige Stundenzuordnung"));
     };
 
-    Ok(TagStunde { tag, stunde })
+    Ok(TagStunde {
+      tag: TagId::deserialize(tag.into_deserializer())?,
+      stunde: StundeId::deserialize(stunde.into_deserializer())?,
+    })
+  }
+}
+
+/// IDs für JavaScript, aufzulösen über die Tabellen des Plans
+#[wasm_bindgen]
+impl TagStunde {
+  #[wasm_bindgen(getter)]
+  pub fn tag(&self) -> usize {
+    self.tag.0
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn stunde(&self) -> usize {
+    self.stunde.0
   }
 }
 
@@ -705,50 +883,333 @@ impl<'de> Deserialize<'de> for TagStunde {
 // TODO: Dnn
 // TODO: MK
 
-//// UTILITY DATA STRUCTURES ////
+// ==== UTILITY DATA STRUCTURES ====
 
-// TODO: Option<Box<T>> to curb memory usage?
-// TODO: Custom debug impl that collapses holes into e.g. <4 empty>
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct SparseVec<T>(Vec<Option<T>>);
+/// Tabelle mit numerischen IDs, die Lücken enthalten darf.
+///
+/// Einträge werden geboxt, damit Lücken nur einen Zeiger groß sind. Serialisiert wird als Map
+/// `{id: eintrag}`, nicht als mit `null` aufgefülltes Array.
+#[derive(Clone)]
+pub struct SparseVec<T> {
+  items: Vec<Option<Box<T>>>,
+  len: usize,
+}
 
 impl<T> Default for SparseVec<T> {
   fn default() -> Self {
-    SparseVec(vec![])
+    SparseVec {
+      items: vec![],
+      len: 0,
+    }
   }
 }
 
 impl<T> SparseVec<T> {
   pub fn insert(&mut self, idx: usize, val: T) -> Option<T> {
-    if self.0.len() > idx {
-      return self.0[idx].replace(val);
+    if self.items.len() > idx {
+      let old = self.items[idx].replace(Box::new(val)).map(|old| *old);
+      if old.is_none() {
+        self.len += 1;
+      }
+      return old;
+    }
+
+    while self.items.len() < idx {
+      self.items.push(None);
     }
 
-    while self.0.len() < idx {
-      self.0.push(None);
+    self.items.push(Some(Box::new(val)));
+    self.len += 1;
+    None
+  }
+
+  pub fn remove(&mut self, idx: usize) -> Option<T> {
+    let old = self.items.get_mut(idx)?.take().map(|old| *old);
+
+    if old.is_some() {
+      self.len -= 1;
+      // Lücken am Ende abschneiden
+      while let Some(None) = self.items.last() {
+        self.items.pop();
+      }
     }
 
-    self.0.push(Some(val));
-    return None;
+    old
   }
 
   pub fn get(&self, idx: usize) -> Option<&T> {
-    self.0.get(idx).and_then(|x| x.as_ref())
+    self.items.get(idx).and_then(|x| x.as_deref())
   }
 
   pub fn get_mut(&mut self, idx: usize) -> Option<&mut T> {
-    self.0.get_mut(idx).and_then(|x| x.as_mut())
+    self.items.get_mut(idx).and_then(|x| x.as_deref_mut())
+  }
+
+  pub fn contains_key(&self, idx: usize) -> bool {
+    self.get(idx).is_some()
+  }
+
+  /// Anzahl der vorhandenen Einträge (ohne Lücken)
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
   }
 
   pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
     self
-      .0
+      .items
       .iter()
       .enumerate()
-      .filter_map(|(id, x)| x.as_ref().map(|x| (id, x)))
+      .filter_map(|(id, x)| x.as_deref().map(|x| (id, x)))
+  }
+
+  pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut T)> {
+    self
+      .items
+      .iter_mut()
+      .enumerate()
+      .filter_map(|(id, x)| x.as_deref_mut().map(|x| (id, x)))
+  }
+
+  pub fn keys(&self) -> impl Iterator<Item = usize> {
+    self.iter().map(|(id, _)| id)
+  }
+
+  pub fn values(&self) -> impl Iterator<Item = &T> {
+    self.iter().map(|(_, x)| x)
+  }
+
+  pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+    self.iter_mut().map(|(_, x)| x)
+  }
+}
+
+impl<T> Index<usize> for SparseVec<T> {
+  type Output = T;
+
+  fn index(&self, idx: usize) -> &T {
+    self
+      .get(idx)
+      .unwrap_or_else(|| panic!("Kein Eintrag mit ID {idx}"))
+  }
+}
+
+impl<T> IndexMut<usize> for SparseVec<T> {
+  fn index_mut(&mut self, idx: usize) -> &mut T {
+    self
+      .get_mut(idx)
+      .unwrap_or_else(|| panic!("Kein Eintrag mit ID {idx}"))
+  }
+}
+
+impl<T> FromIterator<(usize, T)> for SparseVec<T> {
+  fn from_iter<I: IntoIterator<Item = (usize, T)>>(iter: I) -> Self {
+    let mut result = SparseVec::default();
+    for (idx, val) in iter {
+      result.insert(idx, val);
+    }
+    result
+  }
+}
+
+impl<T> IntoIterator for SparseVec<T> {
+  type Item = (usize, T);
+  type IntoIter = std::iter::FilterMap<
+    std::iter::Enumerate<std::vec::IntoIter<Option<Box<T>>>>,
+    fn((usize, Option<Box<T>>)) -> Option<(usize, T)>,
+  >;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self
+      .items
+      .into_iter()
+      .enumerate()
+      .filter_map(|(id, x)| x.map(|x| (id, *x)))
+  }
+}
+
+impl<'a, T> IntoIterator for &'a SparseVec<T> {
+  type Item = (usize, &'a T);
+  type IntoIter = Box<dyn Iterator<Item = (usize, &'a T)> + 'a>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    Box::new(self.iter())
   }
 }
 
+impl<'a, T> IntoIterator for &'a mut SparseVec<T> {
+  type Item = (usize, &'a mut T);
+  type IntoIter = Box<dyn Iterator<Item = (usize, &'a mut T)> + 'a>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    Box::new(self.iter_mut())
+  }
+}
+
+/// Fasst Lücken zusammen, z.B. `[1: …, 2: …, <4 leer>, 7: …]`
+impl<T: fmt::Debug> fmt::Debug for SparseVec<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    enum Entry<'a, T> {
+      Hole(usize),
+      Item(usize, &'a T),
+    }
+
+    impl<T: fmt::Debug> fmt::Debug for Entry<'_, T> {
+      fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+          Entry::Hole(n) => write!(f, "<{n} leer>"),
+          Entry::Item(id, val) => {
+            write!(f, "{id}: ")?;
+            val.fmt(f)
+          }
+        }
+      }
+    }
+
+    let mut list = f.debug_list();
+    let mut holes = 0;
+
+    for (id, item) in self.items.iter().enumerate() {
+      match item {
+        None => holes += 1,
+        Some(val) => {
+          if holes > 0 {
+            list.entry(&Entry::<T>::Hole(holes));
+            holes = 0;
+          }
+          list.entry(&Entry::Item(id, &**val));
+        }
+      }
+    }
+
+    list.finish()
+  }
+}
+
+impl<T: Serialize> Serialize for SparseVec<T> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(self.iter())
+  }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for SparseVec<T> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    /// IDs als Zahl oder, z.B. bei JSON-Objekten, als String
+    struct Key(usize);
+
+    impl<'de> Deserialize<'de> for Key {
+      fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeyVisitor;
+
+        impl Visitor<'_> for KeyVisitor {
+          type Value = Key;
+
+          fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("eine numerische ID")
+          }
+
+          fn visit_u64<E: de::Error>(self, v: u64) -> Result<Key, E> {
+            usize::try_from(v).map(Key).map_err(E::custom)
+          }
+
+          fn visit_i64<E: de::Error>(self, v: i64) -> Result<Key, E> {
+            usize::try_from(v).map(Key).map_err(E::custom)
+          }
+
+          fn visit_f64<E: de::Error>(self, v: f64) -> Result<Key, E> {
+            if v >= 0.0 && v.fract() == 0.0 {
+              Ok(Key(v as usize))
+            } else {
+              Err(E::invalid_value(de::Unexpected::Float(v), &self))
+            }
+          }
+
+          fn visit_str<E: de::Error>(self, v: &str) -> Result<Key, E> {
+            v.parse().map(Key).map_err(E::custom)
+          }
+        }
+
+        deserializer.deserialize_any(KeyVisitor)
+      }
+    }
+
+    struct SparseVecVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>> Visitor<'de> for SparseVecVisitor<T> {
+      type Value = SparseVec<T>;
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("eine Map von IDs auf Einträge")
+      }
+
+      fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut result = SparseVec::default();
+        while let Some((Key(id), val)) = map.next_entry()? {
+          result.insert(id, val);
+        }
+        Ok(result)
+      }
+    }
+
+    deserializer.deserialize_map(SparseVecVisitor(PhantomData))
+  }
+}
+
+/// Kürzel → ID einer Tabelle
+pub(crate) fn index_by<T>(table: &SparseVec<T>, key: impl Fn(&T) -> &str) -> HashMap<&str, usize> {
+  table.iter().map(|(id, row)| (key(row), id)).collect()
+}
+
+/// Tabellen, auf die eine Spalte verweist: eine je durch Leerzeichen getrenntem Kürzel
+type ColumnRefs<'a> = &'a [(&'static str, &'a HashMap<&'a str, usize>)];
+
+/// Ersetzt die Kürzel ab Spalte `start` der Reihe nach durch die IDs der Tabellen in `columns`.
+/// Spalten mit mehreren Kürzeln (Tag/Stunde einer PL-Zeile) bleiben durch Leerzeichen getrennt.
+fn resolve_refs(
+  record: &StringRecord,
+  start: usize,
+  columns: &[ColumnRefs],
+) -> Result<StringRecord, ParseError> {
+  let mut resolved = StringRecord::with_capacity(record.as_slice().len(), record.len());
+
+  for (column, field) in record.iter().enumerate() {
+    let Some(tables) = column.checked_sub(start).and_then(|i| columns.get(i)) else {
+      resolved.push_field(field);
+      continue;
+    };
+
+    let mut kuerzel = field.splitn(tables.len(), ' ');
+    let mut ids = vec![];
+    for (table, table_ids) in tables.iter() {
+      let kuerzel = kuerzel.next().unwrap_or_default();
+      match table_ids.get(kuerzel) {
+        Some(id) => ids.push(id.to_string()),
+        None => {
+          return Err(ParseError::UnknownReference(
+            table,
+            kuerzel.to_string(),
+            record.clone(),
+          ));
+        }
+      }
+    }
+    resolved.push_field(&ids.join(" "));
+  }
+
+  Ok(resolved)
+}
+
+/// Liest eine Uhrzeit der Form „HH:MM“ (auch „H:MM“ oder „HH.MM“) als Minuten nach Mitternacht
+pub fn uhrzeit_minuten(text: &str) -> Option<u32> {
+  let (stunden, minuten) = text.trim().split_once([':', '.'])?;
+  let stunden: u32 = stunden.parse().ok()?;
+  let minuten: u32 = minuten.parse().ok()?;
+  (stunden < 24 && minuten < 60).then_some(stunden * 60 + minuten)
+}
+
 pub fn de_german_float<'de, D>(deserializer: D) -> Result<f64, D::Error>
 where
   D: Deserializer<'de>,
@@ -766,3 +1227,164 @@ where
   let s = format!("{}", value).replace('.', ",");
   serializer.serialize_str(&s)
 }
+
+#[cfg(test)]
+mod tests {
+  use serde::de::value::MapDeserializer;
+
+  use super::*;
+  use crate::testing;
+
+  #[test]
+  fn parse_resolves_ids() {
+    let plan = testing::plan();
+
+    let unterricht = &plan.unterrichtseinheiten[3];
+    assert_eq!(unterricht.id, UnterrichtId(3));
+    assert_eq!(unterricht.lehrkraft, LehrkraftId(4));
+    assert_eq!(unterricht.fach, FachId(1));
+    assert_eq!(unterricht.klasse, KlasseId(3));
+
+    // PL2: „Mo 2“, SCH, 5a, D
+    let line = &plan.stunden_lehrerplan[1];
+    assert_eq!(line.tag_stunde.tag, TagId(1));
+    assert_eq!(line.tag_stunde.stunde, StundeId(2));
+    assert_eq!(line.lehrkraft, LehrkraftId(2));
+    assert_eq!(line.klasse, KlasseId(1));
+    assert_eq!(line.fach, FachId(2));
+  }
+
+  #[test]
+  fn serde_round_trip() {
+    let plan = testing::plan();
+    let json = serde_json::to_string(&plan).unwrap();
+    let again: WilliStundenplan = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(serde_json::to_string(&again).unwrap(), json);
+    assert_eq!(again.stunden_lehrerplan[2].tag_stunde.stunde, StundeId(3));
+  }
+
+  #[test]
+  fn unknown_references_dropped() {
+    let source = [
+      testing::BAL,
+      "U4,MUE,Ph,5a,,1\r\n",
+      "PL6,Di 2,XXX,6b,M\r\n",
+      "PL7,Mi 1,MUE,5a,M\r\n",
+      "U5,MUE,M,7c,,1\r\n",
+      "PL8,Mo 9,MUE,5a,M\r\n",
+      "PL9,Mo1,MUE,5a,M\r\n",
+    ]
+    .concat();
+    let (plan, errors) = WilliStundenplan::parse(&source);
+
+    let references: Vec<_> = errors
+      .iter()
+      .map(|(position, err)| {
+        assert!(position.is_some());
+        match err {
+          ParseError::UnknownReference(table, kuerzel, _) => (*table, &kuerzel[..]),
+          other => panic!("Unerwarteter Fehler: {other}"),
+        }
+      })
+      .collect();
+    assert_eq!(
+      references,
+      [
+        ("Fach", "Ph"),
+        ("Lehrkraft", "XXX"),
+        ("Tag", "Mi"),
+        ("Klasse", "7c"),
+        ("Stunde", "9"),
+        ("Tag", "Mo1"),
+      ]
+    );
+
+    // Die fehlerhaften Zeilen fehlen, alle anderen sind unverändert
+    assert_eq!(plan.unterrichtseinheiten.len(), 3);
+    assert_eq!(plan.stunden_lehrerplan.len(), 4);
+  }
+
+  fn sparse() -> SparseVec<&'static str> {
+    [(1, "a"), (2, "b"), (7, "c")].into_iter().collect()
+  }
+
+  #[test]
+  fn sparse_vec_from_iter() {
+    let vec = sparse();
+
+    assert_eq!(vec.len(), 3);
+    assert_eq!(vec.keys().collect::<Vec<_>>(), [1, 2, 7]);
+    assert_eq!(vec.get(2), Some(&"b"));
+    assert_eq!(vec.get(3), None);
+    assert_eq!(vec[7], "c");
+
+    // Spätere Einträge überschreiben frühere mit gleicher ID
+    let vec: SparseVec<_> = [(4, "x"), (4, "y")].into_iter().collect();
+    assert_eq!(vec.len(), 1);
+    assert_eq!(vec[4], "y");
+  }
+
+  #[test]
+  fn sparse_vec_remove_trims_holes() {
+    let mut vec = sparse();
+
+    assert_eq!(vec.remove(7), Some("c"));
+    assert_eq!(vec.items.len(), 3);
+    assert_eq!(vec.remove(7), None);
+    assert_eq!(vec.remove(1), Some("a"));
+    // Lücken vor dem letzten Eintrag bleiben erhalten
+    assert_eq!(vec.items.len(), 3);
+    assert_eq!(vec.remove(2), Some("b"));
+    assert_eq!(vec.items.len(), 0);
+    assert!(vec.is_empty());
+  }
+
+  #[test]
+  #[should_panic(expected = "Kein Eintrag mit ID 4")]
+  fn sparse_vec_index_hole() {
+    let _ = sparse()[4];
+  }
+
+  #[test]
+  #[should_panic(expected = "Kein Eintrag mit ID 8")]
+  fn sparse_vec_index_past_end() {
+    let _ = sparse()[8];
+  }
+
+  #[test]
+  fn sparse_vec_debug() {
+    assert_eq!(
+      format!("{:?}", sparse()),
+      r#"[<1 leer>, 1: "a", 2: "b", <4 leer>, 7: "c"]"#
+    );
+    assert_eq!(format!("{:?}", SparseVec::<u8>::default()), "[]");
+  }
+
+  #[test]
+  fn sparse_vec_serde() {
+    let json = serde_json::to_string(&sparse()).unwrap();
+    assert_eq!(json, r#"{"1":"a","2":"b","7":"c"}"#);
+
+    let vec: SparseVec<String> = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+      vec.iter().map(|(id, v)| (id, &v[..])).collect::<Vec<_>>(),
+      [(1, "a"), (2, "b"), (7, "c")]
+    );
+
+    // Nicht dichte, unsortierte Schlüssel als Zahl (z.B. aus serde-wasm-bindgen)
+    let entries = [(12u64, "x"), (3, "y")].map(|(id, v)| (id, v.to_string()));
+    let vec = SparseVec::<String>::deserialize(MapDeserializer::<_, de::value::Error>::new(
+      entries.into_iter(),
+    ))
+    .unwrap();
+    assert_eq!(vec.keys().collect::<Vec<_>>(), [3, 12]);
+    assert_eq!(vec.len(), 2);
+
+    let vec: SparseVec<String> = serde_json::from_str(r#"{"5":"x","0":"y"}"#).unwrap();
+    assert_eq!(vec.keys().collect::<Vec<_>>(), [0, 5]);
+
+    assert!(serde_json::from_str::<SparseVec<String>>(r#"{"-1":"x"}"#).is_err());
+    assert!(serde_json::from_str::<SparseVec<String>>(r#"["x"]"#).is_err());
+  }
+}
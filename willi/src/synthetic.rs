@@ -0,0 +1,464 @@
+//! Erzeugung synthetischer WILLI2-Stundenpläne für Tests und Benchmarks.
+//!
+//! Die Pläne sind nicht optimal, aber in sich stimmig: Jede Klasse erhält ihre Fächer entsprechend
+//! der Stundentafel, jede Unterrichtseinheit eine Lehrkraft mit passender Fakultas, und keine
+//! Lehrkraft ist zur selben Stunde doppelt verplant. Eine Klasse hat nur in geteilten Fächern zwei
+//! Einträge in einer Stunde, einen je Gruppe.
+
+use rand::{Rng, SeedableRng, seq::SliceRandom};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+  FachEigenschaft, FachZeile, KlassenZeile, LehrerStundenZeile, LehrkraftZeile, SchuldatenZeile,
+  SparseVec, StundenZeile, TagStunde, TagZeile, UnterrichtsZeile, WilliStundenplan,
+  ids::{FachId, KlasseId, LehrkraftId, StundeId, TagId, UnterrichtId},
+};
+
+const TAGE: [(&str, &str); 7] = [
+  ("Mo", "Montag"),
+  ("Di", "Dienstag"),
+  ("Mi", "Mittwoch"),
+  ("Do", "Donnerstag"),
+  ("Fr", "Freitag"),
+  ("Sa", "Samstag"),
+  ("So", "Sonntag"),
+];
+
+/// Ein Fach der Stundentafel
+#[derive(Debug, Clone)]
+pub struct SyntheticFach {
+  pub kuerzel: String,
+  pub name: String,
+  /// Relativer Anteil des Fachs an den Wochenstunden einer Klasse
+  pub gewicht: f64,
+  /// Die Klasse wird in zwei Gruppen geteilt, die zur selben Zeit von zwei Lehrkräften
+  /// unterrichtet werden
+  pub geteilt: bool,
+  /// Das Fach erhält `FachEigenschaft::D`, sein Unterricht wird soweit möglich paarweise in
+  /// aufeinanderfolgende Stunden gelegt
+  pub doppelstunden: bool,
+}
+
+impl SyntheticFach {
+  pub fn new(kuerzel: &str, name: &str, gewicht: f64) -> Self {
+    SyntheticFach {
+      kuerzel: kuerzel.to_string(),
+      name: name.to_string(),
+      gewicht,
+      geteilt: false,
+      doppelstunden: false,
+    }
+  }
+}
+
+/// Parameter für [`WilliStundenplan::synthetic`].
+#[derive(Debug, Clone)]
+pub struct SyntheticParams {
+  /// Anzahl der Unterrichtstage (höchstens 7)
+  pub tage: usize,
+  /// Stunden pro Tag
+  pub stunden: usize,
+  pub klassen: usize,
+  pub lehrkraefte: usize,
+  /// Anzahl der Fächer, die jede Lehrkraft unterrichten kann
+  pub faecher_pro_lehrkraft: usize,
+  pub faecher: Vec<SyntheticFach>,
+  /// Anteil der Stunden einer Woche, in denen eine Klasse Unterricht hat (0–1)
+  pub dichte: f64,
+  /// Schultypen, die reihum auf die Klassen verteilt werden. Leer = Klassen ohne Schultyp
+  pub schultypen: Vec<String>,
+  pub seed: u64,
+}
+
+impl Default for SyntheticParams {
+  /// Ein mittelgroßes Gymnasium mit 5 Tagen à 8 Stunden, 24 Klassen und 60 Lehrkräften.
+  fn default() -> Self {
+    SyntheticParams {
+      tage: 5,
+      stunden: 8,
+      klassen: 24,
+      lehrkraefte: 60,
+      faecher_pro_lehrkraft: 2,
+      faecher: vec![
+        SyntheticFach::new("M", "Mathematik", 4.0),
+        SyntheticFach::new("D", "Deutsch", 4.0),
+        SyntheticFach::new("E", "Englisch", 4.0),
+        SyntheticFach::new("F", "Französisch", 3.0),
+        SyntheticFach::new("Ph", "Physik", 2.0),
+        SyntheticFach::new("C", "Chemie", 2.0),
+        SyntheticFach::new("B", "Biologie", 2.0),
+        SyntheticFach::new("G", "Geschichte", 2.0),
+        SyntheticFach::new("Geo", "Geographie", 2.0),
+        SyntheticFach::new("Sp", "Sport", 3.0),
+        SyntheticFach::new("Ku", "Kunst", 2.0),
+        SyntheticFach::new("Mu", "Musik", 2.0),
+      ],
+      dichte: 0.85,
+      schultypen: vec![],
+      seed: 0,
+    }
+  }
+}
+
+impl WilliStundenplan {
+  /// Erzeugt einen zufälligen, aber reproduzierbaren Stundenplan gemäß `params`.
+  pub fn synthetic(params: &SyntheticParams) -> WilliStundenplan {
+    let mut rng = ChaCha8Rng::seed_from_u64(params.seed);
+
+    let num_days = params.tage.min(TAGE.len());
+    let num_slots = num_days * params.stunden;
+
+    let mut plan = WilliStundenplan {
+      header: None,
+      schuldaten: Some(SchuldatenZeile {
+        id: "W".to_string(),
+        schulname: "Synthetische Schule".to_string(),
+        titel1: None,
+        titel2: None,
+        schulnummer: None,
+      }),
+      faecher: SparseVec::default(),
+      unterrichtseinheiten: SparseVec::default(),
+      stunden_lehrerplan: vec![],
+      klassen: SparseVec::default(),
+      tage: SparseVec::default(),
+      stunden: SparseVec::default(),
+      lehrkraefte: SparseVec::default(),
+    };
+
+    for (i, (kurz, lang)) in TAGE.iter().take(num_days).enumerate() {
+      plan.tage.insert(
+        i + 1,
+        TagZeile {
+          id: TagId(i + 1),
+          kurz: kurz.to_string(),
+          lang: lang.to_string(),
+          stundenmerkmale: "v".repeat(params.stunden),
+          pausen: String::new(),
+          stundenzeiten: None,
+        },
+      );
+    }
+
+    for i in 0..params.stunden {
+      // 45 Minuten Unterricht, 5 Minuten Pause, ab 8:00
+      let von = 8 * 60 + i * 50;
+      let bis = von + 45;
+      plan.stunden.insert(
+        i + 1,
+        StundenZeile {
+          id: StundeId(i + 1),
+          kurz: (i + 1).to_string(),
+          lang: format!("{}. Stunde", i + 1),
+          von: format!("{:02}:{:02}", von / 60, von % 60),
+          bis: format!("{:02}:{:02}", bis / 60, bis % 60),
+        },
+      );
+    }
+
+    for (i, fach) in params.faecher.iter().enumerate() {
+      plan.faecher.insert(
+        i + 1,
+        FachZeile {
+          id: FachId(i + 1),
+          kuerzel: fach.kuerzel.clone(),
+          kurz: Some(fach.kuerzel.clone()),
+          name: Some(fach.name.clone()),
+          eigenschaft: fach.doppelstunden.then_some(FachEigenschaft::D),
+          ..Default::default()
+        },
+      );
+    }
+
+    // Fakultas: Jede Lehrkraft erhält `faecher_pro_lehrkraft` Fächer. Die ersten Fächer werden
+    // reihum vergeben, damit jedes Fach mindestens eine Lehrkraft hat.
+    let mut fakultas: Vec<Vec<usize>> = vec![];
+    for i in 0..params.lehrkraefte {
+      let mut faecher = vec![i % params.faecher.len().max(1)];
+      while faecher.len() < params.faecher_pro_lehrkraft.min(params.faecher.len()) {
+        let fach = rng.gen_range(0..params.faecher.len());
+        if !faecher.contains(&fach) {
+          faecher.push(fach);
+        }
+      }
+
+      let kuerzel = format!("L{:03}", i + 1);
+      plan.lehrkraefte.insert(
+        i + 1,
+        LehrkraftZeile {
+          id: LehrkraftId(i + 1),
+          kuerzel: kuerzel.clone(),
+          kurz: Some(kuerzel),
+          name: Some(format!("Lehrkraft {}", i + 1)),
+          ..Default::default()
+        },
+      );
+      fakultas.push(faecher);
+    }
+
+    // Klassen gleichmäßig auf die Jahrgangsstufen 5 bis 13 verteilen
+    let mut klassen = vec![];
+    for i in 0..params.klassen {
+      let jahrgang = 5 + i * 9 / params.klassen.max(1);
+      let parallel = klassen.iter().filter(|(j, _)| *j == jahrgang).count();
+      let kuerzel = format!("{jahrgang}{}", parallel_suffix(parallel));
+
+      plan.klassen.insert(
+        i + 1,
+        KlassenZeile {
+          id: KlasseId(i + 1),
+          kuerzel: kuerzel.clone(),
+          kurz: Some(kuerzel.clone()),
+          jahrgangsstufe: Some(jahrgang),
+          schultyp: (!params.schultypen.is_empty())
+            .then(|| params.schultypen[i % params.schultypen.len()].clone()),
+          ..Default::default()
+        },
+      );
+      klassen.push((jahrgang, kuerzel));
+    }
+
+    // Stundentafel: Wochenstunden pro Fach, skaliert auf die gewünschte Dichte
+    let weight_sum: f64 = params.faecher.iter().map(|f| f.gewicht).sum();
+    let target = params.dichte.clamp(0.0, 1.0) * num_slots as f64;
+    let stundentafel: Vec<usize> = params
+      .faecher
+      .iter()
+      .map(|f| (f.gewicht / weight_sum * target).round() as usize)
+      .collect();
+
+    // [lehrkraft][slot] bzw. [klasse][slot] = belegt
+    let mut teacher_busy = vec![vec![false; num_slots]; params.lehrkraefte];
+    let mut teacher_load = vec![0; params.lehrkraefte];
+    let mut class_busy = vec![vec![false; num_slots]; params.klassen];
+
+    let mut unterricht_id = 1;
+    for (class_idx, _) in klassen.iter().enumerate() {
+      for (fach_idx, &stunden) in stundentafel.iter().enumerate() {
+        // Am wenigsten ausgelastete Lehrkraft mit passender Fakultas
+        let Some(lehrkraft) = (0..params.lehrkraefte)
+          .filter(|&l| fakultas[l].contains(&fach_idx))
+          .min_by_key(|&l| teacher_load[l])
+        else {
+          continue;
+        };
+        if stunden == 0 {
+          continue;
+        }
+
+        // In geteilten Fächern unterrichtet eine zweite Lehrkraft die andere Gruppe
+        let zweite = params.faecher[fach_idx]
+          .geteilt
+          .then(|| {
+            (0..params.lehrkraefte)
+              .filter(|&l| l != lehrkraft && fakultas[l].contains(&fach_idx))
+              .min_by_key(|&l| teacher_load[l])
+          })
+          .flatten();
+        let lehrkraefte: Vec<usize> = [Some(lehrkraft), zweite].into_iter().flatten().collect();
+        let fach = FachId(fach_idx + 1);
+        let klasse = KlasseId(class_idx + 1);
+
+        let mut free: Vec<usize> = (0..num_slots)
+          .filter(|&s| {
+            !class_busy[class_idx][s] && lehrkraefte.iter().all(|&l| !teacher_busy[l][s])
+          })
+          .collect();
+        free.shuffle(&mut rng);
+
+        // Doppelstunden zuerst, den Rest wie bei anderen Fächern
+        let mut slots = vec![];
+        if params.faecher[fach_idx].doppelstunden {
+          for &first in &free {
+            if slots.len() + 2 > stunden {
+              break;
+            }
+            let second = first + 1;
+            if second % params.stunden != 0
+              && free.contains(&second)
+              && !slots.contains(&first)
+              && !slots.contains(&second)
+            {
+              slots.extend([first, second]);
+            }
+          }
+        }
+        for &slot in &free {
+          if slots.len() == stunden {
+            break;
+          }
+          if !slots.contains(&slot) {
+            slots.push(slot);
+          }
+        }
+
+        // Die Stundenzahl entspricht den tatsächlich verplanten Stunden, auch wenn die Klasse oder
+        // Lehrkraft nicht mehr genügend freie Stunden hatte.
+        if slots.is_empty() {
+          continue;
+        }
+
+        for &lehrkraft in &lehrkraefte {
+          plan.unterrichtseinheiten.insert(
+            unterricht_id,
+            UnterrichtsZeile {
+              id: UnterrichtId(unterricht_id),
+              lehrkraft: LehrkraftId(lehrkraft + 1),
+              fach,
+              klasse,
+              stundenzahl: slots.len() as u8,
+              ..Default::default()
+            },
+          );
+          unterricht_id += 1;
+        }
+
+        for &slot in &slots {
+          class_busy[class_idx][slot] = true;
+          for &lehrkraft in &lehrkraefte {
+            teacher_busy[lehrkraft][slot] = true;
+            teacher_load[lehrkraft] += 1;
+
+            plan.stunden_lehrerplan.push(LehrerStundenZeile {
+              tag_stunde: TagStunde {
+                tag: TagId(slot / params.stunden + 1),
+                stunde: StundeId(slot % params.stunden + 1),
+              },
+              lehrkraft: LehrkraftId(lehrkraft + 1),
+              klasse,
+              fach,
+              raum: None,
+              fixierung: None,
+            });
+          }
+        }
+      }
+    }
+
+    plan
+  }
+}
+
+/// Kennung der `parallel`-ten Klasse (ab 0) eines Jahrgangs: `a` bis `z`, danach fortlaufende
+/// Nummern (`-27`, `-28`, …), damit auch große Jahrgänge eindeutige Kürzel erhalten.
+fn parallel_suffix(parallel: usize) -> String {
+  match u8::try_from(parallel) {
+    Ok(n) if n < 26 => ((b'a' + n) as char).to_string(),
+    _ => format!("-{}", parallel + 1),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::{HashMap, HashSet};
+
+  use super::*;
+
+  fn params(seed: u64) -> SyntheticParams {
+    SyntheticParams {
+      seed,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn seeded() {
+    let first = serde_json::to_string(&WilliStundenplan::synthetic(&params(3))).unwrap();
+    let second = serde_json::to_string(&WilliStundenplan::synthetic(&params(3))).unwrap();
+    let other = serde_json::to_string(&WilliStundenplan::synthetic(&params(4))).unwrap();
+
+    assert_eq!(first, second);
+    assert_ne!(first, other);
+  }
+
+  #[test]
+  fn no_double_booking() {
+    for seed in 0..4 {
+      let plan = WilliStundenplan::synthetic(&params(seed));
+      let mut classes = HashSet::new();
+      let mut teachers = HashSet::new();
+
+      for pl in &plan.stunden_lehrerplan {
+        let slot = (&pl.tag_stunde.tag, &pl.tag_stunde.stunde);
+        assert!(classes.insert((slot, &pl.klasse)), "{pl:?}");
+        assert!(teachers.insert((slot, &pl.lehrkraft)), "{pl:?}");
+      }
+    }
+  }
+
+  #[test]
+  fn stundenzahl_matches_placed_lessons() {
+    // Zu wenige Lehrkräfte, um alle Stunden der Stundentafel zu verplanen
+    let params = SyntheticParams {
+      klassen: 8,
+      lehrkraefte: 4,
+      dichte: 1.0,
+      ..Default::default()
+    };
+    let plan = WilliStundenplan::synthetic(&params);
+
+    let mut placed: HashMap<_, usize> = HashMap::new();
+    for pl in &plan.stunden_lehrerplan {
+      *placed
+        .entry((&pl.lehrkraft, &pl.fach, &pl.klasse))
+        .or_default() += 1;
+    }
+
+    let einheiten: Vec<_> = plan.unterrichtseinheiten.iter().collect();
+    assert_eq!(einheiten.len(), placed.len());
+    for (_, u) in einheiten {
+      assert_eq!(
+        usize::from(u.stundenzahl),
+        placed[&(&u.lehrkraft, &u.fach, &u.klasse)],
+        "{u:?}"
+      );
+    }
+    // Die Stundentafel sieht hier 8 Klassen × 40 Stunden vor
+    assert!(plan.stunden_lehrerplan.len() < 8 * 40);
+  }
+
+  /// Jede Stunde eines geteilten Fachs hat zwei Einträge mit verschiedenen Lehrkräften, die
+  /// Stundenzahlen stimmen weiterhin
+  #[test]
+  fn geteilt() {
+    let mut params = params(2);
+    params.faecher[0].geteilt = true;
+    let plan = WilliStundenplan::synthetic(&params);
+
+    let mut groups: HashMap<_, Vec<_>> = HashMap::new();
+    for pl in &plan.stunden_lehrerplan {
+      let slot = (&pl.tag_stunde.tag, &pl.tag_stunde.stunde);
+      groups.entry((slot, &pl.klasse)).or_default().push(pl);
+    }
+    for lines in groups.values() {
+      if lines[0].fach == FachId(1) {
+        assert_eq!(lines.len(), 2, "{lines:?}");
+        assert_eq!(lines[1].fach, lines[0].fach);
+        assert_ne!(lines[1].lehrkraft, lines[0].lehrkraft);
+      } else {
+        assert_eq!(lines.len(), 1, "{lines:?}");
+      }
+    }
+
+    for (_, u) in plan.unterrichtseinheiten.iter() {
+      let placed = plan
+        .stunden_lehrerplan
+        .iter()
+        .filter(|pl| (pl.lehrkraft, pl.fach, pl.klasse) == (u.lehrkraft, u.fach, u.klasse))
+        .count();
+      assert_eq!(usize::from(u.stundenzahl), placed, "{u:?}");
+    }
+  }
+
+  #[test]
+  fn many_parallel_classes() {
+    let plan = WilliStundenplan::synthetic(&SyntheticParams {
+      klassen: 400,
+      ..Default::default()
+    });
+
+    let kuerzel: HashSet<_> = plan.klassen.iter().map(|(_, k)| &k.kuerzel[..]).collect();
+    assert_eq!(kuerzel.len(), 400);
+    assert!(kuerzel.contains("5a") && kuerzel.contains("5z") && kuerzel.contains("5-27"));
+  }
+}
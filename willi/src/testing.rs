@@ -0,0 +1,39 @@
+//! Gemeinsame Testdaten für die Unit-Tests des Crates.
+
+use crate::WilliStundenplan;
+
+/// Kleiner Stundenplan im BAL-Format: zwei Tage mit je drei Stunden, zwei Klassen, drei
+/// Lehrkräfte und zwei Fächer. Die IDs sind bewusst nicht lückenlos (`K1`, `K3`).
+pub(crate) const BAL: &str = concat!(
+  "WILLI2 Stundenplan Export Version: 5\r\n",
+  "W,Testschule\r\n",
+  "F1,M,M,Mathematik\r\n",
+  "F2,D,D,Deutsch\r\n",
+  "K1,5a,5a,Klasse 5a,,,,,,,5,,,,,,,,,,,,,\r\n",
+  "K3,6b,6b,Klasse 6b,,,,,,,6,,,,,,,,,,,,,\r\n",
+  "T1,Mo,Montag,,\r\n",
+  "T2,Di,Dienstag,,\r\n",
+  "S1,1,1. Stunde,08:00,08:45\r\n",
+  "S2,2,2. Stunde,08:50,09:35\r\n",
+  "S3,3,3. Stunde,09:55,10:40\r\n",
+  "L1,MUE,Mü,Müller,Anna,Frau\r\n",
+  "L2,SCH,Sc,Schmidt,Bernd,Herr\r\n",
+  "L4,WEB,We,Weber,Clara,Frau\r\n",
+  "U1,MUE,M,5a,,2\r\n",
+  "U2,SCH,D,5a,,1\r\n",
+  "U3,WEB,M,6b,,2\r\n",
+  "PL1,Mo 1,MUE,5a,M\r\n",
+  "PL2,Mo 2,SCH,5a,D\r\n",
+  "PL3,Di 3,MUE,5a,M\r\n",
+  "PL4,Mo 1,WEB,6b,M\r\n",
+);
+
+/// [`BAL`] geparst; schlägt fehl, falls dabei Fehler auftreten.
+pub(crate) fn plan() -> WilliStundenplan {
+  let (plan, errors) = WilliStundenplan::parse(BAL);
+  assert!(
+    errors.is_empty(),
+    "Fehler beim Parsen der Testdaten: {errors:?}"
+  );
+  plan
+}